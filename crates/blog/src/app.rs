@@ -7,11 +7,20 @@ use leptos_router::{
 use reactive_stores::Store;
 
 use crate::pages::{
-    admin::AdminPage, blog_post::BlogPostPage, edit_blog_post::EditBlogPostPage, home::HomePage,
-    loading::LoadingPage, p404::Page404, rss::RSSPage,
+    admin::AdminPage,
+    blog_post::BlogPostPage,
+    edit_blog_post::EditBlogPostPage,
+    home::HomePage,
+    loading::LoadingPage,
+    notifications::{NotificationsPage, UnsubscribePage},
+    p404::Page404,
+    rss::RSSPage,
 };
 use common::models::*;
-use common::ui::CookiePopup;
+use common::{
+    state::{GlobalState, GlobalStateStoreFields, get_user},
+    ui::CookiePopup,
+};
 
 pub fn shell(options: LeptosOptions) -> impl IntoView {
     view! {
@@ -31,25 +40,6 @@ pub fn shell(options: LeptosOptions) -> impl IntoView {
     }
 }
 
-#[server(UserAction, "/api", "GetJson", endpoint = "profile")]
-#[tracing::instrument]
-pub async fn get_user() -> Result<Option<Profile>, ServerFnError> {
-    use axum::extract::Extension;
-    use leptos_axum::extract;
-
-    let Some(user) = extract::<Extension<User>>().await.ok() else {
-        return Ok(None);
-    };
-
-    Ok(Some(user.profile()))
-}
-
-#[derive(Clone, Debug, Default, Store)]
-pub struct GlobalState {
-    logged_in: bool,
-    user: Option<Profile>,
-}
-
 #[component]
 pub fn App() -> impl IntoView {
     let store = Store::new(GlobalState::default());
@@ -89,6 +79,11 @@ pub fn App() -> impl IntoView {
                                             <Route path=path!("") view=HomePage />
                                             <Route path=path!("posts/:slug") view=BlogPostPage />
                                             <Route path=path!("feed") view=RSSPage />
+                                            <Route
+                                                path=path!("notifications")
+                                                view=NotificationsPage
+                                            />
+                                            <Route path=path!("unsubscribe") view=UnsubscribePage />
 
                                             <ParentRoute
                                                 path=path!("admin")
@@ -1,9 +1,106 @@
+use chrono::{Duration, Utc};
 use leptos::{prelude::*, task::spawn_local};
 use reactive_stores::Store;
 
-use crate::app::{GlobalState, GlobalStateStoreFields};
+use crate::markdown::comment_markdown_to_html;
 
-use common::models::*;
+use common::{
+    models::*,
+    state::{GlobalState, GlobalStateStoreFields},
+};
+
+/// How long after posting authors can still edit their comment.
+pub const COMMENT_EDIT_MINUTES: i64 = 15;
+
+/// The emojis a comment can be reacted with.
+pub const REACTIONS: [&str; 6] = ["👍", "❤️", "😂", "🎉", "😮", "😢"];
+
+#[cfg(feature = "back")]
+async fn attach_reactions(
+    comments: &mut [Comment],
+    user_id: Option<i32>,
+) -> Result<(), common::db::sqlx::Error> {
+    let ids = comments.iter().map(|c| c.id).collect::<Vec<_>>();
+
+    let reactions = common::db_query_as!(
+        ReactionCount,
+        fetch_all,
+        r#"
+        SELECT
+            comment_id,
+            emoji,
+            COUNT(*) AS count,
+            COALESCE(BOOL_OR(user_id = $2), false) AS reacted
+        FROM comment_reactions
+        WHERE comment_id = ANY($1)
+        GROUP BY comment_id, emoji
+        "#,
+        ids,
+        user_id
+    )?;
+
+    for comment in comments.iter_mut() {
+        comment.reactions = reactions
+            .iter()
+            .filter(|r| r.comment_id == comment.id)
+            .cloned()
+            .collect();
+    }
+
+    Ok(())
+}
+
+#[server(GetCommentsAction, "/api", "GetJson", endpoint = "comments")]
+#[tracing::instrument]
+pub async fn get_comments(post_id: i32) -> Result<Vec<Comment>, ServerFnError> {
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    // authors still see their own comments while they wait for approval
+    let user_id = extract::<Extension<User>>().await.ok().map(|u| u.id);
+
+    let mut comments = common::db_query_as!(
+        Comment,
+        fetch_all,
+        r#"
+        SELECT 
+            comments.id,
+            users.name AS author_name,
+            comments.author AS author_id,
+            comments.content,
+            comments.replying_to,
+            comments.status,
+            comments.created_at,
+            comments.updated_at
+        FROM comments
+        JOIN posts ON comments.post = posts.id
+        LEFT JOIN users ON comments.author = users.id
+        WHERE comments.post = $1
+        AND (
+            comments.status = 'approved'
+            OR (comments.author = $2 AND comments.status <> 'rejected')
+        )
+        ORDER BY comments.created_at DESC
+        "#,
+        post_id,
+        user_id
+    )
+    .map_err(|e| {
+        let err = format!("Error while getting comments: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not retrieve comments, try again later")
+    })?;
+
+    attach_reactions(&mut comments, user_id)
+        .await
+        .map_err(|e| {
+            let err = format!("Error while getting reactions: {e:?}");
+            tracing::error!("{err}");
+            ServerFnError::new("Could not retrieve comments, try again later")
+        })?;
+
+    Ok(comments)
+}
 
 #[server(CommentAction, "/api", endpoint = "comment")]
 #[tracing::instrument]
@@ -15,20 +112,40 @@ pub async fn comment(comment: NewComment, post_id: i32) -> Result<Vec<Comment>,
         .await
         .map_err(|_| ServerFnError::new("Unauthorized."))?;
 
-    common::db_query!(
-        execute,
+    // stored as written, the markdown is sanitized when rendered
+    let content = comment.content.trim().to_string();
+
+    if content.is_empty() {
+        return Err(ServerFnError::new("Comment cannot be empty."));
+    }
+
+    let (status, spam_score) = crate::moderation::moderate(&user, &content)
+        .await
+        .map_err(|e| {
+            let err = format!("Error while moderating comment: {e:?}");
+            tracing::error!("{err}");
+            ServerFnError::new("Could not create comment.")
+        })?;
+
+    // replies only count within the same post
+    let comment_id = common::db_query_scalar!(
+        i32,
+        fetch_one,
         r#"
         INSERT INTO comments (
-            author, post, content, replying_to
+            author, post, content, replying_to, status, spam_score
         )
         VALUES (
-            $1, $2, $3, $4
+            $1, $2, $3, (SELECT id FROM comments WHERE id = $4 AND post = $2), $5, $6
         )
+        RETURNING id
         "#,
         user.id,
         post_id,
-        ammonia::clean(&comment.content),
-        None::<i32>,
+        &content,
+        comment.replying_to,
+        status,
+        spam_score,
     )
     .map_err(|e| {
         let err = format!("Error while creating comment: {e:?}");
@@ -36,32 +153,224 @@ pub async fn comment(comment: NewComment, post_id: i32) -> Result<Vec<Comment>,
         ServerFnError::new("Could not create comment.")
     })?;
 
-    common::db_query_as!(
+    if status == CommentStatus::Approved
+        && let Err(e) = crate::notifications::comment_approved(comment_id).await
+    {
+        tracing::error!("Error while queueing notifications: {e:?}");
+    }
+
+    get_comments(post_id).await
+}
+
+#[server(EditCommentAction, "/api", endpoint = "edit_comment")]
+#[tracing::instrument]
+pub async fn edit_comment(comment_id: i32, content: String) -> Result<Comment, ServerFnError> {
+    use crate::moderation::{SPAM_THRESHOLD, SpamFilter};
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    let Extension(user) = extract::<Extension<User>>()
+        .await
+        .map_err(|_| ServerFnError::new("Unauthorized."))?;
+
+    let content = content.trim().to_string();
+
+    if content.is_empty() {
+        return Err(ServerFnError::new("Comment cannot be empty."));
+    }
+
+    let Some((old_content, old_status, trained_as)) = common::db_query_as!(
+        (String, CommentStatus, Option<CommentStatus>),
+        fetch_optional,
+        r#"
+        SELECT content, status, trained_as FROM comments
+        WHERE id = $1
+        AND author = $2
+        AND status <> 'rejected'
+        AND created_at > NOW() - make_interval(mins => $3)
+        "#,
+        comment_id,
+        user.id,
+        COMMENT_EDIT_MINUTES as i32,
+    )
+    .map_err(|e| {
+        let err = format!("Error while editing comment: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not edit comment.")
+    })?
+    else {
+        return Err(ServerFnError::new("This comment can no longer be edited."));
+    };
+
+    // edits can only make things worse, a spam comment does not get approved by editing it
+    let spam_score = SpamFilter::for_content(&content).await.score(&content);
+    let status = if !user.admin && spam_score >= SPAM_THRESHOLD {
+        CommentStatus::Spam
+    } else {
+        old_status
+    };
+
+    // everything checked above is checked again, the comment could have changed in between
+    let Some(mut comment) = common::db_query_as!(
         Comment,
-        fetch_all,
+        fetch_optional,
         r#"
-        SELECT 
-            comments.id,
+        WITH updated AS (
+            UPDATE comments
+            SET content = $3, status = $4, spam_score = $5, trained_as = NULL,
+                updated_at = CURRENT_TIMESTAMP
+            WHERE id = $1
+            AND author = $6
+            AND content = $2
+            AND status = $7
+            AND created_at > NOW() - make_interval(mins => $8)
+            RETURNING *
+        ), revision AS (
+            INSERT INTO comment_revisions (comment_id, content)
+            SELECT id, $2 FROM updated
+        )
+        SELECT
+            updated.id,
             users.name AS author_name,
-            comments.author AS author_id,
-            comments.content,
-            comments.replying_to,
-            comments.created_at
-        FROM comments
-        JOIN posts ON comments.post = posts.id
-        LEFT JOIN users ON comments.author = users.id
-        WHERE comments.post = $1
-        ORDER BY comments.created_at DESC
-    "#,
-        post_id
+            updated.author AS author_id,
+            updated.content,
+            updated.replying_to,
+            updated.status,
+            updated.created_at,
+            updated.updated_at
+        FROM updated
+        JOIN users ON users.id = updated.author
+        "#,
+        comment_id,
+        &old_content,
+        &content,
+        status,
+        spam_score,
+        user.id,
+        old_status,
+        COMMENT_EDIT_MINUTES as i32,
     )
     .map_err(|e| {
-        let err = format!("Error while creating comment: {e:?}");
+        let err = format!("Error while editing comment: {e:?}");
         tracing::error!("{err}");
-        ServerFnError::new("Could not create comment.")
+        ServerFnError::new("Could not edit comment.")
+    })?
+    else {
+        return Err(ServerFnError::new("This comment can no longer be edited."));
+    };
+
+    // the new content was not looked at by an admin, so it is not learned
+    if let Some(trained_as) = trained_as
+        && let Err(e) = crate::moderation::forget(&old_content, trained_as).await
+    {
+        tracing::error!("Error while training spam filter: {e:?}");
+    }
+
+    attach_reactions(std::slice::from_mut(&mut comment), Some(user.id))
+        .await
+        .map_err(|e| {
+            let err = format!("Error while getting reactions: {e:?}");
+            tracing::error!("{err}");
+            ServerFnError::new("Could not edit comment.")
+        })?;
+
+    Ok(comment)
+}
+
+#[server(CommentHistoryAction, "/api", "GetJson", endpoint = "comment_history")]
+#[tracing::instrument]
+pub async fn get_comment_history(comment_id: i32) -> Result<Vec<CommentRevision>, ServerFnError> {
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    let user_id = extract::<Extension<User>>().await.ok().map(|u| u.id);
+
+    common::db_query_as!(
+        CommentRevision,
+        fetch_all,
+        r#"
+        SELECT comment_revisions.*
+        FROM comment_revisions
+        JOIN comments ON comment_revisions.comment_id = comments.id
+        WHERE comment_revisions.comment_id = $1
+        AND (comments.status = 'approved' OR comments.author = $2)
+        ORDER BY comment_revisions.edited_at DESC
+        "#,
+        comment_id,
+        user_id
+    )
+    .map_err(|e| {
+        let err = format!("Error while getting comment history: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not retrieve comment history.")
     })
 }
 
+#[server(ToggleReactionAction, "/api", endpoint = "toggle_reaction")]
+#[tracing::instrument]
+pub async fn toggle_reaction(
+    comment_id: i32,
+    emoji: String,
+) -> Result<Vec<ReactionCount>, ServerFnError> {
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    let Extension(user) = extract::<Extension<User>>()
+        .await
+        .map_err(|_| ServerFnError::new("Unauthorized."))?;
+
+    if !REACTIONS.contains(&emoji.as_str()) {
+        return Err(ServerFnError::new("Unknown reaction."));
+    }
+
+    let removed = common::db_query!(
+        execute,
+        "DELETE FROM comment_reactions WHERE comment_id = $1 AND user_id = $2 AND emoji = $3",
+        comment_id,
+        user.id,
+        &emoji,
+    )
+    .map(|r| r.rows_affected())
+    .map_err(|e| {
+        let err = format!("Error while removing reaction from comment: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not react to comment.")
+    })?;
+
+    if removed == 0 {
+        common::db_query!(
+            execute,
+            r#"
+            INSERT INTO comment_reactions (comment_id, user_id, emoji)
+            SELECT id, $2, $3 FROM comments WHERE id = $1 AND status = 'approved'
+            "#,
+            comment_id,
+            user.id,
+            &emoji,
+        )
+        .map_err(|e| {
+            let err = format!("Error while reacting to comment: {e:?}");
+            tracing::error!("{err}");
+            ServerFnError::new("Could not react to comment.")
+        })?;
+    }
+
+    let mut comment = Comment {
+        id: comment_id,
+        ..Default::default()
+    };
+
+    attach_reactions(std::slice::from_mut(&mut comment), Some(user.id))
+        .await
+        .map_err(|e| {
+            let err = format!("Error while getting reactions: {e:?}");
+            tracing::error!("{err}");
+            ServerFnError::new("Could not react to comment.")
+        })?;
+
+    Ok(comment.reactions)
+}
+
 #[server(DeleteCommentAction, "/api", endpoint = "delete_comment")]
 #[tracing::instrument]
 pub async fn delete_comment(comment: Comment) -> Result<u64, ServerFnError> {
@@ -93,9 +402,26 @@ pub fn CommentComponent(
     #[prop(into)] comment: Signal<Comment>,
     #[prop(into)] comments: RwSignal<Vec<Comment>>,
     #[prop(into)] delete_btn: bool,
+    #[prop(into)] reply_to: RwSignal<Option<Comment>>,
 ) -> impl IntoView {
     let icon = icondata::IoPersonCircleOutline;
     let delete_icon = icondata::IoTrashBin;
+    let store = expect_context::<Store<GlobalState>>();
+
+    // edits and reactions only ever touch this one comment
+    let current = RwSignal::new(comment.get_untracked());
+    let editing = RwSignal::new(false);
+    let draft = RwSignal::new(String::new());
+    let history = RwSignal::new(None::<Vec<CommentRevision>>);
+
+    let can_edit = move || {
+        let c = current.get();
+        store
+            .user()
+            .get()
+            .is_some_and(|u| c.author_id == Some(u.id))
+            && Utc::now().naive_utc() - c.created_at < Duration::minutes(COMMENT_EDIT_MINUTES)
+    };
 
     view! {
         <article class="p-6 text-base bg-nf-white rounded-lg">
@@ -117,54 +443,231 @@ pub fn CommentComponent(
                         ></svg>
                     </p>
                     <div class=move || {
-                        comment.get().author_id.map(|_| "mr-6").unwrap_or("text-red-500 mr-6")
-                    }>{comment.get().author_name.unwrap_or("DELETED USER".into())}</div>
+                        current.get().author_id.map(|_| "mr-6").unwrap_or("text-red-500 mr-6")
+                    }>{current.get().author_name.unwrap_or("DELETED USER".into())}</div>
                     <p class="text-sm text-gray-600">
                         <time
                             prop:pubdate
-                            datetime=comment.get().created_at.format("%Y-%m-%d").to_string()
+                            datetime=current.get().created_at.format("%Y-%m-%d").to_string()
                         >
-                            {comment.get().created_at.format("%b. %d, %Y").to_string()}
+                            {current.get().created_at.format("%b. %d, %Y").to_string()}
                         </time>
                     </p>
-                </div>
-                // delete icon
-                <Show when=move || delete_btn>
-                    <div class="right-0 top-0">
-                        <button on:click=move |_| {
-                            spawn_local(async move {
-                                let comment = comment.get();
-                                if delete_comment(comment.clone()).await.is_ok() {
-                                    comments
-                                        .set(
-                                            comments
-                                                .get()
-                                                .into_iter()
-                                                .filter(|c| c.id != comment.id)
-                                                .collect(),
-                                        )
+                    <Show when=move || current.get().updated_at.is_some()>
+                        <button
+                            class="ml-6 text-sm italic text-gray-600 hover:underline"
+                            on:click=move |_| {
+                                if history.get().is_some() {
+                                    history.set(None);
+                                    return;
+                                }
+                                spawn_local(async move {
+                                    if let Ok(h) = get_comment_history(current.get_untracked().id)
+                                        .await
+                                    {
+                                        history.set(Some(h));
+                                    }
+                                })
+                            }
+                        >
+                            "(edited)"
+                        </button>
+                    </Show>
+                    <Show when=move || current.get().status != CommentStatus::Approved>
+                        <p class="ml-6 text-sm italic text-gray-600">
+                            "Awaiting approval"
+                        </p>
+                    </Show>
+                    {move || {
+                        current
+                            .get()
+                            .replying_to
+                            .map(|id| {
+                                let parent = comments
+                                    .get()
+                                    .into_iter()
+                                    .find(|c| c.id == id)
+                                    .and_then(|c| c.author_name)
+                                    .unwrap_or("a comment".into());
+                                view! {
+                                    <p class="ml-6 text-sm text-gray-600">
+                                        {format!("in reply to {parent}")}
+                                    </p>
                                 }
                             })
-                        }>
-                            <svg
-                                x=delete_icon.x
-                                y=delete_icon.y
-                                width=32
-                                height=32
-                                viewBox=delete_icon.view_box
-                                stroke-linecap=delete_icon.stroke_linecap
-                                stroke-linejoin=delete_icon.stroke_linejoin
-                                stroke-width=delete_icon.stroke_width
-                                stroke=delete_icon.stroke
-                                fill="red"
-                                inner_html=delete_icon.data
-                            ></svg>
+                    }}
+                </div>
+                <div class="flex items-center gap-4">
+                    <Show when=move || {
+                        store.user().get().is_some()
+                            && current.get().status == CommentStatus::Approved
+                    }>
+                        <button
+                            class="text-sm text-gray-600 hover:underline"
+                            on:click=move |_| reply_to.set(Some(current.get_untracked()))
+                        >
+                            Reply
                         </button>
-                    </div>
-                </Show>
+                    </Show>
+                    <Show when=move || can_edit() && !editing.get()>
+                        <button
+                            class="text-sm text-gray-600 hover:underline"
+                            on:click=move |_| {
+                                draft.set(current.get_untracked().content);
+                                editing.set(true);
+                            }
+                        >
+                            Edit
+                        </button>
+                    </Show>
+                    // delete icon
+                    <Show when=move || delete_btn>
+                        <div class="right-0 top-0">
+                            <button on:click=move |_| {
+                                spawn_local(async move {
+                                    let comment = current.get();
+                                    if delete_comment(comment.clone()).await.is_ok() {
+                                        comments
+                                            .set(
+                                                comments
+                                                    .get()
+                                                    .into_iter()
+                                                    .filter(|c| c.id != comment.id)
+                                                    .collect(),
+                                            )
+                                    }
+                                })
+                            }>
+                                <svg
+                                    x=delete_icon.x
+                                    y=delete_icon.y
+                                    width=32
+                                    height=32
+                                    viewBox=delete_icon.view_box
+                                    stroke-linecap=delete_icon.stroke_linecap
+                                    stroke-linejoin=delete_icon.stroke_linejoin
+                                    stroke-width=delete_icon.stroke_width
+                                    stroke=delete_icon.stroke
+                                    fill="red"
+                                    inner_html=delete_icon.data
+                                ></svg>
+                            </button>
+                        </div>
+                    </Show>
+                </div>
 
             </footer>
-            <p class="text-nf-dark">{comment.get().content}</p>
+            <Show
+                when=move || editing.get()
+                fallback=move || {
+                    view! {
+                        <div
+                            class="markdown text-nf-dark"
+                            inner_html=move || comment_markdown_to_html(&current.get().content)
+                        ></div>
+                    }
+                }
+            >
+                <textarea
+                    rows="4"
+                    class="px-0 w-full text-sm text-nf-dark border-0 focus:ring-0 focus:outline-none bg-nf-white"
+                    prop:value=move || draft.get()
+                    on:input=move |ev| draft.set(event_target_value(&ev))
+                ></textarea>
+                <div class="flex gap-2">
+                    <button
+                        class="inline-flex items-center py-2.5 px-4 text-xs font-medium text-center text-nf-white bg-nf-color rounded-lg"
+                        on:click=move |_| {
+                            spawn_local(async move {
+                                if let Ok(c) = edit_comment(
+                                        current.get_untracked().id,
+                                        draft.get_untracked(),
+                                    )
+                                    .await
+                                {
+                                    current.set(c);
+                                    history.set(None);
+                                    editing.set(false);
+                                }
+                            });
+                        }
+                    >
+                        Save
+                    </button>
+                    <button
+                        class="inline-flex items-center py-2.5 px-4 text-xs font-medium text-center text-nf-dark bg-gray-100 rounded-lg"
+                        on:click=move |_| editing.set(false)
+                    >
+                        Cancel
+                    </button>
+                </div>
+            </Show>
+            // previous versions, newest first
+            {move || {
+                history
+                    .get()
+                    .map(|revisions| {
+                        view! {
+                            <ul class="mt-4 border-l-2 border-gray-200 pl-4 list-none">
+                                {revisions
+                                    .into_iter()
+                                    .map(|r| {
+                                        view! {
+                                            <li class="mb-2">
+                                                <p class="text-xs text-gray-600">
+                                                    {r.edited_at.format("%b. %d, %Y %H:%M").to_string()}
+                                                </p>
+                                                <div
+                                                    class="markdown text-sm text-gray-600"
+                                                    inner_html=comment_markdown_to_html(&r.content)
+                                                ></div>
+                                            </li>
+                                        }
+                                    })
+                                    .collect_view()}
+                            </ul>
+                        }
+                    })
+            }}
+            <div class="flex flex-wrap gap-2 mt-4">
+                <For
+                    each=move || REACTIONS
+                    key=|emoji| *emoji
+                    children=move |emoji| {
+                        let reaction = move || {
+                            current.get().reactions.into_iter().find(|r| r.emoji == emoji)
+                        };
+                        view! {
+                            <button
+                                class=move || {
+                                    if reaction().is_some_and(|r| r.reacted) {
+                                        "rounded-full px-3 py-1 text-sm bg-nf-color text-nf-white"
+                                    } else {
+                                        "rounded-full px-3 py-1 text-sm bg-gray-100 text-nf-dark"
+                                    }
+                                }
+                                disabled=move || store.user().get().is_none()
+                                on:click=move |_| {
+                                    spawn_local(async move {
+                                        if let Ok(reactions) = toggle_reaction(
+                                                current.get_untracked().id,
+                                                emoji.to_string(),
+                                            )
+                                            .await
+                                        {
+                                            current.update(|c| c.reactions = reactions);
+                                        }
+                                    });
+                                }
+                            >
+                                {emoji}
+                                " "
+                                {move || reaction().map(|r| r.count.to_string()).unwrap_or_default()}
+                            </button>
+                        }
+                    }
+                />
+            </div>
         </article>
     }
 }
@@ -174,10 +677,11 @@ pub fn CommentSection(
     #[prop(into)] comments: Vec<Comment>,
     #[prop(into)] blog_post_id: Signal<i32>,
 ) -> impl IntoView {
-    let (new_comment, set_new_comment) = signal(Default::default());
+    let (new_comment, set_new_comment) = signal(NewComment::default());
     let store = expect_context::<Store<GlobalState>>();
 
     let comments = RwSignal::new(comments);
+    let reply_to = RwSignal::new(None::<Comment>);
 
     view! {
         <div class="bg-nf-dark p-4 pb-12">
@@ -194,6 +698,26 @@ pub fn CommentSection(
                     view! {
                         // textarea for new comment
                         <div class="mb-6 min-w-full px-1 md:px-32 lg:px-48">
+                            {move || {
+                                reply_to
+                                    .get()
+                                    .map(|c| {
+                                        view! {
+                                            <p class="mx-4 text-sm text-nf-white">
+                                                {format!(
+                                                    "Replying to {} ",
+                                                    c.author_name.unwrap_or("DELETED USER".into()),
+                                                )}
+                                                <button
+                                                    class="underline text-nf-color"
+                                                    on:click=move |_| reply_to.set(None)
+                                                >
+                                                    cancel
+                                                </button>
+                                            </p>
+                                        }
+                                    })
+                            }}
                             <div class="py-2 px-4 m-4 bg-nf-white rounded-lg">
                                 <label for="comment" class="sr-only">
                                     Your comment
@@ -201,11 +725,14 @@ pub fn CommentSection(
                                 <textarea
                                     rows="6"
                                     class="px-0 w-full text-sm text-nf-dark border-0 focus:ring-0 focus:outline-none placeholder-gray-900 bg-nf-white"
-                                    placeholder="Write a (plaintext) comment..."
+                                    placeholder="Write a comment, **markdown** is supported..."
                                     required
                                     on:input=move |ev| {
                                         let new_value = event_target_value(&ev);
-                                        set_new_comment(NewComment { content: new_value });
+                                        set_new_comment(NewComment {
+                                            content: new_value,
+                                            replying_to: None,
+                                        });
                                     }
                                 ></textarea>
                             </div>
@@ -214,19 +741,27 @@ pub fn CommentSection(
                                 class="ml-4 inline-flex items-center py-2.5 px-4 text-xs font-medium text-center text-nf-white bg-nf-color rounded-lg focus:ring-4 focus:ring-primary-200"
                                 on:click=move |_| {
                                     spawn_local(async move {
-                                        if let Ok(c) = comment(
-                                                new_comment.get(),
-                                                blog_post_id.get(),
-                                            )
+                                        let new_comment = NewComment {
+                                            replying_to: reply_to.get_untracked().map(|c| c.id),
+                                            ..new_comment.get_untracked()
+                                        };
+                                        if let Ok(c) = comment(new_comment, blog_post_id.get())
                                             .await
                                         {
                                             comments.set(c);
+                                            reply_to.set(None);
                                         }
                                     });
                                 }
                             >
                                 Post comment
                             </button>
+                            <a
+                                href="/notifications"
+                                class="ml-4 text-xs underline text-nf-white hover:text-nf-color"
+                            >
+                                Notification settings
+                            </a>
                         </div>
                     }
                         .into_any()
@@ -269,7 +804,7 @@ pub fn CommentSection(
                                         });
                                     view! {
                                         <li class="px-4 md:px-32 lg:px-48 min-w-full">
-                                            <CommentComponent comment comments delete_btn />
+                                            <CommentComponent comment comments delete_btn reply_to />
                                         </li>
                                     }
                                 }
@@ -2,8 +2,10 @@ use leptos::{prelude::*, task::spawn_local};
 use leptos_router::{components::A, hooks::use_location};
 use reactive_stores::Store;
 
-use crate::app::{GlobalState, GlobalStateStoreFields};
-use common::Apps;
+use common::{
+    Apps,
+    state::{GlobalState, GlobalStateStoreFields},
+};
 
 #[component]
 pub fn Header() -> impl IntoView {
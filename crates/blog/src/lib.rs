@@ -1,5 +1,10 @@
 pub mod app;
 pub mod components;
+pub mod markdown;
+#[cfg(feature = "back")]
+pub mod moderation;
+#[cfg(feature = "back")]
+pub mod notifications;
 pub mod pages;
 
 pub const THEME_STR: &str = include_str!("peel-light.tmTheme");
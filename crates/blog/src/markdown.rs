@@ -0,0 +1,325 @@
+use flate2::Compression;
+use flate2::write::DeflateEncoder;
+use pulldown_cmark::*;
+use std::collections::HashMap;
+use std::io::{Cursor, Write};
+use syntect::{highlighting::ThemeSet, html::highlighted_html_for_string, parsing::SyntaxSet};
+
+use crate::THEME_STR;
+use common::{apps::Apps, models::sandbox_embed};
+
+/// Widths offered in `srcset`, the files app renders these without rounding.
+const IMAGE_WIDTHS: [u32; 4] = [480, 960, 1280, 1920];
+/// Widest the content of a post gets, see `BlogPost`.
+const IMAGE_SIZES: &str = "(min-width: 48rem) 48rem, 100vw";
+
+/// Renders a post, `image_widths` are the widths of its images as found by [`image_urls`].
+pub fn markdown_to_html(markdown: &str, image_widths: &HashMap<String, u32>) -> String {
+    let parser = pulldown_cmark::Parser::new_ext(markdown, pulldown_cmark::Options::all());
+    let events = add_markdown_heading_ids(parser.into_iter().collect());
+    let events = highlight_code(events);
+    let events = add_image_srcset(events, image_widths);
+    let mut html_output = String::new();
+    pulldown_cmark::html::push_html(&mut html_output, events.into_iter());
+
+    html_output
+}
+
+/// Renders the restricted markdown subset allowed in comments.
+///
+/// Goes through the same code highlighting as posts, but headings become paragraphs,
+/// images and raw html are reduced to text, diagrams and embeds stay code blocks and the
+/// result is sanitized with ammonia.
+pub fn comment_markdown_to_html(markdown: &str) -> String {
+    let parser = pulldown_cmark::Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH);
+    let events = restrict_comment_events(parser.into_iter().collect());
+    let events = highlight_code(events);
+    let mut html_output = String::new();
+    pulldown_cmark::html::push_html(&mut html_output, events.into_iter());
+
+    ammonia::Builder::empty()
+        .add_tags([
+            "p",
+            "br",
+            "em",
+            "strong",
+            "del",
+            "code",
+            "pre",
+            "span",
+            "a",
+            "ul",
+            "ol",
+            "li",
+            "blockquote",
+        ])
+        .add_tag_attributes("a", ["href"])
+        .add_tag_attributes("pre", ["style"])
+        .add_tag_attributes("span", ["style"])
+        .add_url_schemes(["http", "https", "mailto"])
+        .link_rel(Some("nofollow noopener noreferrer"))
+        .clean(&html_output)
+        .to_string()
+}
+
+fn restrict_comment_events(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    events
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::Start(Tag::Heading { .. }) => Some(Event::Start(Tag::Paragraph)),
+            Event::End(TagEnd::Heading(_)) => Some(Event::End(TagEnd::Paragraph)),
+            // the alt text in between stays as plain text
+            Event::Start(Tag::Image { .. }) | Event::End(TagEnd::Image) => None,
+            Event::Html(html) | Event::InlineHtml(html) => Some(Event::Text(html)),
+            // diagrams and sandbox embeds would be removed by ammonia, so they show as code
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang)))
+                if matches!(lang.as_ref(), "plantuml" | "sandbox") =>
+            {
+                Some(Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(
+                    "txt".into(),
+                ))))
+            }
+            e => Some(e),
+        })
+        .collect()
+}
+
+/// Images hosted in the files app that can get a `srcset`.
+pub fn image_urls(markdown: &str) -> Vec<String> {
+    pulldown_cmark::Parser::new_ext(markdown, pulldown_cmark::Options::all())
+        .filter_map(|event| match event {
+            Event::Start(Tag::Image { dest_url, .. }) if resizable(&dest_url) => {
+                Some(dest_url.to_string())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn resizable(url: &str) -> bool {
+    url.starts_with(&format!("{}/f/", Apps::Files.url()))
+        && !url.contains('?')
+        && [".jpg", ".jpeg", ".png", ".webp"]
+            .iter()
+            .any(|ext| url.to_lowercase().ends_with(ext))
+}
+
+/// Images hosted in the files app with a known width get a `srcset` of AVIF variants,
+/// never wider than the image, with the original as fallback for browsers without AVIF.
+fn add_image_srcset<'a>(
+    events: Vec<Event<'a>>,
+    image_widths: &HashMap<String, u32>,
+) -> Vec<Event<'a>> {
+    let width = |url: &str| image_widths.get(url).copied().filter(|_| resizable(url));
+
+    // url, title and the alt text collected from the events in between
+    let mut image = None::<(String, String, String)>;
+    let mut out_events = Vec::new();
+
+    for event in events {
+        match event {
+            Event::Start(Tag::Image {
+                dest_url, title, ..
+            }) if width(&dest_url).is_some() => {
+                image = Some((dest_url.to_string(), title.to_string(), String::new()));
+            }
+            Event::End(TagEnd::Image) if image.is_some() => {
+                let (url, title, alt) = image.take().unwrap_or_default();
+                let original = width(&url).unwrap_or_default();
+                let srcset = IMAGE_WIDTHS
+                    .iter()
+                    .filter(|w| **w < original)
+                    .map(|w| format!("{url}?w={w}&fmt=avif {w}w"))
+                    // larger images stop at the widest of the widths
+                    .chain(
+                        (original <= IMAGE_WIDTHS[IMAGE_WIDTHS.len() - 1])
+                            .then(|| format!("{url}?fmt=avif {original}w")),
+                    )
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                out_events.push(Event::Html(CowStr::from(format!(
+                    "<picture><source type=\"image/avif\" srcset=\"{}\" sizes=\"{IMAGE_SIZES}\" /><img src=\"{}\" alt=\"{}\" title=\"{}\" loading=\"lazy\" /></picture>",
+                    escape_attribute(&srcset),
+                    escape_attribute(&url),
+                    escape_attribute(&alt),
+                    escape_attribute(&title),
+                ))));
+            }
+            Event::Text(text) | Event::Code(text) if image.is_some() => {
+                if let Some((_, _, alt)) = image.as_mut() {
+                    alt.push_str(&text);
+                }
+            }
+            // formatting inside the alt text is dropped, like the html renderer does
+            _ if image.is_some() => {}
+            e => out_events.push(e),
+        }
+    }
+
+    out_events
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn add_markdown_heading_ids(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut parsing_header = false;
+    let mut heading_id = String::new();
+    let mut events_to_return = Vec::new();
+
+    for event in events {
+        match event {
+            Event::Start(pulldown_cmark::Tag::Heading { .. }) => {
+                parsing_header = true;
+                heading_id.clear();
+            }
+            Event::End(pulldown_cmark::TagEnd::Heading { .. }) => {
+                parsing_header = false;
+                heading_id = heading_id.replace(" ", "_");
+
+                events_to_return.push(Event::Text(CowStr::from(" ")));
+                events_to_return.push(Event::Html(CowStr::from(format!(
+                    "<a href=\"#{heading_id}\" id=\"{heading_id}\"><span class=\"anchor-icon\">#</span></a>"
+                ))));
+            }
+            Event::Text(ref text) if parsing_header => {
+                heading_id.push_str(text);
+            }
+            _ => {}
+        }
+        events_to_return.push(event);
+    }
+
+    events_to_return
+}
+
+fn highlight_code(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut in_code_block = false;
+    let syntax_set = SyntaxSet::load_defaults_nonewlines();
+    let mut syntax = syntax_set.find_syntax_plain_text();
+
+    let theme = ThemeSet::load_from_reader(&mut Cursor::new(THEME_STR)).unwrap();
+
+    let mut to_highlight = String::new();
+    let mut out_events = Vec::new();
+
+    let mut plantuml = false;
+    let mut sandbox = false;
+
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                match kind {
+                    CodeBlockKind::Fenced(lang) => {
+                        plantuml = lang == "plantuml".into();
+                        sandbox = lang == "sandbox".into();
+                        syntax = syntax_set.find_syntax_by_token(&lang).unwrap_or(syntax);
+                    }
+                    CodeBlockKind::Indented => {
+                        plantuml = false;
+                        sandbox = false;
+                    }
+                }
+                in_code_block = true;
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if !in_code_block {
+                    panic!("this should never happen");
+                }
+
+                if plantuml {
+                    let diagram_url = generate_plantuml_diagram_url(&to_highlight);
+                    let img_tag = format!("<img src=\"{diagram_url}\" alt=\"PlantUML Diagram\" />");
+                    out_events.push(Event::Html(CowStr::from(img_tag)));
+                } else if sandbox {
+                    // the slug of a page from the sandbox gallery
+                    let slug = to_highlight.trim();
+                    out_events.push(Event::Html(CowStr::from(sandbox_embed(slug, slug))));
+                } else {
+                    // Regular code block, highlight syntax
+                    let html =
+                        highlighted_html_for_string(&to_highlight, &syntax_set, syntax, &theme)
+                            .unwrap();
+                    out_events.push(Event::Html(CowStr::from(html)));
+                }
+
+                to_highlight.clear();
+                in_code_block = false;
+            }
+            Event::Text(t) => {
+                if in_code_block {
+                    to_highlight.push_str(&t);
+                } else {
+                    out_events.push(Event::Text(t));
+                }
+            }
+            e => {
+                out_events.push(e);
+            }
+        }
+    }
+
+    out_events
+}
+
+fn generate_plantuml_diagram_url(plantuml_code: &str) -> String {
+    let encoded = encode64(&compress_data(plantuml_code));
+    let url = format!("http://www.plantuml.com/plantuml/png/{encoded}");
+    url
+}
+
+fn compress_data(data: &str) -> Vec<u8> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(data.as_bytes()).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn encode6bit(b: u8) -> char {
+    match b {
+        0..=9 => (b + 48) as char,        // '0'..'9'
+        10..=35 => (b - 10 + 65) as char, // 'A'..'Z'
+        36..=61 => (b - 36 + 97) as char, // 'a'..'z'
+        62 => '-',                        // '-'
+        63 => '_',                        // '_'
+        _ => '?',                         // Fallback (should not happen)
+    }
+}
+
+fn append3bytes(b1: u8, b2: u8, b3: u8) -> String {
+    let c1 = b1 >> 2;
+    let c2 = ((b1 & 0x3) << 4) | (b2 >> 4);
+    let c3 = ((b2 & 0xF) << 2) | (b3 >> 6);
+    let c4 = b3 & 0x3F;
+
+    let mut r = String::new();
+    r.push(encode6bit(c1 & 0x3F));
+    r.push(encode6bit(c2 & 0x3F));
+    r.push(encode6bit(c3 & 0x3F));
+    r.push(encode6bit(c4 & 0x3F));
+
+    r
+}
+
+fn encode64(c: &[u8]) -> String {
+    let mut str = String::new();
+    let len = c.len();
+
+    let mut i = 0;
+    while i < len {
+        if i + 2 == len {
+            str.push_str(&append3bytes(c[i], c[i + 1], 0));
+        } else if i + 1 == len {
+            str.push_str(&append3bytes(c[i], 0, 0));
+        } else {
+            str.push_str(&append3bytes(c[i], c[i + 1], c[i + 2]));
+        }
+        i += 3;
+    }
+    str
+}
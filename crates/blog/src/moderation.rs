@@ -0,0 +1,301 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::LazyLock,
+};
+
+use common::db::sqlx;
+use common::models::{CommentStatus, User};
+use regex::Regex;
+
+/// Comments scoring at or above this are filed as spam without ever showing up.
+pub const SPAM_THRESHOLD: f32 = 0.8;
+
+static LINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)(https?://|www\.)").unwrap());
+static TOKEN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[\w$€'-]{2,32}").unwrap());
+
+/// Scores a comment between `0.0` (ham) and `1.0` (spam).
+///
+/// Returning `None` means the scorer has no opinion on this comment,
+/// it is then left out of the combined score.
+pub trait SpamScorer: Send + Sync {
+    fn score(&self, content: &str) -> Option<f32>;
+}
+
+/// Flags comments that are mostly links, no opinion on comments without any.
+pub struct LinkCountScorer {
+    pub max_links: usize,
+}
+
+impl Default for LinkCountScorer {
+    fn default() -> Self {
+        Self { max_links: 5 }
+    }
+}
+
+impl SpamScorer for LinkCountScorer {
+    fn score(&self, content: &str) -> Option<f32> {
+        let links = LINK.find_iter(content).count();
+
+        (links > 0).then(|| (links as f32 / self.max_links.max(1) as f32).min(1.0))
+    }
+}
+
+/// Flags comments containing the usual spam vocabulary, no opinion on comments without any.
+pub struct KeywordScorer {
+    pub keywords: Vec<String>,
+    /// number of hits after which a comment counts as spam
+    pub max_hits: usize,
+}
+
+impl Default for KeywordScorer {
+    fn default() -> Self {
+        Self {
+            keywords: [
+                "viagra",
+                "casino",
+                "crypto",
+                "bitcoin",
+                "forex",
+                "loan",
+                "payday",
+                "escort",
+                "seo services",
+                "backlinks",
+                "buy now",
+                "click here",
+                "free money",
+                "make money",
+                "work from home",
+                "limited offer",
+            ]
+            .iter()
+            .map(|k| k.to_string())
+            .collect(),
+            max_hits: 3,
+        }
+    }
+}
+
+impl SpamScorer for KeywordScorer {
+    fn score(&self, content: &str) -> Option<f32> {
+        let content = content.to_lowercase();
+        let hits = self
+            .keywords
+            .iter()
+            .filter(|k| content.contains(k.as_str()))
+            .count();
+
+        (hits > 0).then(|| (hits as f32 / self.max_hits.max(1) as f32).min(1.0))
+    }
+}
+
+/// Naive bayes filter trained on moderation decisions, see [`retrain`].
+///
+/// Only the token counts relevant to a single comment are loaded,
+/// so create one per comment with [`BayesianScorer::load`].
+pub struct BayesianScorer {
+    tokens: HashMap<String, (i32, i32)>,
+    spam_total: i64,
+    ham_total: i64,
+}
+
+impl BayesianScorer {
+    /// how many of the most telling tokens are combined
+    const INTERESTING_TOKENS: usize = 15;
+    /// tokens seen fewer times than this are ignored
+    const MIN_OCCURRENCES: i32 = 2;
+
+    pub async fn load(content: &str) -> Result<Self, sqlx::Error> {
+        let tokens = tokenize(content).into_iter().collect::<Vec<_>>();
+
+        let tokens = common::db_query_as!(
+            (String, i32, i32),
+            fetch_all,
+            "SELECT token, spam_count, ham_count FROM spam_tokens WHERE token = ANY($1)",
+            tokens
+        )?
+        .into_iter()
+        .map(|(token, spam, ham)| (token, (spam, ham)))
+        .collect();
+
+        let (spam_total, ham_total) = common::db_query_as!(
+            (i64, i64),
+            fetch_one,
+            r#"
+            SELECT
+                COALESCE(SUM(spam_count), 0)::BIGINT,
+                COALESCE(SUM(ham_count), 0)::BIGINT
+            FROM spam_tokens
+            "#
+        )?;
+
+        Ok(Self {
+            tokens,
+            spam_total,
+            ham_total,
+        })
+    }
+}
+
+impl SpamScorer for BayesianScorer {
+    fn score(&self, content: &str) -> Option<f32> {
+        // nothing to compare against until both sides have been trained
+        if self.spam_total == 0 || self.ham_total == 0 {
+            return None;
+        }
+
+        let mut probabilities = tokenize(content)
+            .iter()
+            .filter_map(|t| self.tokens.get(t))
+            .filter(|(spam, ham)| spam + ham >= Self::MIN_OCCURRENCES)
+            .map(|&(spam, ham)| {
+                let spam = spam as f64 / self.spam_total as f64;
+                let ham = ham as f64 / self.ham_total as f64;
+                (spam / (spam + ham)).clamp(0.01, 0.99)
+            })
+            .collect::<Vec<_>>();
+
+        if probabilities.is_empty() {
+            return None;
+        }
+
+        probabilities.sort_by(|a, b| (b - 0.5).abs().total_cmp(&(a - 0.5).abs()));
+        probabilities.truncate(Self::INTERESTING_TOKENS);
+
+        // combined in log space to not underflow on long comments
+        let eta: f64 = probabilities.iter().map(|p| (1.0 - p).ln() - p.ln()).sum();
+
+        Some((1.0 / (1.0 + eta.exp())) as f32)
+    }
+}
+
+/// Combines the opinions of all its scorers as a noisy-OR.
+///
+/// Every scorer is taken as an independent chance of the comment being spam,
+/// so one confident scorer is enough and weak signals add up.
+#[derive(Default)]
+pub struct SpamFilter {
+    scorers: Vec<Box<dyn SpamScorer>>,
+}
+
+impl SpamFilter {
+    pub fn with(mut self, scorer: impl SpamScorer + 'static) -> Self {
+        self.scorers.push(Box::new(scorer));
+        self
+    }
+
+    /// The heuristics plus the bayes filter loaded for `content`.
+    pub async fn for_content(content: &str) -> Self {
+        let filter = Self::default()
+            .with(LinkCountScorer::default())
+            .with(KeywordScorer::default());
+
+        match BayesianScorer::load(content).await {
+            Ok(bayes) => filter.with(bayes),
+            Err(e) => {
+                tracing::error!("Could not load spam tokens: {e:?}");
+                filter
+            }
+        }
+    }
+
+    pub fn score(&self, content: &str) -> f32 {
+        1.0 - self
+            .scorers
+            .iter()
+            .filter_map(|s| s.score(content))
+            .map(|score| 1.0 - score.clamp(0.0, 1.0))
+            .product::<f32>()
+    }
+}
+
+/// Decides what happens to a new comment and returns its status and spam score.
+///
+/// Admins are always approved, everything above [`SPAM_THRESHOLD`] is spam
+/// and the first comment of every user has to be approved by hand.
+pub async fn moderate(user: &User, content: &str) -> Result<(CommentStatus, f32), sqlx::Error> {
+    let score = SpamFilter::for_content(content).await.score(content);
+
+    if user.admin {
+        return Ok((CommentStatus::Approved, score));
+    }
+
+    if score >= SPAM_THRESHOLD {
+        return Ok((CommentStatus::Spam, score));
+    }
+
+    let approved = common::db_query_scalar!(
+        i64,
+        fetch_one,
+        "SELECT COUNT(*) FROM comments WHERE author = $1 AND status = 'approved'",
+        user.id
+    )?;
+
+    if approved == 0 {
+        Ok((CommentStatus::Pending, score))
+    } else {
+        Ok((CommentStatus::Approved, score))
+    }
+}
+
+/// Keeps the bayes filter in line with an admin decision on a comment:
+/// approved comments count as ham, spam as spam, everything else not at all.
+///
+/// `old` is what the comment was learned as before, if anything.
+pub async fn retrain(
+    content: &str,
+    old: Option<CommentStatus>,
+    new: CommentStatus,
+) -> Result<(), sqlx::Error> {
+    if old == Some(new) {
+        return Ok(());
+    }
+
+    if let Some(old) = old {
+        forget(content, old).await?;
+    }
+
+    learn(content, new).await
+}
+
+/// Counts `content` towards whatever `status` stands for in the bayes filter.
+pub async fn learn(content: &str, status: CommentStatus) -> Result<(), sqlx::Error> {
+    adjust(content, status, 1).await
+}
+
+/// Undoes a previous [`learn`] with the same content and status.
+pub async fn forget(content: &str, status: CommentStatus) -> Result<(), sqlx::Error> {
+    adjust(content, status, -1).await
+}
+
+async fn adjust(content: &str, status: CommentStatus, delta: i32) -> Result<(), sqlx::Error> {
+    let (spam, ham) = match status {
+        CommentStatus::Spam => (delta, 0),
+        CommentStatus::Approved => (0, delta),
+        CommentStatus::Pending | CommentStatus::Rejected => return Ok(()),
+    };
+
+    let tokens = tokenize(content).into_iter().collect::<Vec<_>>();
+
+    common::db_query!(
+        execute,
+        r#"
+        INSERT INTO spam_tokens (token, spam_count, ham_count)
+        SELECT token, GREATEST($2, 0), GREATEST($3, 0) FROM UNNEST($1::TEXT[]) AS token
+        ON CONFLICT (token) DO UPDATE SET
+            spam_count = GREATEST(spam_tokens.spam_count + $2, 0),
+            ham_count = GREATEST(spam_tokens.ham_count + $3, 0)
+        "#,
+        tokens,
+        spam,
+        ham,
+    )
+    .map(|_| ())
+}
+
+fn tokenize(content: &str) -> HashSet<String> {
+    TOKEN
+        .find_iter(&content.to_lowercase())
+        .map(|m| m.as_str().to_string())
+        .collect()
+}
@@ -0,0 +1,528 @@
+use std::{collections::BTreeMap, future::Future, pin::Pin, sync::OnceLock, time::Duration};
+
+use chrono::{NaiveDateTime, Utc};
+use common::{
+    Apps,
+    jobs::{self, Job},
+    models::NotificationPreferences,
+};
+use hmac::{Hmac, Mac};
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    message::header::{HeaderName, HeaderValue},
+    transport::smtp::authentication::Credentials,
+};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// How often the collected notifications of digest users are sent, see [`SendDigestJob`].
+pub const DIGEST_INTERVAL: Duration = Duration::from_secs(60 * 60 * 24);
+
+pub const SIGNATURE_HEADER: &str = "X-Microweb-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Microweb-Timestamp";
+
+#[derive(sqlx::Type, Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[sqlx(type_name = "notification_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    PostComment,
+    Reply,
+}
+
+/// A queued notification with everything needed to render it.
+#[derive(sqlx::FromRow, Clone, Debug, Serialize)]
+pub struct Notification {
+    pub id: i32,
+    #[serde(skip)]
+    pub user_id: i32,
+    #[serde(skip)]
+    pub user_name: String,
+    #[serde(skip)]
+    pub user_email: String,
+    pub kind: NotificationKind,
+    pub comment_id: i32,
+    pub commenter_name: Option<String>,
+    pub content: String,
+    pub post_title: String,
+    pub post_slug: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl Notification {
+    fn describe(&self) -> String {
+        let commenter = self.commenter_name.as_deref().unwrap_or("Someone");
+        match self.kind {
+            NotificationKind::PostComment => {
+                format!("{commenter} commented on \"{}\"", self.post_title)
+            }
+            NotificationKind::Reply => {
+                format!("{commenter} replied to you on \"{}\"", self.post_title)
+            }
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct UserPreferences {
+    user_id: i32,
+    unsubscribe_token: uuid::Uuid,
+    #[sqlx(flatten)]
+    preferences: NotificationPreferences,
+}
+
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    pub unsubscribe_url: String,
+}
+
+/// Delivers mails, the global one can be swapped out with [`set_mailer`].
+pub trait Mailer: Send + Sync {
+    fn send<'a>(&'a self, mail: &'a Mail) -> BoxFuture<'a, Result<(), String>>;
+}
+
+/// Only logs mails, used when no smtp server is configured.
+pub struct LogMailer;
+
+impl Mailer for LogMailer {
+    fn send<'a>(&'a self, mail: &'a Mail) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            tracing::info!(
+                to = mail.to,
+                subject = mail.subject,
+                "no SMTP_HOST configured, not sending mail"
+            );
+            Ok(())
+        })
+    }
+}
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: String,
+}
+
+impl SmtpMailer {
+    /// Configured with `SMTP_HOST`, `SMTP_USER`, `SMTP_PASSWORD` and `MAIL_FROM`.
+    pub fn from_env() -> Option<Self> {
+        let host = std::env::var("SMTP_HOST").ok()?;
+
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::relay(&host)
+            .inspect_err(|e| tracing::error!("Invalid SMTP_HOST: {e:?}"))
+            .ok()?;
+
+        if let (Ok(user), Ok(password)) =
+            (std::env::var("SMTP_USER"), std::env::var("SMTP_PASSWORD"))
+        {
+            builder = builder.credentials(Credentials::new(user, password));
+        }
+
+        Some(Self {
+            transport: builder.build(),
+            from: std::env::var("MAIL_FROM").unwrap_or(format!(
+                "noreply@{}",
+                common::DOMAIN.split(':').next().unwrap()
+            )),
+        })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send<'a>(&'a self, mail: &'a Mail) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            let message = Message::builder()
+                .from(
+                    self.from
+                        .parse()
+                        .map_err(|e| format!("Invalid sender: {e}"))?,
+                )
+                .to(mail
+                    .to
+                    .parse()
+                    .map_err(|e| format!("Invalid recipient: {e}"))?)
+                .subject(&mail.subject)
+                .raw_header(HeaderValue::new(
+                    HeaderName::new_from_ascii_str("List-Unsubscribe"),
+                    format!("<{}>", mail.unsubscribe_url),
+                ))
+                .body(mail.body.clone())
+                .map_err(|e| format!("Could not build mail: {e}"))?;
+
+            self.transport
+                .send(message)
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("Could not send mail: {e}"))
+        })
+    }
+}
+
+static MAILER: OnceLock<Box<dyn Mailer>> = OnceLock::new();
+
+pub fn set_mailer(mailer: impl Mailer + 'static) -> Result<(), Box<dyn Mailer>> {
+    MAILER.set(Box::new(mailer))
+}
+
+fn mailer<'a>() -> &'a dyn Mailer {
+    MAILER
+        .get_or_init(|| match SmtpMailer::from_env() {
+            Some(smtp) => Box::new(smtp),
+            None => Box::new(LogMailer),
+        })
+        .as_ref()
+}
+
+fn client<'a>() -> &'a reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("could not build http client")
+    })
+}
+
+/// Signs `{timestamp}.{body}` so receivers can check the origin and reject replays.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    event: &'static str,
+    notifications: &'a [Notification],
+}
+
+async fn send_webhook(
+    url: &str,
+    secret: Option<&str>,
+    notifications: &[Notification],
+) -> Result<(), String> {
+    let timestamp = Utc::now().timestamp();
+    let body = serde_json::to_vec(&WebhookPayload {
+        event: "comments",
+        notifications,
+    })
+    .map_err(|e| e.to_string())?;
+
+    let mut request = client()
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(TIMESTAMP_HEADER, timestamp);
+
+    if let Some(secret) = secret {
+        request = request.header(SIGNATURE_HEADER, sign(secret, timestamp, &body));
+    }
+
+    request
+        .body(body)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map(|_| ())
+        .map_err(|e| format!("Could not send webhook: {e}"))
+}
+
+fn render_mail(notifications: &[Notification], unsubscribe_token: uuid::Uuid) -> Mail {
+    let blog = Apps::Blog.url();
+    let unsubscribe_url = format!("{blog}/unsubscribe?token={unsubscribe_token}");
+
+    let subject = match notifications {
+        [single] => single.describe(),
+        _ => format!("{} new comments on the Blog", notifications.len()),
+    };
+
+    let mut body = format!("Hi {},\n\n", notifications[0].user_name);
+    for n in notifications {
+        let quoted = n
+            .content
+            .lines()
+            .map(|l| format!("> {l}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        body.push_str(&format!(
+            "{}:\n\n{quoted}\n\n{blog}/posts/{}#comment_section\n\n",
+            n.describe(),
+            n.post_slug
+        ));
+    }
+    body.push_str(&format!(
+        "--\nChange what you get notified about at {blog}/notifications\nor stop all mails with one click: {unsubscribe_url}\n"
+    ));
+
+    Mail {
+        to: notifications[0].user_email.clone(),
+        subject,
+        body,
+        unsubscribe_url,
+    }
+}
+
+/// Queues notifications for a comment that just became visible,
+/// the instant ones are handed to delivery jobs right away.
+pub async fn comment_approved(comment_id: i32) -> Result<(), sqlx::Error> {
+    // replies go first, so a post author that got replied to is only notified once
+    common::db_query!(
+        execute,
+        r#"
+        INSERT INTO notifications (user_id, comment_id, kind)
+        SELECT parent.author, comments.id, 'reply'
+        FROM comments
+        JOIN comments parent ON comments.replying_to = parent.id
+        WHERE comments.id = $1
+        AND parent.author IS NOT NULL
+        AND parent.author IS DISTINCT FROM comments.author
+        ON CONFLICT (user_id, comment_id) DO NOTHING
+        "#,
+        comment_id
+    )?;
+
+    common::db_query!(
+        execute,
+        r#"
+        INSERT INTO notifications (user_id, comment_id, kind)
+        SELECT posts.author, comments.id, 'post_comment'
+        FROM comments
+        JOIN posts ON comments.post = posts.id
+        WHERE comments.id = $1
+        AND posts.author IS NOT NULL
+        AND posts.author IS DISTINCT FROM comments.author
+        ON CONFLICT (user_id, comment_id) DO NOTHING
+        "#,
+        comment_id
+    )?;
+
+    tokio::spawn(async {
+        if let Err(e) = deliver(false).await {
+            tracing::error!("Error while queueing notifications: {e:?}");
+        }
+    });
+
+    Ok(())
+}
+
+/// Queues the delivery of every pending notification of users with (or without) digest mode,
+/// one [`DeliverNotificationsJob`] per user and channel.
+///
+/// Notifications are claimed while queueing, so concurrent runs never queue one twice.
+pub async fn deliver(digest: bool) -> Result<(), sqlx::Error> {
+    let mut tx = common::db::db().begin().await?;
+
+    // the rows stay locked until the jobs are queued, a failure leaves them pending
+    let pending = sqlx::query_as::<_, (i32, i32, NotificationKind)>(
+        r#"
+        SELECT notifications.id, notifications.user_id, notifications.kind FROM notifications
+        LEFT JOIN notification_preferences prefs
+            ON prefs.user_id = notifications.user_id
+        WHERE notifications.queued_at IS NULL
+        AND COALESCE(prefs.digest, false) = $1
+        ORDER BY notifications.created_at
+        FOR UPDATE OF notifications SKIP LOCKED
+        "#,
+    )
+    .bind(digest)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let mut by_user: BTreeMap<i32, Vec<(i32, NotificationKind)>> = BTreeMap::new();
+    for (id, user_id, kind) in &pending {
+        by_user.entry(*user_id).or_default().push((*id, *kind));
+    }
+
+    let user_ids = by_user.keys().copied().collect::<Vec<_>>();
+
+    // everyone needs a row for their unsubscribe token
+    common::db_query!(
+        execute,
+        r#"
+        INSERT INTO notification_preferences (user_id)
+        SELECT UNNEST($1::INTEGER[])
+        ON CONFLICT (user_id) DO NOTHING
+        "#,
+        &user_ids
+    )?;
+
+    let preferences = common::db_query_as!(
+        UserPreferences,
+        fetch_all,
+        "SELECT * FROM notification_preferences WHERE user_id = ANY($1)",
+        &user_ids
+    )?;
+
+    for UserPreferences {
+        user_id,
+        preferences,
+        ..
+    } in preferences
+    {
+        let notifications = by_user
+            .remove(&user_id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(_, kind)| match kind {
+                NotificationKind::PostComment => preferences.notify_post_comments,
+                NotificationKind::Reply => preferences.notify_replies,
+            })
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+
+        if notifications.is_empty() {
+            continue;
+        }
+
+        let channels = [
+            (preferences.email_enabled, Channel::Mail),
+            (preferences.webhook_url.is_some(), Channel::Webhook),
+        ];
+
+        for (_, channel) in channels.into_iter().filter(|(enabled, _)| *enabled) {
+            jobs::enqueue(&DeliverNotificationsJob {
+                user_id,
+                channel,
+                notifications: notifications.clone(),
+            })
+            .await?;
+        }
+    }
+
+    sqlx::query("UPDATE notifications SET queued_at = CURRENT_TIMESTAMP WHERE id = ANY($1)")
+        .bind(pending.iter().map(|(id, ..)| *id).collect::<Vec<_>>())
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub enum Channel {
+    Mail,
+    Webhook,
+}
+
+/// Sends notifications of one user over one channel, with the retries of the job queue.
+///
+/// Every channel gets its own job, so a failing webhook does not send the mail again.
+/// Notifications of deleted comments are gone by then and simply left out.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeliverNotificationsJob {
+    pub user_id: i32,
+    pub channel: Channel,
+    pub notifications: Vec<i32>,
+}
+
+impl Job for DeliverNotificationsJob {
+    const KIND: &'static str = "blog::deliver_notifications";
+
+    async fn run(self) -> Result<(), String> {
+        let notifications = common::db_query_as!(
+            Notification,
+            fetch_all,
+            r#"
+            SELECT
+                notifications.id,
+                notifications.user_id,
+                users.name AS user_name,
+                users.email AS user_email,
+                notifications.kind,
+                notifications.comment_id,
+                commenter.name AS commenter_name,
+                comments.content,
+                posts.title AS post_title,
+                posts.slug AS post_slug,
+                notifications.created_at
+            FROM notifications
+            JOIN users ON users.id = notifications.user_id
+            JOIN comments ON comments.id = notifications.comment_id
+            JOIN posts ON posts.id = comments.post
+            LEFT JOIN users commenter ON commenter.id = comments.author
+            WHERE notifications.id = ANY($1)
+            ORDER BY notifications.created_at
+            "#,
+            &self.notifications
+        )
+        .map_err(|e| format!("DB error: {e}"))?;
+
+        let Some(UserPreferences {
+            unsubscribe_token,
+            preferences,
+            ..
+        }) = common::db_query_as!(
+            UserPreferences,
+            fetch_optional,
+            "SELECT * FROM notification_preferences WHERE user_id = $1",
+            self.user_id
+        )
+        .map_err(|e| format!("DB error: {e}"))?
+        else {
+            return Ok(());
+        };
+
+        if notifications.is_empty() {
+            return Ok(());
+        }
+
+        // the preferences may have changed since, like after unsubscribing
+        match self.channel {
+            Channel::Mail if preferences.email_enabled => {
+                mailer()
+                    .send(&render_mail(&notifications, unsubscribe_token))
+                    .await
+            }
+            Channel::Webhook => match &preferences.webhook_url {
+                Some(url) => {
+                    send_webhook(url, preferences.webhook_secret.as_deref(), &notifications).await
+                }
+                None => Ok(()),
+            },
+            Channel::Mail => Ok(()),
+        }
+    }
+}
+
+/// Queues the delivery of the digests and the next digest one [`DIGEST_INTERVAL`] later.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SendDigestJob {}
+
+impl Job for SendDigestJob {
+    const KIND: &'static str = "blog::send_digest";
+
+    async fn run(self) -> Result<(), String> {
+        // queued first, so a failing digest does not end the chain, retries find it queued
+        jobs::enqueue_once_in(&SendDigestJob {}, DIGEST_INTERVAL)
+            .await
+            .map_err(|e| format!("Could not queue the next digest: {e}"))?;
+
+        deliver(true).await.map_err(|e| format!("DB error: {e}"))
+    }
+}
+
+/// Queues the next digest one [`DIGEST_INTERVAL`] after the last one, called at startup.
+///
+/// Restarts keep an already queued digest, so they do not push it back.
+pub async fn schedule_digest() {
+    let delay = match jobs::since_last_done::<SendDigestJob>().await {
+        Ok(Some(since)) => DIGEST_INTERVAL.saturating_sub(since),
+        Ok(None) => DIGEST_INTERVAL,
+        Err(e) => {
+            tracing::error!("Error while looking for the last digest: {e:?}");
+            return;
+        }
+    };
+
+    if let Err(e) = jobs::enqueue_once_in(&SendDigestJob {}, delay).await {
+        tracing::error!("Error while queueing the digest: {e:?}");
+    }
+}
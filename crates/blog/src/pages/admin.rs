@@ -1,11 +1,16 @@
 use crate::{
-    app::{GlobalState, GlobalStateStoreFields},
     components::{header::Header, side_menu::SideMenu},
     pages::loading::LoadingPage,
 };
 use chrono::Utc;
 use common::Apps;
 use common::models::*;
+use common::portfolio::{
+    PortfolioTable, delete_portfolio_entry, get_all_projects, get_experience, get_skills,
+    get_social_links, move_portfolio_entry, save_experience, save_project, save_skill,
+    save_social_link,
+};
+use common::state::{GlobalState, GlobalStateStoreFields};
 use leptos::prelude::*;
 use leptos::{Params, task::spawn_local};
 use leptos_meta::*;
@@ -134,13 +139,17 @@ pub async fn delete_post(post_id: i32) -> Result<u64, ServerFnError> {
         return Err(ServerFnError::new("Unauthorized."));
     };
 
-    common::db_query!(execute, "DELETE FROM posts WHERE id = $1", post_id)
+    let affected = common::db_query!(execute, "DELETE FROM posts WHERE id = $1", post_id)
         .map_err(|e| {
             let err = format!("Error while deleting post: {e:?}");
             tracing::error!("{err}");
             ServerFnError::new("Could not delete post.")
-        })
-        .map(|r| r.rows_affected())
+        })?
+        .rows_affected();
+
+    crate::pages::rss::feed_changed().await;
+
+    Ok(affected)
 }
 
 #[server(ReleasePostAction, "/api/admin", endpoint = "release_post")]
@@ -153,7 +162,7 @@ pub async fn release_post(release: bool, post_id: i32) -> Result<u64, ServerFnEr
         return Err(ServerFnError::new("Unauthorized."));
     };
 
-    common::db_query!(
+    let affected = common::db_query!(
         execute,
         r#"
             UPDATE posts
@@ -168,8 +177,12 @@ pub async fn release_post(release: bool, post_id: i32) -> Result<u64, ServerFnEr
         let err = format!("Error while releasing post: {e:?}");
         tracing::error!("{err}");
         ServerFnError::new("Could not release post.")
-    })
-    .map(|r| r.rows_affected())
+    })?
+    .rows_affected();
+
+    crate::pages::rss::feed_changed().await;
+
+    Ok(affected)
 }
 
 #[server(CreatePostAction, "/api/admin", endpoint = "create_post")]
@@ -198,6 +211,7 @@ pub async fn create_post() -> Result<u64, ServerFnError> {
         release_date: None,
         created_at: Utc::now().naive_local(),
         updated_at: None,
+        image_widths: Default::default(),
     };
 
     common::db_query!(
@@ -237,7 +251,7 @@ pub async fn get_files() -> Result<Vec<File>, ServerFnError> {
     let Some(dir) = common::db_query_as!(
         Directory,
         fetch_optional,
-        "SELECT * FROM directories WHERE dir_path = $1",
+        "SELECT * FROM directories WHERE dir_path = $1 AND deleted_at IS NULL",
         "/~/blogs"
     )
     .unwrap_or(None) else {
@@ -250,6 +264,202 @@ pub async fn get_files() -> Result<Vec<File>, ServerFnError> {
         .map_err(|_| ServerFnError::ServerError("Not found.".into()))
 }
 
+#[server(
+    GetStorageUsageAction,
+    "/api/admin",
+    "GetJson",
+    endpoint = "storage_usage"
+)]
+#[tracing::instrument]
+pub async fn get_storage_usage() -> Result<StorageUsage, ServerFnError> {
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    if !extract::<Extension<User>>().await.is_ok_and(|u| u.admin) {
+        return Err(ServerFnError::new("Unauthorized."));
+    };
+
+    files::policy::usage().await.map_err(|e| {
+        let err = format!("Error while getting storage usage: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not get storage usage.")
+    })
+}
+
+#[server(
+    GetCommentQueueAction,
+    "/api/admin",
+    "GetJson",
+    endpoint = "comment_queue"
+)]
+#[tracing::instrument]
+pub async fn get_comment_queue() -> Result<Vec<ModerationComment>, ServerFnError> {
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    if !extract::<Extension<User>>().await.is_ok_and(|u| u.admin) {
+        return Err(ServerFnError::new("Unauthorized."));
+    };
+
+    common::db_query_as!(
+        ModerationComment,
+        fetch_all,
+        r#"
+        SELECT
+            comments.id,
+            users.name AS author_name,
+            comments.author AS author_id,
+            posts.title AS post_title,
+            posts.slug AS post_slug,
+            comments.content,
+            comments.status,
+            comments.spam_score,
+            comments.created_at
+        FROM comments
+        JOIN posts ON comments.post = posts.id
+        LEFT JOIN users ON comments.author = users.id
+        WHERE comments.status IN ('pending', 'spam')
+        ORDER BY comments.created_at DESC
+        "#
+    )
+    .map_err(|e| {
+        let err = format!("Error while getting comment queue: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not retrieve comments, try again later")
+    })
+}
+
+#[server(ModerateCommentAction, "/api/admin", endpoint = "moderate_comment")]
+#[tracing::instrument]
+pub async fn moderate_comment(
+    comment_id: i32,
+    status: CommentStatus,
+) -> Result<u64, ServerFnError> {
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    if !extract::<Extension<User>>().await.is_ok_and(|u| u.admin) {
+        return Err(ServerFnError::new("Unauthorized."));
+    };
+
+    let (content, old, trained_as) = common::db_query_as!(
+        (String, CommentStatus, Option<CommentStatus>),
+        fetch_one,
+        "SELECT content, status, trained_as FROM comments WHERE id = $1",
+        comment_id
+    )
+    .map_err(|e| {
+        let err = format!("Error while moderating comment: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not moderate comment.")
+    })?;
+
+    // only what admins decide is learned, the filter would otherwise reinforce its own mistakes
+    let trained = matches!(status, CommentStatus::Approved | CommentStatus::Spam).then_some(status);
+
+    let affected = common::db_query!(
+        execute,
+        "UPDATE comments SET status = $1, trained_as = $2 WHERE id = $3",
+        status,
+        trained,
+        comment_id,
+    )
+    .map_err(|e| {
+        let err = format!("Error while moderating comment: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not moderate comment.")
+    })?
+    .rows_affected();
+
+    if let Err(e) = crate::moderation::retrain(&content, trained_as, status).await {
+        tracing::error!("Error while training spam filter: {e:?}");
+    }
+
+    if status == CommentStatus::Approved
+        && old != CommentStatus::Approved
+        && let Err(e) = crate::notifications::comment_approved(comment_id).await
+    {
+        tracing::error!("Error while queueing notifications: {e:?}");
+    }
+
+    Ok(affected)
+}
+
+#[server(GetJobsAction, "/api/admin", "GetJson", endpoint = "jobs")]
+#[tracing::instrument]
+pub async fn get_jobs() -> Result<Vec<JobRecord>, ServerFnError> {
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    if !extract::<Extension<User>>().await.is_ok_and(|u| u.admin) {
+        return Err(ServerFnError::new("Unauthorized."));
+    };
+
+    common::db_query_as!(
+        JobRecord,
+        fetch_all,
+        r#"
+        SELECT
+            id,
+            kind,
+            payload::TEXT AS payload,
+            status,
+            attempts,
+            max_attempts,
+            last_error,
+            run_at,
+            created_at,
+            finished_at
+        FROM jobs
+        ORDER BY created_at DESC
+        LIMIT 200
+        "#
+    )
+    .map_err(|e| {
+        let err = format!("Error while getting jobs: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not retrieve jobs, try again later")
+    })
+}
+
+#[server(RetryJobAction, "/api/admin", endpoint = "retry_job")]
+#[tracing::instrument]
+pub async fn retry_job(job_id: i64) -> Result<u64, ServerFnError> {
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    if !extract::<Extension<User>>().await.is_ok_and(|u| u.admin) {
+        return Err(ServerFnError::new("Unauthorized."));
+    };
+
+    common::jobs::retry(job_id).await.map_err(|e| {
+        let err = format!("Error while retrying job: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not retry job.")
+    })
+}
+
+/// Compiles the resume from its typst sources again, served on www once done.
+#[server(BuildCvAction, "/api/admin", endpoint = "build_cv")]
+#[tracing::instrument]
+pub async fn build_cv() -> Result<(), ServerFnError> {
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    if !extract::<Extension<User>>().await.is_ok_and(|u| u.admin) {
+        return Err(ServerFnError::new("Unauthorized."));
+    };
+
+    common::jobs::enqueue_kind_once(common::jobs::kinds::BUILD_CV)
+        .await
+        .map(|_| ())
+        .map_err(|e| {
+            let err = format!("Error while queueing the resume build: {e:?}");
+            tracing::error!("{err}");
+            ServerFnError::new("Could not build the resume.")
+        })
+}
+
 #[component]
 pub fn AdminPage() -> impl IntoView {
     let query = use_query::<TabQuery>();
@@ -262,6 +472,13 @@ pub fn AdminPage() -> impl IntoView {
     let (blog_posts, set_blog_posts) = signal(Vec::new());
     let (users, set_users) = signal(Vec::new());
     let (contained_files, set_files) = signal(Vec::new());
+    let (storage_usage, set_storage_usage) = signal(StorageUsage::default());
+    let (comment_queue, set_comment_queue) = signal(Vec::new());
+    let (job_list, set_job_list) = signal(Vec::new());
+    let (experience, set_experience) = signal(Vec::new());
+    let (skills, set_skills) = signal(Vec::new());
+    let (projects, set_projects) = signal(Vec::new());
+    let (social_links, set_social_links) = signal(Vec::new());
 
     let posts_res = Resource::new(
         move || updated.get(),
@@ -278,6 +495,41 @@ pub fn AdminPage() -> impl IntoView {
         |_| async move { get_files().await.unwrap_or(Vec::new()) },
     );
 
+    let usage_res = Resource::new(
+        move || updated.get(),
+        |_| async move { get_storage_usage().await.unwrap_or_default() },
+    );
+
+    let comments_res = Resource::new(
+        move || updated.get(),
+        |_| async move { get_comment_queue().await.unwrap_or(Vec::new()) },
+    );
+
+    let jobs_res = Resource::new(
+        move || updated.get(),
+        |_| async move { get_jobs().await.unwrap_or(Vec::new()) },
+    );
+
+    let experience_res = Resource::new(
+        move || updated.get(),
+        |_| async move { get_experience().await.unwrap_or(Vec::new()) },
+    );
+
+    let skills_res = Resource::new(
+        move || updated.get(),
+        |_| async move { get_skills().await.unwrap_or(Vec::new()) },
+    );
+
+    let projects_res = Resource::new(
+        move || updated.get(),
+        |_| async move { get_all_projects().await.unwrap_or(Vec::new()) },
+    );
+
+    let social_links_res = Resource::new(
+        move || updated.get(),
+        |_| async move { get_social_links().await.unwrap_or(Vec::new()) },
+    );
+
     Effect::new(move |_| {
         if !store.user().get().is_some_and(|u| u.is_admin) {
             use_navigate()("/", Default::default());
@@ -290,6 +542,13 @@ pub fn AdminPage() -> impl IntoView {
         set_blog_posts(posts_res.get().unwrap_or(Vec::new()));
         set_users(users_res.get().unwrap_or(Vec::new()));
         set_files(files_res.get().unwrap_or(Vec::new()));
+        set_storage_usage(usage_res.get().unwrap_or_default());
+        set_comment_queue(comments_res.get().unwrap_or(Vec::new()));
+        set_job_list(jobs_res.get().unwrap_or(Vec::new()));
+        set_experience(experience_res.get().unwrap_or(Vec::new()));
+        set_skills(skills_res.get().unwrap_or(Vec::new()));
+        set_projects(projects_res.get().unwrap_or(Vec::new()));
+        set_social_links(social_links_res.get().unwrap_or(Vec::new()));
     });
 
     view! {
@@ -307,8 +566,26 @@ pub fn AdminPage() -> impl IntoView {
                     <div class="p-6">
                         {move || match current_tab.get().as_str() {
                             "users" => view! { <UserSection users set_updated /> }.into_any(),
-                            "files" => view! { <FilesSection contained_files /> }.into_any(),
+                            "files" => {
+                                view! { <FilesSection contained_files storage_usage /> }.into_any()
+                            }
+                            "comments" => {
+                                view! { <CommentsSection comment_queue set_updated /> }.into_any()
+                            }
                             "blogs" => view! { <BlogSection blog_posts set_updated /> }.into_any(),
+                            "jobs" => view! { <JobsSection job_list set_updated /> }.into_any(),
+                            "portfolio" => {
+                                view! {
+                                    <PortfolioSection
+                                        experience
+                                        skills
+                                        projects
+                                        social_links
+                                        set_updated
+                                    />
+                                }
+                                    .into_any()
+                            }
                             _ => view! { <LoadingPage /> }.into_any(),
                         }}
                     </div>
@@ -482,8 +759,56 @@ pub fn UserEditRow(
 }
 
 #[component]
-pub fn FilesSection(contained_files: ReadSignal<Vec<File>>) -> impl IntoView {
+pub fn FilesSection(
+    contained_files: ReadSignal<Vec<File>>,
+    storage_usage: ReadSignal<StorageUsage>,
+) -> impl IntoView {
+    let percent = move || {
+        let usage = storage_usage.get();
+        usage
+            .quota
+            .filter(|quota| *quota > 0)
+            .map(|quota| (usage.used as f64 / quota as f64 * 100.0).min(100.0))
+    };
+
     view! {
+        <div class="mb-6 rounded border border-gray-200 p-4 text-sm">
+            <p class="font-medium text-gray-900">
+                {move || {
+                    let usage = storage_usage.get();
+                    match usage.quota {
+                        Some(quota) => {
+                            format!(
+                                "{} of {} used",
+                                format_bytes(usage.used),
+                                format_bytes(quota),
+                            )
+                        }
+                        None => format!("{} used, no quota", format_bytes(usage.used)),
+                    }
+                }}
+            </p>
+            <Show when=move || percent().is_some()>
+                <div class="mt-2 h-2 w-full rounded bg-gray-200">
+                    <div
+                        class="h-2 rounded"
+                        class=("bg-red-600", move || percent().is_some_and(|p| p >= 90.0))
+                        class=("bg-indigo-600", move || percent().is_some_and(|p| p < 90.0))
+                        style:width=move || format!("{:.1}%", percent().unwrap_or_default())
+                    ></div>
+                </div>
+            </Show>
+            <p class="mt-2 text-gray-700">
+                {move || {
+                    let usage = storage_usage.get();
+                    format!(
+                        "{} files, {} only held by the trash",
+                        usage.files,
+                        format_bytes(usage.trash),
+                    )
+                }}
+            </p>
+        </div>
         <div class="overflow-x-auto">
             <table class="min-w-full divide-y-2 divide-gray-200 text-sm">
                 <thead class="text-left">
@@ -530,6 +855,583 @@ pub fn FilesSection(contained_files: ReadSignal<Vec<File>>) -> impl IntoView {
     }
 }
 
+fn format_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+#[component]
+pub fn CommentsSection(
+    comment_queue: ReadSignal<Vec<ModerationComment>>,
+    set_updated: WriteSignal<u32>,
+) -> impl IntoView {
+    let moderate = move |id: i32, status: CommentStatus| {
+        spawn_local(async move {
+            if moderate_comment(id, status).await.is_ok() {
+                set_updated.update(|i| *i += 1);
+            }
+        });
+    };
+
+    view! {
+        <div class="overflow-x-auto">
+            <table class="min-w-full divide-y-2 divide-gray-200 text-sm">
+                <thead class="text-left">
+                    <tr>
+                        <th class="whitespace-nowrap px-4 py-2 font-medium text-gray-900">ID</th>
+                        <th class="whitespace-nowrap px-4 py-2 font-medium text-gray-900">
+                            Author
+                        </th>
+                        <th class="whitespace-nowrap px-4 py-2 font-medium text-gray-900">Post</th>
+                        <th class="whitespace-nowrap px-4 py-2 font-medium text-gray-900">
+                            Content
+                        </th>
+                        <th class="whitespace-nowrap px-4 py-2 font-medium text-gray-900">
+                            Status
+                        </th>
+                        <th class="whitespace-nowrap px-4 py-2 font-medium text-gray-900">
+                            Spam Score
+                        </th>
+                        <th class="whitespace-nowrap px-4 py-2 font-medium text-gray-900">
+                            Created At
+                        </th>
+                        <th class="px-4 py-2"></th>
+                        <th class="px-4 py-2"></th>
+                        <th class="px-4 py-2"></th>
+                    </tr>
+                </thead>
+
+                <tbody class="divide-y divide-gray-200">
+                    <For
+                        each=move || comment_queue.get()
+                        key=|c| (c.id, c.status)
+                        children=move |c: ModerationComment| {
+                            view! {
+                                <tr class="odd:bg-gray-50">
+                                    <td class="whitespace-nowrap px-4 py-2 text-gray-700">
+                                        {c.id}
+                                    </td>
+                                    <td class="whitespace-nowrap px-4 py-2 font-medium text-gray-900">
+                                        {c.author_name.unwrap_or("DELETED USER".into())}
+                                    </td>
+                                    <td class="whitespace-nowrap px-4 py-2 text-gray-700">
+                                        <a href=format!("/posts/{}", c.post_slug) target="_blank">
+                                            {c.post_title}
+                                        </a>
+                                    </td>
+                                    <td class="px-4 py-2 text-gray-700">{c.content}</td>
+                                    <td class="whitespace-nowrap px-4 py-2 text-gray-700">
+                                        {c.status.to_string()}
+                                    </td>
+                                    <td class="whitespace-nowrap px-4 py-2 text-gray-700">
+                                        {format!("{:.2}", c.spam_score)}
+                                    </td>
+                                    <td class="whitespace-nowrap px-4 py-2 text-gray-700">
+                                        {c.created_at.to_string()}
+                                    </td>
+                                    <td class="whitespace-nowrap px-4 py-2">
+                                        <button
+                                            class="border-none inline-block rounded bg-indigo-600 px-4 py-2 text-xs font-medium text-white hover:bg-indigo-700"
+                                            on:click=move |_| moderate(c.id, CommentStatus::Approved)
+                                        >
+                                            Approve
+                                        </button>
+                                    </td>
+                                    <td class="whitespace-nowrap px-4 py-2">
+                                        <button
+                                            class="border-none inline-block rounded bg-red-600 px-4 py-2 text-xs font-medium text-white hover:bg-red-700"
+                                            on:click=move |_| moderate(c.id, CommentStatus::Rejected)
+                                        >
+                                            Reject
+                                        </button>
+                                    </td>
+                                    <td class="whitespace-nowrap px-4 py-2">
+                                        <Show when=move || c.status != CommentStatus::Spam>
+                                            <button
+                                                class="border-none inline-block rounded bg-red-600 px-4 py-2 text-xs font-medium text-white hover:bg-red-700"
+                                                on:click=move |_| moderate(c.id, CommentStatus::Spam)
+                                            >
+                                                Spam
+                                            </button>
+                                        </Show>
+                                    </td>
+                                </tr>
+                            }
+                        }
+                    />
+                </tbody>
+            </table>
+        </div>
+    }
+}
+
+#[component]
+pub fn JobsSection(
+    job_list: ReadSignal<Vec<JobRecord>>,
+    set_updated: WriteSignal<u32>,
+) -> impl IntoView {
+    let retry = move |id: i64| {
+        spawn_local(async move {
+            if retry_job(id).await.is_ok() {
+                set_updated.update(|i| *i += 1);
+            }
+        });
+    };
+
+    view! {
+        <div class="overflow-x-auto">
+            <button
+                class="mb-4 border-none inline-block rounded bg-indigo-600 px-4 py-2 text-xs font-medium text-white hover:bg-indigo-700"
+                on:click=move |_| set_updated.update(|i| *i += 1)
+            >
+                Refresh
+            </button>
+            <button
+                class="mb-4 ml-2 border-none inline-block rounded bg-indigo-600 px-4 py-2 text-xs font-medium text-white hover:bg-indigo-700"
+                on:click=move |_| {
+                    spawn_local(async move {
+                        if build_cv().await.is_ok() {
+                            set_updated.update(|i| *i += 1);
+                        }
+                    });
+                }
+            >
+                Build CV
+            </button>
+            <table class="min-w-full divide-y-2 divide-gray-200 text-sm">
+                <thead class="text-left">
+                    <tr>
+                        <th class="whitespace-nowrap px-4 py-2 font-medium text-gray-900">ID</th>
+                        <th class="whitespace-nowrap px-4 py-2 font-medium text-gray-900">Kind</th>
+                        <th class="whitespace-nowrap px-4 py-2 font-medium text-gray-900">
+                            Payload
+                        </th>
+                        <th class="whitespace-nowrap px-4 py-2 font-medium text-gray-900">
+                            Status
+                        </th>
+                        <th class="whitespace-nowrap px-4 py-2 font-medium text-gray-900">
+                            Attempts
+                        </th>
+                        <th class="whitespace-nowrap px-4 py-2 font-medium text-gray-900">
+                            Last Error
+                        </th>
+                        <th class="whitespace-nowrap px-4 py-2 font-medium text-gray-900">
+                            Run At
+                        </th>
+                        <th class="whitespace-nowrap px-4 py-2 font-medium text-gray-900">
+                            Finished At
+                        </th>
+                        <th class="px-4 py-2"></th>
+                    </tr>
+                </thead>
+
+                <tbody class="divide-y divide-gray-200">
+                    <For
+                        each=move || job_list.get()
+                        key=|j| (j.id, j.status, j.attempts)
+                        children=move |j: JobRecord| {
+                            view! {
+                                <tr class="odd:bg-gray-50">
+                                    <td class="whitespace-nowrap px-4 py-2 text-gray-700">
+                                        {j.id}
+                                    </td>
+                                    <td class="whitespace-nowrap px-4 py-2 font-medium text-gray-900">
+                                        {j.kind}
+                                    </td>
+                                    <td class="px-4 py-2 text-gray-700">
+                                        <code>{j.payload}</code>
+                                    </td>
+                                    <td class="whitespace-nowrap px-4 py-2 text-gray-700">
+                                        {j.status.to_string()}
+                                    </td>
+                                    <td class="whitespace-nowrap px-4 py-2 text-gray-700">
+                                        {format!("{}/{}", j.attempts, j.max_attempts)}
+                                    </td>
+                                    <td class="px-4 py-2 text-gray-700">
+                                        {j.last_error.unwrap_or_default()}
+                                    </td>
+                                    <td class="whitespace-nowrap px-4 py-2 text-gray-700">
+                                        {j.run_at.to_string()}
+                                    </td>
+                                    <td class="whitespace-nowrap px-4 py-2 text-gray-700">
+                                        {j.finished_at.map(|d| d.to_string()).unwrap_or_default()}
+                                    </td>
+                                    <td class="whitespace-nowrap px-4 py-2">
+                                        <Show when=move || j.status == JobStatus::Failed>
+                                            <button
+                                                class="border-none inline-block rounded bg-indigo-600 px-4 py-2 text-xs font-medium text-white hover:bg-indigo-700"
+                                                on:click=move |_| retry(j.id)
+                                            >
+                                                Retry
+                                            </button>
+                                        </Show>
+                                    </td>
+                                </tr>
+                            }
+                        }
+                    />
+                </tbody>
+            </table>
+        </div>
+    }
+}
+
+/// Runs a change to the portfolio, showing what went wrong or loading the lists again.
+fn change_portfolio(
+    set_updated: WriteSignal<u32>,
+    change: impl Future<Output = Result<(), ServerFnError>> + 'static,
+) {
+    spawn_local(async move {
+        match change.await {
+            Ok(()) => set_updated.update(|i| *i += 1),
+            Err(ServerFnError::ServerError(e)) => {
+                let _ = window().alert_with_message(&e);
+            }
+            Err(e) => {
+                let _ = window().alert_with_message(&e.to_string());
+            }
+        }
+    });
+}
+
+#[component]
+pub fn PortfolioSection(
+    experience: ReadSignal<Vec<Experience>>,
+    skills: ReadSignal<Vec<Skill>>,
+    projects: ReadSignal<Vec<Project>>,
+    social_links: ReadSignal<Vec<SocialLink>>,
+    set_updated: WriteSignal<u32>,
+) -> impl IntoView {
+    view! {
+        <div class="flex flex-col gap-8">
+            <PortfolioGroup title="Experience" columns=&["Title", "Company", "Url", "Period"]>
+                <For
+                    each=move || experience.get()
+                    key=|e| (e.id, e.position)
+                    children=move |e: Experience| view! { <ExperienceRow experience=e set_updated /> }
+                />
+                // rendered anew after every change, so it is empty again after adding
+                {move || {
+                    experience.track();
+                    view! { <ExperienceRow experience=Experience::default() set_updated /> }
+                }}
+            </PortfolioGroup>
+            <PortfolioGroup title="Skills" columns=&["Name"]>
+                <For
+                    each=move || skills.get()
+                    key=|s| (s.id, s.position)
+                    children=move |s: Skill| view! { <SkillRow skill=s set_updated /> }
+                />
+                {move || {
+                    skills.track();
+                    view! { <SkillRow skill=Skill::default() set_updated /> }
+                }}
+            </PortfolioGroup>
+            <PortfolioGroup
+                title="Projects"
+                columns=&[
+                    "Name",
+                    "Slug",
+                    "Description",
+                    "Tags",
+                    "Url",
+                    "Repository",
+                    "Sandbox Page",
+                    "Blog Posts",
+                ]
+            >
+                <For
+                    each=move || projects.get()
+                    key=|p| (p.id, p.position)
+                    children=move |p: Project| view! { <ProjectRow project=p set_updated /> }
+                />
+                {move || {
+                    projects.track();
+                    view! { <ProjectRow project=Project::default() set_updated /> }
+                }}
+            </PortfolioGroup>
+            <PortfolioGroup title="Social Links" columns=&["Name (the icon)", "Url"]>
+                <For
+                    each=move || social_links.get()
+                    key=|s| (s.id, s.position)
+                    children=move |s: SocialLink| {
+                        view! { <SocialLinkRow social_link=s set_updated /> }
+                    }
+                />
+                {move || {
+                    social_links.track();
+                    view! { <SocialLinkRow social_link=SocialLink::default() set_updated /> }
+                }}
+            </PortfolioGroup>
+        </div>
+    }
+}
+
+#[component]
+fn PortfolioGroup(
+    title: &'static str,
+    columns: &'static [&'static str],
+    children: Children,
+) -> impl IntoView {
+    view! {
+        <div class="overflow-x-auto">
+            <h2 class="mb-2 text-lg font-bold">{title}</h2>
+            <table class="min-w-full divide-y-2 divide-gray-200 text-sm">
+                <thead class="text-left">
+                    <tr>
+                        {columns
+                            .iter()
+                            .map(|column| {
+                                view! {
+                                    <th class="whitespace-nowrap px-4 py-2 font-medium text-gray-900">
+                                        {*column}
+                                    </th>
+                                }
+                            })
+                            .collect_view()}
+                        <th class="px-4 py-2"></th>
+                        <th class="px-4 py-2"></th>
+                        <th class="px-4 py-2"></th>
+                        <th class="px-4 py-2"></th>
+                    </tr>
+                </thead>
+
+                <tbody class="divide-y divide-gray-200">{children()}</tbody>
+            </table>
+        </div>
+    }
+}
+
+/// One editable entry of a portfolio table, or a new one if its id is 0.
+#[component]
+fn PortfolioRow(
+    table: PortfolioTable,
+    id: i32,
+    fields: Vec<RwSignal<String>>,
+    save: Callback<()>,
+    set_updated: WriteSignal<u32>,
+) -> impl IntoView {
+    let reorder = move |up: bool| {
+        change_portfolio(set_updated, move_portfolio_entry(table, id, up));
+    };
+
+    let delete = move |_| {
+        if window()
+            .confirm_with_message("Delete this entry?")
+            .unwrap_or(false)
+        {
+            change_portfolio(set_updated, async move {
+                delete_portfolio_entry(table, id).await.map(|_| ())
+            });
+        }
+    };
+
+    view! {
+        <tr class="odd:bg-gray-50">
+            {fields
+                .into_iter()
+                .map(|field| {
+                    view! {
+                        <td class="px-4 py-2 text-gray-700">
+                            <input
+                                class="w-full rounded border border-gray-200 px-2 py-1"
+                                prop:value=move || field.get()
+                                on:input=move |ev| field.set(event_target_value(&ev))
+                            />
+                        </td>
+                    }
+                })
+                .collect_view()}
+            <td class="whitespace-nowrap px-4 py-2">
+                <button
+                    class="border-none inline-block rounded bg-indigo-600 px-4 py-2 text-xs font-medium text-white hover:bg-indigo-700"
+                    on:click=move |_| save.run(())
+                >
+                    {if id == 0 { "Add" } else { "Save" }}
+                </button>
+            </td>
+            <Show
+                when=move || id != 0
+                fallback=|| {
+                    view! {
+                        <td class="px-4 py-2"></td>
+                        <td class="px-4 py-2"></td>
+                        <td class="px-4 py-2"></td>
+                    }
+                }
+            >
+                <td class="whitespace-nowrap px-4 py-2">
+                    <button
+                        class="border-none inline-block rounded bg-indigo-600 px-4 py-2 text-xs font-medium text-white hover:bg-indigo-700"
+                        title="Move up"
+                        on:click=move |_| reorder(true)
+                    >
+                        "↑"
+                    </button>
+                </td>
+                <td class="whitespace-nowrap px-4 py-2">
+                    <button
+                        class="border-none inline-block rounded bg-indigo-600 px-4 py-2 text-xs font-medium text-white hover:bg-indigo-700"
+                        title="Move down"
+                        on:click=move |_| reorder(false)
+                    >
+                        "↓"
+                    </button>
+                </td>
+                <td class="whitespace-nowrap px-4 py-2">
+                    <button
+                        class="border-none inline-block rounded bg-red-600 px-4 py-2 text-xs font-medium text-white hover:bg-red-700"
+                        on:click=delete
+                    >
+                        Delete
+                    </button>
+                </td>
+            </Show>
+        </tr>
+    }
+}
+
+#[component]
+fn ExperienceRow(experience: Experience, set_updated: WriteSignal<u32>) -> impl IntoView {
+    let Experience { id, position, .. } = experience;
+    let title = RwSignal::new(experience.title);
+    let company = RwSignal::new(experience.company);
+    let url = RwSignal::new(experience.url);
+    let period = RwSignal::new(experience.period);
+
+    let save = Callback::new(move |()| {
+        let experience = Experience {
+            id,
+            title: title.get_untracked(),
+            company: company.get_untracked(),
+            url: url.get_untracked(),
+            period: period.get_untracked(),
+            position,
+        };
+        change_portfolio(set_updated, async move {
+            save_experience(experience).await.map(|_| ())
+        });
+    });
+
+    view! {
+        <PortfolioRow
+            table=PortfolioTable::Experience
+            id
+            fields=vec![title, company, url, period]
+            save
+            set_updated
+        />
+    }
+}
+
+#[component]
+fn SkillRow(skill: Skill, set_updated: WriteSignal<u32>) -> impl IntoView {
+    let Skill { id, position, .. } = skill;
+    let name = RwSignal::new(skill.name);
+
+    let save = Callback::new(move |()| {
+        let skill = Skill {
+            id,
+            name: name.get_untracked(),
+            position,
+        };
+        change_portfolio(
+            set_updated,
+            async move { save_skill(skill).await.map(|_| ()) },
+        );
+    });
+
+    view! { <PortfolioRow table=PortfolioTable::Skills id fields=vec![name] save set_updated /> }
+}
+
+#[component]
+fn ProjectRow(project: Project, set_updated: WriteSignal<u32>) -> impl IntoView {
+    let Project { id, position, .. } = project;
+    let name = RwSignal::new(project.name);
+    let slug = RwSignal::new(project.slug);
+    let description = RwSignal::new(project.description);
+    let tags = RwSignal::new(project.tags.join(", "));
+    let url = RwSignal::new(project.url.unwrap_or_default());
+    let repository = RwSignal::new(project.repository.unwrap_or_default());
+    let sandbox_slug = RwSignal::new(project.sandbox_slug.unwrap_or_default());
+    let posts = RwSignal::new(project.posts.join(", "));
+
+    let split = |list: String| {
+        list.split(',')
+            .map(|item| item.trim().to_string())
+            .collect()
+    };
+
+    let save = Callback::new(move |()| {
+        let project = Project {
+            id,
+            name: name.get_untracked(),
+            slug: slug.get_untracked(),
+            description: description.get_untracked(),
+            tags: split(tags.get_untracked()),
+            url: Some(url.get_untracked()),
+            repository: Some(repository.get_untracked()),
+            sandbox_slug: Some(sandbox_slug.get_untracked()),
+            posts: split(posts.get_untracked()),
+            position,
+        };
+        change_portfolio(set_updated, async move {
+            save_project(project).await.map(|_| ())
+        });
+    });
+
+    view! {
+        <PortfolioRow
+            table=PortfolioTable::Projects
+            id
+            fields=vec![name, slug, description, tags, url, repository, sandbox_slug, posts]
+            save
+            set_updated
+        />
+    }
+}
+
+#[component]
+fn SocialLinkRow(social_link: SocialLink, set_updated: WriteSignal<u32>) -> impl IntoView {
+    let SocialLink { id, position, .. } = social_link;
+    let name = RwSignal::new(social_link.name);
+    let url = RwSignal::new(social_link.url);
+
+    let save = Callback::new(move |()| {
+        let social_link = SocialLink {
+            id,
+            name: name.get_untracked(),
+            url: url.get_untracked(),
+            position,
+        };
+        change_portfolio(set_updated, async move {
+            save_social_link(social_link).await.map(|_| ())
+        });
+    });
+
+    view! {
+        <PortfolioRow
+            table=PortfolioTable::SocialLinks
+            id
+            fields=vec![name, url]
+            save
+            set_updated
+        />
+    }
+}
+
 #[component]
 pub fn BlogSection(
     blog_posts: ReadSignal<Vec<Post>>,
@@ -54,7 +54,7 @@ pub async fn update_post(post: NewPost, post_id: i32) -> Result<u64, ServerFnErr
         return Err(ServerFnError::new("Unauthorized."));
     };
 
-    common::db_query!(
+    let affected = common::db_query!(
         execute,
         r#"
         UPDATE posts
@@ -72,8 +72,12 @@ pub async fn update_post(post: NewPost, post_id: i32) -> Result<u64, ServerFnErr
         let err = format!("Error while getting posts: {e:?}");
         tracing::error!("{err}");
         ServerFnError::new("Could not retrieve posts, try again later")
-    })
-    .map(|r| r.rows_affected())
+    })?
+    .rows_affected();
+
+    crate::pages::rss::feed_changed().await;
+
+    Ok(affected)
 }
 
 #[component]
@@ -3,5 +3,6 @@ pub mod blog_post;
 pub mod edit_blog_post;
 pub mod home;
 pub mod loading;
+pub mod notifications;
 pub mod p404;
 pub mod rss;
@@ -0,0 +1,324 @@
+use leptos::{Params, prelude::*, task::spawn_local};
+use leptos_meta::Title;
+use leptos_router::{hooks::use_query, params::Params};
+use reactive_stores::Store;
+
+use crate::{components::header::Header, pages::loading::LoadingPage};
+use common::{
+    models::*,
+    state::{GlobalState, GlobalStateStoreFields},
+};
+
+#[derive(Params, PartialEq)]
+struct UnsubscribeQuery {
+    token: Option<String>,
+}
+
+#[server(
+    GetNotificationPreferencesAction,
+    "/api",
+    "GetJson",
+    endpoint = "notification_preferences"
+)]
+#[tracing::instrument]
+pub async fn get_notification_preferences() -> Result<NotificationPreferences, ServerFnError> {
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    let Extension(user) = extract::<Extension<User>>()
+        .await
+        .map_err(|_| ServerFnError::new("Unauthorized."))?;
+
+    common::db_query_as!(
+        NotificationPreferences,
+        fetch_optional,
+        "SELECT * FROM notification_preferences WHERE user_id = $1",
+        user.id
+    )
+    .map(Option::unwrap_or_default)
+    .map_err(|e| {
+        let err = format!("Error while getting notification preferences: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not retrieve notification preferences.")
+    })
+}
+
+#[server(
+    UpdateNotificationPreferencesAction,
+    "/api",
+    endpoint = "update_notification_preferences"
+)]
+#[tracing::instrument]
+pub async fn update_notification_preferences(
+    preferences: NotificationPreferences,
+) -> Result<NotificationPreferences, ServerFnError> {
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    let Extension(user) = extract::<Extension<User>>()
+        .await
+        .map_err(|_| ServerFnError::new("Unauthorized."))?;
+
+    let webhook_url = preferences
+        .webhook_url
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty());
+
+    // the server would post to whatever is in here, so only admins get to choose
+    if webhook_url.is_some() && !user.admin {
+        return Err(ServerFnError::new("Only admins can set up webhooks."));
+    }
+
+    if webhook_url
+        .as_ref()
+        .is_some_and(|url| !url.starts_with("https://") && !url.starts_with("http://"))
+    {
+        return Err(ServerFnError::new("Webhook url has to be http(s)."));
+    }
+
+    // the secret is kept as long as there is a webhook, only a new webhook gets a new secret
+    let new_secret = webhook_url
+        .as_ref()
+        .map(|_| uuid::Uuid::new_v4().simple().to_string());
+
+    common::db_query_as!(
+        NotificationPreferences,
+        fetch_one,
+        r#"
+        INSERT INTO notification_preferences (
+            user_id, email_enabled, notify_post_comments, notify_replies, digest,
+            webhook_url, webhook_secret
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (user_id) DO UPDATE SET
+            email_enabled = EXCLUDED.email_enabled,
+            notify_post_comments = EXCLUDED.notify_post_comments,
+            notify_replies = EXCLUDED.notify_replies,
+            digest = EXCLUDED.digest,
+            webhook_url = EXCLUDED.webhook_url,
+            webhook_secret = CASE
+                WHEN EXCLUDED.webhook_url IS NULL THEN NULL
+                ELSE COALESCE(notification_preferences.webhook_secret, EXCLUDED.webhook_secret)
+            END
+        RETURNING *
+        "#,
+        user.id,
+        preferences.email_enabled,
+        preferences.notify_post_comments,
+        preferences.notify_replies,
+        preferences.digest,
+        webhook_url,
+        new_secret,
+    )
+    .map_err(|e| {
+        let err = format!("Error while updating notification preferences: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not update notification preferences.")
+    })
+}
+
+#[server(UnsubscribeAction, "/api", endpoint = "unsubscribe")]
+#[tracing::instrument]
+pub async fn unsubscribe(token: String) -> Result<(), ServerFnError> {
+    let token = uuid::Uuid::parse_str(&token).map_err(|_| ServerFnError::new("Invalid link."))?;
+
+    let affected = common::db_query!(
+        execute,
+        "UPDATE notification_preferences SET email_enabled = false WHERE unsubscribe_token = $1",
+        token
+    )
+    .map_err(|e| {
+        let err = format!("Error while unsubscribing: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not unsubscribe, try again later")
+    })?
+    .rows_affected();
+
+    if affected == 1 {
+        Ok(())
+    } else {
+        Err(ServerFnError::new("Invalid link."))
+    }
+}
+
+#[component]
+pub fn NotificationsPage() -> impl IntoView {
+    let store = expect_context::<Store<GlobalState>>();
+
+    let preferences = RwSignal::new(None::<NotificationPreferences>);
+    let (message, set_message) = signal(String::new());
+
+    let preferences_res = Resource::new(
+        move || store.user().get().map(|u| u.id),
+        |user| async move {
+            match user {
+                Some(_) => get_notification_preferences().await.ok(),
+                None => None,
+            }
+        },
+    );
+
+    Effect::new(move |_| {
+        preferences.set(preferences_res.get().flatten());
+    });
+
+    let save = move |_| {
+        let Some(p) = preferences.get_untracked() else {
+            return;
+        };
+        spawn_local(async move {
+            match update_notification_preferences(p).await {
+                Ok(p) => {
+                    preferences.set(Some(p));
+                    set_message("Saved.".into());
+                }
+                Err(e) => set_message(e.to_string()),
+            }
+        });
+    };
+
+    let checkbox = move |label: &'static str,
+                         get: fn(&NotificationPreferences) -> bool,
+                         set: fn(&mut NotificationPreferences, bool)| {
+        view! {
+            <label class="flex items-center gap-2 text-sm text-gray-900">
+                <input
+                    type="checkbox"
+                    prop:checked=move || preferences.get().as_ref().is_some_and(get)
+                    on:change=move |ev| {
+                        let checked = event_target_checked(&ev);
+                        preferences.update(|p| p.iter_mut().for_each(|p| set(p, checked)));
+                    }
+                />
+                {label}
+            </label>
+        }
+    };
+
+    view! {
+        <Title text="Notification Settings" />
+
+        <Header />
+        <div class="py-12 px-4 md:px-0 md:mx-auto md:w-[48rem]">
+            <h1 class="text-4xl font-bold mb-6">Notifications</h1>
+            <Show
+                when=move || store.user().get().is_some()
+                fallback=|| {
+                    view! { <p>"You need to be logged in to change your notifications."</p> }
+                }
+            >
+                <Show when=move || preferences.get().is_some() fallback=LoadingPage>
+                    <div class="space-y-3">
+                        {checkbox(
+                            "Send me emails",
+                            |p| p.email_enabled,
+                            |p, v| p.email_enabled = v,
+                        )}
+                        {checkbox(
+                            "Notify me about comments on my posts",
+                            |p| p.notify_post_comments,
+                            |p, v| p.notify_post_comments = v,
+                        )}
+                        {checkbox(
+                            "Notify me about replies to my comments",
+                            |p| p.notify_replies,
+                            |p, v| p.notify_replies = v,
+                        )}
+                        {checkbox(
+                            "Send one daily digest instead of single mails",
+                            |p| p.digest,
+                            |p, v| p.digest = v,
+                        )}
+                        <Show when=move || store.user().get().is_some_and(|u| u.is_admin)>
+                            <label class="block text-sm font-medium text-gray-700">
+                                Webhook URL
+                            </label>
+                            <input
+                                class="w-full p-2 border rounded"
+                                placeholder="https://example.com/hook"
+                                prop:value=move || {
+                                    preferences.get().and_then(|p| p.webhook_url).unwrap_or_default()
+                                }
+                                on:input=move |ev| {
+                                    let url = event_target_value(&ev);
+                                    preferences
+                                        .update(|p| {
+                                            p.iter_mut().for_each(|p| p.webhook_url = Some(url.clone()))
+                                        });
+                                }
+                            />
+                            {move || {
+                                preferences
+                                    .get()
+                                    .and_then(|p| p.webhook_secret)
+                                    .map(|secret| {
+                                        view! {
+                                            <p class="text-xs text-gray-600">
+                                                "Payloads are signed with HMAC-SHA256 over "
+                                                <code>"{timestamp}.{body}"</code>
+                                                " using the secret "
+                                                <code>{secret}</code>
+                                            </p>
+                                        }
+                                    })
+                            }}
+                        </Show>
+                        <button
+                            class="border-none inline-block rounded bg-indigo-600 px-4 py-2 text-xs font-medium text-white hover:bg-indigo-700"
+                            on:click=save
+                        >
+                            Save
+                        </button>
+                        <p class="text-sm text-gray-600">{message}</p>
+                    </div>
+                </Show>
+            </Show>
+        </div>
+    }
+}
+
+#[component]
+pub fn UnsubscribePage() -> impl IntoView {
+    let query = use_query::<UnsubscribeQuery>();
+
+    let result = Resource::new(
+        move || query.with(|q| q.as_ref().ok().and_then(|q| q.token.clone())),
+        |token| async move {
+            match token {
+                Some(token) => unsubscribe(token).await,
+                None => Err(ServerFnError::new("Invalid link.")),
+            }
+        },
+    );
+
+    view! {
+        <Title text="Unsubscribe" />
+
+        <Header />
+        <div class="grid h-[60vh] place-content-center px-4 text-center">
+            <Suspense fallback=LoadingPage>
+                {move || {
+                    result
+                        .get()
+                        .map(|r| match r {
+                            Ok(()) => {
+                                view! {
+                                    <p class="text-2xl font-bold">
+                                        "You will not get any more mails from us."
+                                    </p>
+                                }
+                                    .into_any()
+                            }
+                            Err(e) => {
+                                view! { <p class="text-2xl font-bold">{e.to_string()}</p> }
+                                    .into_any()
+                            }
+                        })
+                }}
+                <a href="/notifications" class="mt-6 underline text-nf-color">
+                    Notification settings
+                </a>
+            </Suspense>
+        </div>
+    }
+}
@@ -104,9 +104,54 @@ impl RssEntry {
     }
 }
 
+/// The rendered feed, kept until [`RegenerateFeedJob`] replaces it.
+#[cfg(feature = "back")]
+static FEED: std::sync::RwLock<Option<String>> = std::sync::RwLock::new(None);
+
+/// Renders the feed again after posts changed.
+#[cfg(feature = "back")]
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RegenerateFeedJob {}
+
+#[cfg(feature = "back")]
+impl common::jobs::Job for RegenerateFeedJob {
+    const KIND: &'static str = "blog::regenerate_feed";
+
+    async fn run(self) -> Result<(), String> {
+        let feed = build_feed().await.map_err(|e| e.to_string())?;
+        *FEED.write().unwrap() = Some(feed);
+        Ok(())
+    }
+}
+
+/// Queues a [`RegenerateFeedJob`], to be called whenever released posts change.
+#[cfg(feature = "back")]
+pub async fn feed_changed() {
+    if let Err(e) = common::jobs::enqueue_once(&RegenerateFeedJob {}).await {
+        tracing::error!("Error while queueing feed regeneration: {e:?}");
+    }
+}
+
 #[server(Rss, "/api", "GetJson", endpoint = "rss.xml")]
 #[tracing::instrument]
 pub async fn rss() -> Result<String, ServerFnError> {
+    if let Some(feed) = FEED.read().unwrap().clone() {
+        return Ok(feed);
+    }
+
+    let feed = build_feed().await.map_err(|e| {
+        let err = format!("Error while getting posts: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not retrieve posts, try again later")
+    })?;
+
+    *FEED.write().unwrap() = Some(feed.clone());
+
+    Ok(feed)
+}
+
+#[cfg(feature = "back")]
+async fn build_feed() -> Result<String, common::db::sqlx::Error> {
     Ok(generate_rss(
         "Nicolas' Blog",
         "Ramblings of a Rust Developer",
@@ -129,12 +174,7 @@ pub async fn rss() -> Result<String, ServerFnError> {
             FROM posts
             JOIN users ON posts.author = users.id
             WHERE released = true ORDER BY release_date DESC"#
-        )
-        .map_err(|e| {
-            let err = format!("Error while getting posts: {e:?}");
-            tracing::error!("{err}");
-            ServerFnError::new("Could not retrieve posts, try again later")
-        })?,
+        )?,
     ))
 }
 
@@ -25,10 +25,6 @@ impl Apps {
         string.as_ref().starts_with(self.prefix())
     }
 
-    pub fn is_leptos(&self) -> bool {
-        !matches!(self, Apps::Files | Apps::SandBox)
-    }
-
     pub fn url(&self) -> String {
         format!("{}://{}{}", crate::PROTOCOL, self.prefix(), crate::DOMAIN)
     }
@@ -0,0 +1,117 @@
+//! Calls from the browser to the json apis of the files and sandbox apps.
+//!
+//! Errors come back as the messages the api sent, see `api::ApiError`.
+
+use std::{cell::RefCell, rc::Rc};
+
+use futures::channel::oneshot;
+use gloo_net::http::{Request, RequestBuilder, Response};
+use serde::{Serialize, de::DeserializeOwned};
+use wasm_bindgen::{JsCast, JsValue, closure::Closure};
+use web_sys::{FormData, ProgressEvent, XmlHttpRequest};
+
+/// Sends a request, turning answers other than success into their messages.
+pub async fn send(request: RequestBuilder) -> Result<Response, String> {
+    execute(request.build().map_err(|e| e.to_string())?).await
+}
+
+/// Sends `body` as json, like [`send`].
+pub async fn send_with<B: Serialize>(
+    request: RequestBuilder,
+    body: &B,
+) -> Result<Response, String> {
+    execute(request.json(body).map_err(|e| e.to_string())?).await
+}
+
+/// Sends a request and reads the json it answers with.
+pub async fn fetch<T: DeserializeOwned>(request: RequestBuilder) -> Result<T, String> {
+    read(send(request).await?).await
+}
+
+/// Sends `body` as json and reads the json it answers with.
+pub async fn fetch_with<B: Serialize, T: DeserializeOwned>(
+    request: RequestBuilder,
+    body: &B,
+) -> Result<T, String> {
+    read(send_with(request, body).await?).await
+}
+
+async fn execute(request: Request) -> Result<Response, String> {
+    let response = request.send().await.map_err(|e| e.to_string())?;
+
+    if response.ok() {
+        Ok(response)
+    } else {
+        Err(messages(
+            response.status(),
+            &response.text().await.unwrap_or_default(),
+        ))
+    }
+}
+
+async fn read<T: DeserializeOwned>(response: Response) -> Result<T, String> {
+    response.json().await.map_err(|e| e.to_string())
+}
+
+/// Sends files as `multipart/form-data` in the field `file`, calling `on_progress` with
+/// the bytes sent so far and in total, which `fetch` can not do.
+///
+/// Returns the body of the answer.
+pub async fn upload(
+    method: &str,
+    url: &str,
+    files: &[web_sys::File],
+    on_progress: impl Fn(f64, f64) + 'static,
+) -> Result<String, String> {
+    let form = FormData::new().map_err(js_error)?;
+    for file in files {
+        form.append_with_blob_and_filename("file", file, &file.name())
+            .map_err(js_error)?;
+    }
+
+    let xhr = XmlHttpRequest::new().map_err(js_error)?;
+    xhr.open(method, url).map_err(js_error)?;
+
+    let progress = Closure::<dyn Fn(ProgressEvent)>::new(move |e: ProgressEvent| {
+        if e.length_computable() {
+            on_progress(e.loaded(), e.total());
+        }
+    });
+    xhr.upload()
+        .map_err(js_error)?
+        .set_onprogress(Some(progress.as_ref().unchecked_ref()));
+
+    // fired once the request is over, whether it worked or not
+    let (done_tx, done_rx) = oneshot::channel::<()>();
+    let done_tx = Rc::new(RefCell::new(Some(done_tx)));
+    let done = Closure::<dyn Fn()>::new(move || {
+        if let Some(tx) = done_tx.borrow_mut().take() {
+            let _ = tx.send(());
+        }
+    });
+    xhr.set_onloadend(Some(done.as_ref().unchecked_ref()));
+
+    xhr.send_with_opt_form_data(Some(&form)).map_err(js_error)?;
+    let _ = done_rx.await;
+
+    let status = xhr.status().map_err(js_error)?;
+    let body = xhr.response_text().map_err(js_error)?.unwrap_or_default();
+
+    match status {
+        0 => Err("The upload was interrupted.".to_string()),
+        200..=299 => Ok(body),
+        _ => Err(messages(status, &body)),
+    }
+}
+
+/// The messages of an error answer, or its status if it has none.
+fn messages(status: u16, body: &str) -> String {
+    match serde_json::from_str::<Vec<String>>(body) {
+        Ok(messages) if !messages.is_empty() => messages.join("\n"),
+        _ => format!("Request failed with status {status}."),
+    }
+}
+
+fn js_error(e: JsValue) -> String {
+    e.as_string().unwrap_or_else(|| format!("{e:?}"))
+}
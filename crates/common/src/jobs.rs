@@ -0,0 +1,354 @@
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::sync::Notify;
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+type Handler = fn(String) -> BoxFuture<Result<(), String>>;
+type GiveUpHandler = fn(String) -> BoxFuture<()>;
+
+/// How long an idle worker waits before looking for due jobs again.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Delay before the first retry, doubled with every further attempt.
+const BACKOFF_BASE_SECS: i64 = 30;
+const BACKOFF_MAX_SECS: i64 = 60 * 60;
+/// How often a worker tells that it is still running its job.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(60);
+/// Running jobs without a heartbeat for this long are assumed to belong to a dead worker.
+const STALE_AFTER_MINUTES: i32 = 5;
+/// Finished jobs are kept this long to be looked at in the admin page.
+const KEEP_FINISHED_DAYS: i32 = 7;
+const DEFAULT_MAX_ATTEMPTS: i32 = 5;
+
+static WAKE: Notify = Notify::const_new();
+
+/// Work that is too slow for a request handler.
+///
+/// The job is stored as json in the `jobs` table under [`Job::KIND`], so it can be picked up
+/// by any worker, even after a restart. Failed jobs are retried with exponential backoff
+/// until [`Job::MAX_ATTEMPTS`] is reached, which means `run` has to be safe to repeat.
+pub trait Job: Serialize + DeserializeOwned + Send + 'static {
+    /// Unique name of the job, has to stay the same as long as such jobs can be queued.
+    const KIND: &'static str;
+    const MAX_ATTEMPTS: i32 = DEFAULT_MAX_ATTEMPTS;
+
+    fn run(self) -> impl Future<Output = Result<(), String>> + Send;
+
+    /// Called once the last attempt failed, to clean up what `run` would have taken care of.
+    fn give_up(self) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+}
+
+/// Puts a job into the queue, it will run as soon as a worker is free.
+pub async fn enqueue<J: Job>(job: &J) -> Result<i64, sqlx::Error> {
+    let payload = serde_json::to_string(job).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+
+    let id = common::db_query_scalar!(
+        i64,
+        fetch_one,
+        r#"
+        INSERT INTO jobs (kind, payload, max_attempts)
+        VALUES ($1, $2::JSONB, $3)
+        RETURNING id
+        "#,
+        J::KIND,
+        payload,
+        J::MAX_ATTEMPTS,
+    )?;
+
+    WAKE.notify_one();
+
+    Ok(id)
+}
+
+/// Kinds of jobs queued from crates that can not depend on the one running them.
+pub mod kinds {
+    /// `www::cv::BuildCvJob`, queued from the blog admin page.
+    pub const BUILD_CV: &str = "www::build_cv";
+}
+
+/// Like [`enqueue`] but only if no job of the same kind is waiting to run for the first time,
+/// for jobs where running once more covers everything queued since.
+pub async fn enqueue_once<J: Job>(job: &J) -> Result<Option<i64>, sqlx::Error> {
+    let payload = serde_json::to_string(job).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+
+    insert_once(J::KIND, payload, J::MAX_ATTEMPTS, Duration::ZERO).await
+}
+
+/// Like [`enqueue_once`] but the job only runs after `delay`, for jobs that queue their
+/// next run themselves.
+pub async fn enqueue_once_in<J: Job>(job: &J, delay: Duration) -> Result<Option<i64>, sqlx::Error> {
+    let payload = serde_json::to_string(job).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+
+    insert_once(J::KIND, payload, J::MAX_ATTEMPTS, delay).await
+}
+
+/// How long ago a job of this kind last finished successfully, if that is still kept.
+pub async fn since_last_done<J: Job>() -> Result<Option<Duration>, sqlx::Error> {
+    let seconds = common::db_query_scalar!(
+        Option<f64>,
+        fetch_one,
+        r#"
+        SELECT EXTRACT(EPOCH FROM NOW() - MAX(finished_at))::FLOAT8 FROM jobs
+        WHERE kind = $1 AND status = 'done'
+        "#,
+        J::KIND
+    )?;
+
+    Ok(seconds.map(|seconds| Duration::from_secs_f64(seconds.max(0.0))))
+}
+
+/// Like [`enqueue_once`] for a job only known by one of the [`kinds`], which has to be a job
+/// without fields and with the default [`Job::MAX_ATTEMPTS`].
+pub async fn enqueue_kind_once(kind: &str) -> Result<Option<i64>, sqlx::Error> {
+    insert_once(kind, "{}".to_string(), DEFAULT_MAX_ATTEMPTS, Duration::ZERO).await
+}
+
+async fn insert_once(
+    kind: &str,
+    payload: String,
+    max_attempts: i32,
+    delay: Duration,
+) -> Result<Option<i64>, sqlx::Error> {
+    // the unique index on waiting `once` jobs makes this safe against concurrent calls
+    let id = common::db_query_scalar!(
+        i64,
+        fetch_optional,
+        r#"
+        INSERT INTO jobs (kind, payload, max_attempts, once, run_at)
+        VALUES ($1, $2::JSONB, $3, TRUE, NOW() + make_interval(secs => $4))
+        ON CONFLICT (kind) WHERE status = 'queued' AND once DO NOTHING
+        RETURNING id
+        "#,
+        kind,
+        payload,
+        max_attempts,
+        delay.as_secs_f64(),
+    )?;
+
+    if id.is_some() {
+        WAKE.notify_one();
+    }
+
+    Ok(id)
+}
+
+/// Puts a failed job back into the queue with a fresh set of attempts.
+pub async fn retry(id: i64) -> Result<u64, sqlx::Error> {
+    let affected = common::db_query!(
+        execute,
+        r#"
+        UPDATE jobs
+        SET status = 'queued', attempts = 0, run_at = NOW(), finished_at = NULL
+        WHERE id = $1 AND status = 'failed'
+        "#,
+        id
+    )?
+    .rows_affected();
+
+    WAKE.notify_one();
+
+    Ok(affected)
+}
+
+/// The set of jobs this process knows how to run.
+///
+/// ```ignore
+/// Workers::default()
+///     .register::<DeleteDirectoryJob>()
+///     .start(4);
+/// ```
+#[derive(Default, Clone)]
+pub struct Workers {
+    handlers: HashMap<&'static str, (Handler, GiveUpHandler)>,
+}
+
+impl Workers {
+    pub fn register<J: Job>(mut self) -> Self {
+        self.handlers.insert(J::KIND, (handle::<J>, give_up::<J>));
+        self
+    }
+
+    /// Spawns `count` workers polling the queue for the registered jobs.
+    pub fn start(self, count: usize) {
+        let workers = Arc::new(self);
+
+        for _ in 0..count {
+            let workers = workers.clone();
+            tokio::spawn(async move { workers.work().await });
+        }
+    }
+
+    async fn work(&self) {
+        let kinds = self.handlers.keys().copied().collect::<Vec<_>>();
+
+        loop {
+            match claim(&kinds).await {
+                Ok(Some(job)) => self.run(job).await,
+                Ok(None) => {
+                    if let Err(e) = housekeeping().await {
+                        tracing::error!("Error while cleaning up jobs: {e:?}");
+                    }
+                    let _ = tokio::time::timeout(POLL_INTERVAL, WAKE.notified()).await;
+                }
+                Err(e) => {
+                    tracing::error!("Error while claiming job: {e:?}");
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+            }
+        }
+    }
+
+    #[tracing::instrument(skip(self, job), fields(id = job.id, kind = %job.kind))]
+    async fn run(&self, job: ClaimedJob) {
+        // claim only hands out registered kinds
+        let (handler, give_up) = self.handlers[job.kind.as_str()];
+
+        let mut task = tokio::spawn(handler(job.payload.clone()));
+
+        // keeps housekeeping from handing the job to another worker while it is still running
+        let joined = loop {
+            match tokio::time::timeout(HEARTBEAT_INTERVAL, &mut task).await {
+                Ok(joined) => break joined,
+                Err(_) => {
+                    if let Err(e) = heartbeat(job.id).await {
+                        tracing::error!("Error while refreshing job lock: {e:?}");
+                    }
+                }
+            }
+        };
+
+        let result = match joined {
+            Ok(result) => result,
+            Err(e) => Err(format!("job panicked: {e}")),
+        };
+
+        let finished = match result {
+            Ok(()) => {
+                common::db_query!(
+                    execute,
+                    r#"
+                    UPDATE jobs
+                    SET status = 'done', last_error = NULL, finished_at = NOW()
+                    WHERE id = $1
+                    "#,
+                    job.id
+                )
+            }
+            Err(err) if job.attempts >= job.max_attempts => {
+                tracing::error!("Job failed for good: {err}");
+                give_up(job.payload).await;
+                common::db_query!(
+                    execute,
+                    r#"
+                    UPDATE jobs
+                    SET status = 'failed', last_error = $2, finished_at = NOW()
+                    WHERE id = $1
+                    "#,
+                    job.id,
+                    err
+                )
+            }
+            Err(err) => {
+                tracing::warn!("Job failed, retrying: {err}");
+                common::db_query!(
+                    execute,
+                    r#"
+                    UPDATE jobs
+                    SET status = 'queued', last_error = $2,
+                        run_at = NOW() + make_interval(secs => $3)
+                    WHERE id = $1
+                    "#,
+                    job.id,
+                    err,
+                    backoff(job.attempts) as f64
+                )
+            }
+        };
+
+        if let Err(e) = finished {
+            tracing::error!("Error while finishing job: {e:?}");
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ClaimedJob {
+    id: i64,
+    kind: String,
+    payload: String,
+    attempts: i32,
+    max_attempts: i32,
+}
+
+fn handle<J: Job>(payload: String) -> BoxFuture<Result<(), String>> {
+    Box::pin(async move {
+        let job = serde_json::from_str::<J>(&payload).map_err(|e| e.to_string())?;
+        job.run().await
+    })
+}
+
+fn give_up<J: Job>(payload: String) -> BoxFuture<()> {
+    Box::pin(async move {
+        match serde_json::from_str::<J>(&payload) {
+            Ok(job) => job.give_up().await,
+            Err(e) => tracing::error!("Could not give up on job: {e}"),
+        }
+    })
+}
+
+fn backoff(attempts: i32) -> i64 {
+    let exponent = attempts.clamp(1, 16) as u32 - 1;
+    (BACKOFF_BASE_SECS << exponent).min(BACKOFF_MAX_SECS)
+}
+
+/// Takes the oldest due job, skipping the ones other workers are holding on to.
+async fn claim(kinds: &[&'static str]) -> Result<Option<ClaimedJob>, sqlx::Error> {
+    common::db_query_as!(
+        ClaimedJob,
+        fetch_optional,
+        r#"
+        UPDATE jobs
+        SET status = 'running', attempts = attempts + 1, locked_at = NOW(), once = FALSE
+        WHERE id = (
+            SELECT id FROM jobs
+            WHERE status = 'queued' AND run_at <= NOW() AND kind = ANY($1)
+            ORDER BY run_at
+            FOR UPDATE SKIP LOCKED
+            LIMIT 1
+        )
+        RETURNING id, kind, payload::TEXT AS payload, attempts, max_attempts
+        "#,
+        kinds
+    )
+}
+
+async fn heartbeat(id: i64) -> Result<(), sqlx::Error> {
+    common::db_query!(
+        execute,
+        "UPDATE jobs SET locked_at = NOW() WHERE id = $1 AND status = 'running'",
+        id
+    )
+    .map(|_| ())
+}
+
+/// Requeues jobs of crashed workers and drops old finished ones.
+async fn housekeeping() -> Result<(), sqlx::Error> {
+    common::db_query!(
+        execute,
+        r#"
+        UPDATE jobs
+        SET status = 'queued', last_error = 'worker went away'
+        WHERE status = 'running' AND locked_at < NOW() - make_interval(mins => $1)
+        "#,
+        STALE_AFTER_MINUTES
+    )?;
+
+    common::db_query!(
+        execute,
+        "DELETE FROM jobs WHERE status = 'done' AND finished_at < NOW() - make_interval(days => $1)",
+        KEEP_FINISHED_DAYS
+    )
+    .map(|_| ())
+}
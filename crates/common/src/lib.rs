@@ -1,14 +1,22 @@
+// lets the `db_query` macros name the crate the same way inside of it as outside
+extern crate self as common;
+
 #[cfg(feature = "back")]
 pub mod api;
 pub mod apps;
 #[cfg(feature = "back")]
 pub mod auth;
+pub mod client;
 #[cfg(feature = "back")]
 pub mod db;
 #[cfg(feature = "back")]
+pub mod jobs;
+#[cfg(feature = "back")]
 pub mod trace;
 pub use apps::*;
 pub mod models;
+pub mod portfolio;
+pub mod state;
 pub mod ui;
 
 pub static EMAIL: &str = "nicolas.theo.frey@gmail.com";
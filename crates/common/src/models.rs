@@ -13,6 +13,80 @@ pub struct SandboxPage {
     #[cfg(not(feature = "back"))]
     pub id: String,
     pub slug: String,
+    /// replacing the default response headers, an empty value drops the header
+    #[cfg_attr(feature = "back", sqlx(json))]
+    pub headers: std::collections::HashMap<String, String>,
+    pub title: String,
+    pub description: String,
+    pub tags: Vec<String>,
+    /// an image inside the page, relative to it
+    pub thumbnail: Option<String>,
+    /// public pages are in the gallery, unlisted ones are only served, private ones neither
+    pub visibility: Visibility,
+    pub created_at: chrono::NaiveDateTime,
+    /// when the metadata changed or another version went live
+    pub updated_at: Option<chrono::NaiveDateTime>,
+}
+
+/// What the page manager can change about a sandbox page besides its content.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct SandboxPageMeta {
+    pub title: String,
+    pub description: String,
+    pub tags: Vec<String>,
+    pub thumbnail: Option<String>,
+    pub visibility: Visibility,
+}
+
+/// A public sandbox page as listed in the gallery.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct GalleryPage {
+    pub slug: String,
+    pub title: String,
+    pub description: String,
+    pub tags: Vec<String>,
+    pub url: String,
+    pub thumbnail_url: Option<String>,
+    /// html showing the page inside of another one, see [`sandbox_embed`]
+    pub embed: String,
+    /// fenced code block the blog turns into the [`embed`](Self::embed)
+    pub markdown: String,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: Option<chrono::NaiveDateTime>,
+}
+
+/// An iframe with the sandbox page under `slug`, the page brings its own sandboxing.
+pub fn sandbox_embed(slug: &str, title: &str) -> String {
+    let escape = |value: &str| {
+        value
+            .replace('&', "&amp;")
+            .replace('"', "&quot;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    };
+
+    format!(
+        "<iframe src=\"{}/{}\" title=\"{}\" loading=\"lazy\" allow=\"fullscreen\" \
+        style=\"width: 100%; aspect-ratio: 16 / 9; border: 0;\"></iframe>",
+        crate::Apps::SandBox.url(),
+        escape(slug),
+        escape(title),
+    )
+}
+
+/// One upload of a sandbox page, kept around to roll back to.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "back", derive(sqlx::FromRow))]
+pub struct SandboxVersion {
+    pub version: i32,
+    pub directory_id: i32,
+    pub created_at: chrono::NaiveDateTime,
+    /// missing while the upload is still being extracted
+    pub extracted_at: Option<chrono::NaiveDateTime>,
+    /// set if extracting the upload was given up on
+    pub failed_at: Option<chrono::NaiveDateTime>,
+    /// the one being served
+    pub current: bool,
 }
 
 #[derive(Default, Clone, Debug, Serialize, Deserialize)]
@@ -22,6 +96,10 @@ pub struct Directory {
     pub parent_id: Option<i32>,
     pub dir_name: String,
     pub dir_path: String,
+    /// inherited from the parent if not set
+    pub visibility: Option<Visibility>,
+    /// in the trash since
+    pub deleted_at: Option<chrono::NaiveDateTime>,
 }
 
 #[cfg(feature = "back")]
@@ -32,6 +110,8 @@ impl Directory {
             parent_id: None,
             dir_name: FILE_ROOT.to_string(),
             dir_path: FILE_ROOT.to_string(),
+            visibility: Some(Visibility::Public),
+            deleted_at: None,
         }
     }
 }
@@ -48,6 +128,123 @@ pub struct File {
     pub mime_type: String,
     pub uploaded_at: chrono::NaiveDateTime,
     pub file_path: String,
+    /// in bytes
+    pub size: Option<i64>,
+    /// hex encoded SHA-256 of the content
+    pub sha256: Option<String>,
+    /// inherited from the directory if not set
+    pub visibility: Option<Visibility>,
+    /// in the trash since
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+}
+
+/// Who gets to download a file without a share link.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+    strum::EnumIter,
+    strum::Display,
+)]
+#[cfg_attr(feature = "back", derive(sqlx::Type))]
+#[cfg_attr(
+    feature = "back",
+    sqlx(type_name = "visibility", rename_all = "lowercase")
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Visibility {
+    /// anyone knowing the path
+    #[default]
+    Public,
+    /// anyone knowing the id
+    Unlisted,
+    /// admins only
+    Private,
+}
+
+/// A link handing out a file or directory to people without an account.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "back", derive(sqlx::FromRow))]
+pub struct Share {
+    #[cfg(feature = "back")]
+    pub id: uuid::Uuid,
+    #[cfg(not(feature = "back"))]
+    pub id: String,
+    #[cfg(feature = "back")]
+    pub file_id: Option<uuid::Uuid>,
+    #[cfg(not(feature = "back"))]
+    pub file_id: Option<String>,
+    pub directory_id: Option<i32>,
+    pub expires_at: Option<chrono::NaiveDateTime>,
+    pub max_downloads: Option<i32>,
+    pub downloads: i32,
+    #[serde(skip)]
+    pub password_hash: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+/// A link to share a file or directory with.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct ShareRequest {
+    /// never expires if missing
+    pub expires_in_hours: Option<i64>,
+    /// unlimited if missing
+    pub max_downloads: Option<i32>,
+    pub password: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct ShareLink {
+    #[serde(flatten)]
+    pub share: Share,
+    /// relative to the files app
+    pub url: String,
+}
+
+/// What may be uploaded into a directory, unset fields are inherited from its parent.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "back", derive(sqlx::FromRow))]
+pub struct DirectoryPolicy {
+    /// anything not denied if missing, `image/*` matches all images
+    pub allowed_mime_types: Option<Vec<String>>,
+    pub denied_mime_types: Option<Vec<String>>,
+    /// in bytes
+    pub max_file_size: Option<i64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct PolicyResponse {
+    /// set on the directory itself
+    pub own: DirectoryPolicy,
+    /// what uploads into it are checked against
+    pub effective: DirectoryPolicy,
+}
+
+/// How much of the file storage is used.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct StorageUsage {
+    /// in bytes, content shared by several files only counts once
+    pub used: i64,
+    /// in bytes, unlimited if missing
+    pub quota: Option<i64>,
+    /// in bytes, the part of `used` only held by the trash
+    pub trash: i64,
+    pub files: i64,
+}
+
+/// What was deleted by itself, the contents of deleted directories come back with them.
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct Trash {
+    pub files: Vec<File>,
+    pub directories: Vec<Directory>,
+    pub retention_days: i32,
 }
 
 #[derive(Default, Clone, Debug, Serialize, Deserialize)]
@@ -91,6 +288,9 @@ pub struct Post {
     pub release_date: Option<chrono::NaiveDateTime>,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: Option<chrono::NaiveDateTime>,
+    /// widths of the images hosted in the files app, by url
+    #[cfg_attr(feature = "back", sqlx(skip))]
+    pub image_widths: std::collections::HashMap<String, u32>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
@@ -126,6 +326,34 @@ pub struct NewPost {
     pub markdown_content: String,
 }
 
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+    strum::EnumIter,
+    strum::Display,
+)]
+#[cfg_attr(feature = "back", derive(sqlx::Type))]
+#[cfg_attr(
+    feature = "back",
+    sqlx(type_name = "comment_status", rename_all = "lowercase")
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum CommentStatus {
+    #[default]
+    Pending,
+    Approved,
+    Rejected,
+    Spam,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 #[cfg_attr(feature = "back", derive(sqlx::FromRow))]
 pub struct Comment {
@@ -134,6 +362,44 @@ pub struct Comment {
     pub author_id: Option<i32>,
     pub content: String,
     pub replying_to: Option<i32>,
+    pub status: CommentStatus,
+    pub created_at: chrono::NaiveDateTime,
+    pub updated_at: Option<chrono::NaiveDateTime>,
+    #[cfg_attr(feature = "back", sqlx(skip))]
+    pub reactions: Vec<ReactionCount>,
+}
+
+/// How often an emoji was used on a comment and whether the current user is among them.
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+#[cfg_attr(feature = "back", derive(sqlx::FromRow))]
+pub struct ReactionCount {
+    pub comment_id: i32,
+    pub emoji: String,
+    pub count: i64,
+    pub reacted: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "back", derive(sqlx::FromRow))]
+pub struct CommentRevision {
+    pub id: i32,
+    pub comment_id: i32,
+    pub content: String,
+    pub edited_at: chrono::NaiveDateTime,
+}
+
+/// A comment as seen from the moderation queue, with the post it belongs to.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "back", derive(sqlx::FromRow))]
+pub struct ModerationComment {
+    pub id: i32,
+    pub author_name: Option<String>,
+    pub author_id: Option<i32>,
+    pub post_title: String,
+    pub post_slug: String,
+    pub content: String,
+    pub status: CommentStatus,
+    pub spam_score: f32,
     pub created_at: chrono::NaiveDateTime,
 }
 
@@ -141,4 +407,144 @@ pub struct Comment {
 #[cfg_attr(feature = "back", derive(sqlx::FromRow))]
 pub struct NewComment {
     pub content: String,
+    pub replying_to: Option<i32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "back", derive(sqlx::FromRow))]
+pub struct NotificationPreferences {
+    pub email_enabled: bool,
+    pub notify_post_comments: bool,
+    pub notify_replies: bool,
+    /// collect notifications into one daily mail instead of sending them right away
+    pub digest: bool,
+    pub webhook_url: Option<String>,
+    /// used to sign webhook payloads, generated once a webhook url is set
+    pub webhook_secret: Option<String>,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            email_enabled: true,
+            notify_post_comments: true,
+            notify_replies: true,
+            digest: false,
+            webhook_url: None,
+            webhook_secret: None,
+        }
+    }
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    Serialize,
+    Deserialize,
+    strum::EnumIter,
+    strum::Display,
+)]
+#[cfg_attr(feature = "back", derive(sqlx::Type))]
+#[cfg_attr(
+    feature = "back",
+    sqlx(type_name = "job_status", rename_all = "lowercase")
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum JobStatus {
+    #[default]
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+/// A row of the job queue as shown to admins, see `common::jobs` for running them.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "back", derive(sqlx::FromRow))]
+pub struct JobRecord {
+    pub id: i64,
+    pub kind: String,
+    pub payload: String,
+    pub status: JobStatus,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub last_error: Option<String>,
+    pub run_at: chrono::NaiveDateTime,
+    pub created_at: chrono::NaiveDateTime,
+    pub finished_at: Option<chrono::NaiveDateTime>,
+}
+
+/// A job shown on the www page, see `common::portfolio` for the other parts of it.
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+#[cfg_attr(feature = "back", derive(sqlx::FromRow))]
+pub struct Experience {
+    pub id: i32,
+    pub title: String,
+    pub company: String,
+    pub url: String,
+    /// free text, like `2023 - 2024`
+    pub period: String,
+    pub position: i32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+#[cfg_attr(feature = "back", derive(sqlx::FromRow))]
+pub struct Skill {
+    pub id: i32,
+    pub name: String,
+    pub position: i32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+#[cfg_attr(feature = "back", derive(sqlx::FromRow))]
+pub struct Project {
+    pub id: i32,
+    pub name: String,
+    /// the page of the project on www, `/projects/{slug}`
+    pub slug: String,
+    pub description: String,
+    pub tags: Vec<String>,
+    /// a website of the project
+    pub url: Option<String>,
+    pub repository: Option<String>,
+    /// the sandbox page with a live demo
+    pub sandbox_slug: Option<String>,
+    /// slugs of related blog posts
+    pub posts: Vec<String>,
+    pub position: i32,
+}
+
+/// A project with what its page on www shows besides it.
+#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+pub struct ProjectDetails {
+    pub project: Project,
+    /// where the live demo is served, missing without one or if the page is private
+    pub demo_url: Option<String>,
+    /// the released ones of the related blog posts
+    pub posts: Vec<ProjectPost>,
+}
+
+/// A blog post related to a project.
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+#[cfg_attr(feature = "back", derive(sqlx::FromRow))]
+pub struct ProjectPost {
+    pub slug: String,
+    pub title: String,
+    pub description: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+#[cfg_attr(feature = "back", derive(sqlx::FromRow))]
+pub struct SocialLink {
+    pub id: i32,
+    /// also the icon, `/assets/{name}.svg`
+    pub name: String,
+    pub url: String,
+    pub position: i32,
 }
@@ -0,0 +1,574 @@
+//! What the www page shows besides its texts, kept in the database so it changes
+//! without a redeploy.
+//!
+//! Anyone can read it, admins edit it in the portfolio tab of the blog admin.
+
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Experience, Project, ProjectDetails, Skill, SocialLink};
+
+/// The tables of the portfolio, each ordered by its `position`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, strum::Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum PortfolioTable {
+    Experience,
+    Skills,
+    Projects,
+    SocialLinks,
+}
+
+#[cfg(feature = "back")]
+fn require(field: &str, value: &str) -> Result<(), ServerFnError> {
+    if value.trim().is_empty() {
+        return Err(ServerFnError::new(format!("The {field} can not be empty.")));
+    }
+
+    Ok(())
+}
+
+/// Turns a name like "C/C++ Engine" into "c-c-engine", keeping only lowercase letters and
+/// digits with single dashes between them.
+#[cfg(feature = "back")]
+fn slugify(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Slugs end up in the project URLs, so they are held to the same rules as sandbox slugs.
+#[cfg(feature = "back")]
+fn check_slug(slug: &str) -> Result<(), ServerFnError> {
+    let valid = !slug.is_empty()
+        && !slug.starts_with('.')
+        && slug
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'));
+
+    if !valid {
+        return Err(ServerFnError::new(
+            "Slugs may only contain letters, digits, '-', '_' and '.' and not start with '.'.",
+        ));
+    }
+
+    Ok(())
+}
+
+#[server(GetExperienceAction, "/api", "GetJson", endpoint = "experience")]
+#[cfg_attr(feature = "back", tracing::instrument)]
+pub async fn get_experience() -> Result<Vec<Experience>, ServerFnError> {
+    common::db_query_as!(
+        Experience,
+        fetch_all,
+        "SELECT * FROM experience ORDER BY position, id"
+    )
+    .map_err(|e| {
+        let err = format!("Error while getting experience: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not retrieve experience, try again later")
+    })
+}
+
+#[server(GetSkillsAction, "/api", "GetJson", endpoint = "skills")]
+#[cfg_attr(feature = "back", tracing::instrument)]
+pub async fn get_skills() -> Result<Vec<Skill>, ServerFnError> {
+    common::db_query_as!(
+        Skill,
+        fetch_all,
+        "SELECT * FROM skills ORDER BY position, id"
+    )
+    .map_err(|e| {
+        let err = format!("Error while getting skills: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not retrieve skills, try again later")
+    })
+}
+
+/// A project along with the slugs of its related blog posts, only the released ones unless
+/// `all_posts` is set.
+#[cfg(feature = "back")]
+fn select_projects(all_posts: bool) -> String {
+    let released = if all_posts { "" } else { "AND posts.released" };
+
+    format!(
+        r#"
+        SELECT projects.*, ARRAY(
+            SELECT posts.slug FROM project_posts
+            JOIN posts ON posts.id = project_posts.post_id
+            WHERE project_posts.project_id = projects.id {released}
+            ORDER BY posts.slug
+        ) AS posts
+        FROM projects
+        "#
+    )
+}
+
+#[server(GetProjectsAction, "/api", "GetJson", endpoint = "projects")]
+#[cfg_attr(feature = "back", tracing::instrument)]
+pub async fn get_projects() -> Result<Vec<Project>, ServerFnError> {
+    common::db_query_as!(
+        Project,
+        fetch_all,
+        &format!("{} ORDER BY position, id", select_projects(false))
+    )
+    .map_err(|e| {
+        let err = format!("Error while getting projects: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not retrieve projects, try again later")
+    })
+}
+
+/// The projects with all of their related posts, released or not, for editing them.
+#[server(GetAllProjectsAction, "/api/admin", "GetJson", endpoint = "projects")]
+#[cfg_attr(feature = "back", tracing::instrument)]
+pub async fn get_all_projects() -> Result<Vec<Project>, ServerFnError> {
+    use crate::models::User;
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    if !extract::<Extension<User>>().await.is_ok_and(|u| u.admin) {
+        return Err(ServerFnError::new("Unauthorized."));
+    };
+
+    common::db_query_as!(
+        Project,
+        fetch_all,
+        &format!("{} ORDER BY position, id", select_projects(true))
+    )
+    .map_err(|e| {
+        let err = format!("Error while getting projects: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not retrieve projects, try again later")
+    })
+}
+
+#[server(GetProjectAction, "/api", "GetJson", endpoint = "project")]
+#[cfg_attr(feature = "back", tracing::instrument)]
+pub async fn get_project(slug: String) -> Result<ProjectDetails, ServerFnError> {
+    use crate::models::{ProjectPost, Visibility};
+
+    let project = common::db_query_as!(
+        Project,
+        fetch_optional,
+        &format!("{} WHERE slug = $1", select_projects(false)),
+        slug
+    )
+    .map_err(|e| {
+        let err = format!("Error while getting project: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not retrieve project, try again later")
+    })?
+    .ok_or_else(|| ServerFnError::new("Not found."))?;
+
+    let demo = common::db_query_scalar!(
+        String,
+        fetch_optional,
+        "SELECT slug FROM sandbox WHERE slug = $1 AND visibility <> $2",
+        &project.sandbox_slug,
+        Visibility::Private,
+    )
+    .map_err(|e| {
+        let err = format!("Error while getting project demo: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not retrieve project, try again later")
+    })?;
+
+    let posts = common::db_query_as!(
+        ProjectPost,
+        fetch_all,
+        r#"
+        SELECT posts.slug, posts.title, posts.description FROM project_posts
+        JOIN posts ON posts.id = project_posts.post_id
+        WHERE project_posts.project_id = $1 AND posts.released
+        ORDER BY posts.release_date DESC
+        "#,
+        project.id
+    )
+    .map_err(|e| {
+        let err = format!("Error while getting project posts: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not retrieve project, try again later")
+    })?;
+
+    Ok(ProjectDetails {
+        demo_url: demo.map(|slug| format!("{}/{slug}", crate::Apps::SandBox.url())),
+        project,
+        posts,
+    })
+}
+
+#[server(GetSocialLinksAction, "/api", "GetJson", endpoint = "social_links")]
+#[cfg_attr(feature = "back", tracing::instrument)]
+pub async fn get_social_links() -> Result<Vec<SocialLink>, ServerFnError> {
+    common::db_query_as!(
+        SocialLink,
+        fetch_all,
+        "SELECT * FROM social_links ORDER BY position, id"
+    )
+    .map_err(|e| {
+        let err = format!("Error while getting social links: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not retrieve social links, try again later")
+    })
+}
+
+/// Adds the entry at the end if its id is 0, updates it otherwise.
+#[server(SaveExperienceAction, "/api/admin", endpoint = "save_experience")]
+#[cfg_attr(feature = "back", tracing::instrument)]
+pub async fn save_experience(experience: Experience) -> Result<Experience, ServerFnError> {
+    use crate::models::User;
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    if !extract::<Extension<User>>().await.is_ok_and(|u| u.admin) {
+        return Err(ServerFnError::new("Unauthorized."));
+    };
+
+    require("title", &experience.title)?;
+    require("company", &experience.company)?;
+
+    let saved = if experience.id == 0 {
+        common::db_query_as!(
+            Experience,
+            fetch_one,
+            r#"
+            INSERT INTO experience (title, company, url, period, position)
+            VALUES ($1, $2, $3, $4, (SELECT COALESCE(MAX(position) + 1, 0) FROM experience))
+            RETURNING *
+            "#,
+            experience.title,
+            experience.company,
+            experience.url,
+            experience.period,
+        )
+    } else {
+        common::db_query_as!(
+            Experience,
+            fetch_one,
+            r#"
+            UPDATE experience SET title = $2, company = $3, url = $4, period = $5
+            WHERE id = $1
+            RETURNING *
+            "#,
+            experience.id,
+            experience.title,
+            experience.company,
+            experience.url,
+            experience.period,
+        )
+    };
+
+    saved.map_err(|e| {
+        let err = format!("Error while saving experience: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not save experience, try again later")
+    })
+}
+
+/// Adds the entry at the end if its id is 0, updates it otherwise.
+#[server(SaveSkillAction, "/api/admin", endpoint = "save_skill")]
+#[cfg_attr(feature = "back", tracing::instrument)]
+pub async fn save_skill(skill: Skill) -> Result<Skill, ServerFnError> {
+    use crate::models::User;
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    if !extract::<Extension<User>>().await.is_ok_and(|u| u.admin) {
+        return Err(ServerFnError::new("Unauthorized."));
+    };
+
+    require("name", &skill.name)?;
+
+    let saved = if skill.id == 0 {
+        common::db_query_as!(
+            Skill,
+            fetch_one,
+            r#"
+            INSERT INTO skills (name, position)
+            VALUES ($1, (SELECT COALESCE(MAX(position) + 1, 0) FROM skills))
+            RETURNING *
+            "#,
+            skill.name,
+        )
+    } else {
+        common::db_query_as!(
+            Skill,
+            fetch_one,
+            "UPDATE skills SET name = $2 WHERE id = $1 RETURNING *",
+            skill.id,
+            skill.name,
+        )
+    };
+
+    saved.map_err(|e| {
+        let err = format!("Error while saving skill: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not save skill, try again later")
+    })
+}
+
+/// Adds the entry at the end if its id is 0, updates it otherwise.
+///
+/// Without a slug the name is taken, the related posts are given by their slugs.
+#[server(SaveProjectAction, "/api/admin", endpoint = "save_project")]
+#[cfg_attr(feature = "back", tracing::instrument)]
+pub async fn save_project(project: Project) -> Result<Project, ServerFnError> {
+    use crate::models::User;
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    if !extract::<Extension<User>>().await.is_ok_and(|u| u.admin) {
+        return Err(ServerFnError::new("Unauthorized."));
+    };
+
+    require("name", &project.name)?;
+
+    let slug = match project.slug.trim() {
+        "" => slugify(&project.name),
+        slug => slug.to_string(),
+    };
+    check_slug(&slug)?;
+    let optional = |value: Option<String>| value.filter(|value| !value.trim().is_empty());
+    let tags = project
+        .tags
+        .iter()
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect::<Vec<_>>();
+    let mut posts = project
+        .posts
+        .iter()
+        .map(|post| post.trim().to_string())
+        .filter(|post| !post.is_empty())
+        .collect::<Vec<_>>();
+    posts.sort();
+    posts.dedup();
+
+    let db_error = |e: sqlx::Error| {
+        let err = format!("Error while saving project: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not save project, try again later")
+    };
+
+    // the project and its posts are saved together
+    let mut tx = crate::db::db().begin().await.map_err(db_error)?;
+
+    let saved = if project.id == 0 {
+        sqlx::query_scalar::<_, i32>(
+            r#"
+            INSERT INTO projects (name, slug, description, tags, url, repository, sandbox_slug, position)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, (SELECT COALESCE(MAX(position) + 1, 0) FROM projects))
+            RETURNING id
+            "#,
+        )
+        .bind(project.name)
+        .bind(&slug)
+        .bind(project.description)
+        .bind(tags)
+        .bind(optional(project.url))
+        .bind(optional(project.repository))
+        .bind(optional(project.sandbox_slug))
+        .fetch_one(&mut *tx)
+        .await
+    } else {
+        sqlx::query_scalar::<_, i32>(
+            r#"
+            UPDATE projects SET
+                name = $2, slug = $3, description = $4, tags = $5,
+                url = $6, repository = $7, sandbox_slug = $8
+            WHERE id = $1
+            RETURNING id
+            "#,
+        )
+        .bind(project.id)
+        .bind(project.name)
+        .bind(&slug)
+        .bind(project.description)
+        .bind(tags)
+        .bind(optional(project.url))
+        .bind(optional(project.repository))
+        .bind(optional(project.sandbox_slug))
+        .fetch_one(&mut *tx)
+        .await
+    };
+
+    let id = saved.map_err(|e| match e.as_database_error() {
+        Some(db) if db.is_unique_violation() => {
+            ServerFnError::new(format!("The slug {slug} is taken already."))
+        }
+        Some(db) if db.is_foreign_key_violation() => {
+            ServerFnError::new("There is no sandbox page with that slug.")
+        }
+        _ => db_error(e),
+    })?;
+
+    sqlx::query("DELETE FROM project_posts WHERE project_id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_error)?;
+
+    let linked = sqlx::query(
+        r#"
+        INSERT INTO project_posts (project_id, post_id)
+        SELECT $1, id FROM posts WHERE slug = ANY($2)
+        "#,
+    )
+    .bind(id)
+    .bind(&posts)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_error)?;
+
+    if linked.rows_affected() != posts.len() as u64 {
+        return Err(ServerFnError::new(
+            "Some of the related posts do not exist.",
+        ));
+    }
+
+    tx.commit().await.map_err(db_error)?;
+
+    common::db_query_as!(
+        Project,
+        fetch_one,
+        &format!("{} WHERE id = $1", select_projects(true)),
+        id
+    )
+    .map_err(db_error)
+}
+
+/// Adds the entry at the end if its id is 0, updates it otherwise.
+#[server(SaveSocialLinkAction, "/api/admin", endpoint = "save_social_link")]
+#[cfg_attr(feature = "back", tracing::instrument)]
+pub async fn save_social_link(social_link: SocialLink) -> Result<SocialLink, ServerFnError> {
+    use crate::models::User;
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    if !extract::<Extension<User>>().await.is_ok_and(|u| u.admin) {
+        return Err(ServerFnError::new("Unauthorized."));
+    };
+
+    require("name", &social_link.name)?;
+    require("url", &social_link.url)?;
+
+    let saved = if social_link.id == 0 {
+        common::db_query_as!(
+            SocialLink,
+            fetch_one,
+            r#"
+            INSERT INTO social_links (name, url, position)
+            VALUES ($1, $2, (SELECT COALESCE(MAX(position) + 1, 0) FROM social_links))
+            RETURNING *
+            "#,
+            social_link.name,
+            social_link.url,
+        )
+    } else {
+        common::db_query_as!(
+            SocialLink,
+            fetch_one,
+            "UPDATE social_links SET name = $2, url = $3 WHERE id = $1 RETURNING *",
+            social_link.id,
+            social_link.name,
+            social_link.url,
+        )
+    };
+
+    saved.map_err(|e| {
+        let err = format!("Error while saving social link: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not save social link, try again later")
+    })
+}
+
+#[server(
+    DeletePortfolioEntryAction,
+    "/api/admin",
+    endpoint = "delete_portfolio_entry"
+)]
+#[cfg_attr(feature = "back", tracing::instrument)]
+pub async fn delete_portfolio_entry(table: PortfolioTable, id: i32) -> Result<u64, ServerFnError> {
+    use crate::models::User;
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    if !extract::<Extension<User>>().await.is_ok_and(|u| u.admin) {
+        return Err(ServerFnError::new("Unauthorized."));
+    };
+
+    common::db_query!(execute, &format!("DELETE FROM {table} WHERE id = $1"), id)
+        .map(|r| r.rows_affected())
+        .map_err(|e| {
+            let err = format!("Error while deleting {table} entry: {e:?}");
+            tracing::error!("{err}");
+            ServerFnError::new("Could not delete entry, try again later")
+        })
+}
+
+/// Swaps an entry with the one before or after it, numbering the whole table anew.
+#[server(
+    MovePortfolioEntryAction,
+    "/api/admin",
+    endpoint = "move_portfolio_entry"
+)]
+#[cfg_attr(feature = "back", tracing::instrument)]
+pub async fn move_portfolio_entry(
+    table: PortfolioTable,
+    id: i32,
+    up: bool,
+) -> Result<(), ServerFnError> {
+    use crate::models::User;
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    if !extract::<Extension<User>>().await.is_ok_and(|u| u.admin) {
+        return Err(ServerFnError::new("Unauthorized."));
+    };
+
+    let mut ids = common::db_query_scalar!(
+        i32,
+        fetch_all,
+        &format!("SELECT id FROM {table} ORDER BY position, id")
+    )
+    .map_err(|e| {
+        let err = format!("Error while getting {table} order: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not move entry, try again later")
+    })?;
+
+    let Some(index) = ids.iter().position(|i| *i == id) else {
+        return Err(ServerFnError::new("Not found."));
+    };
+
+    let other = if up {
+        index.checked_sub(1)
+    } else {
+        Some(index + 1).filter(|i| *i < ids.len())
+    };
+
+    if let Some(other) = other {
+        ids.swap(index, other);
+    }
+
+    common::db_query!(
+        execute,
+        &format!(
+            r#"
+            UPDATE {table} t SET position = o.position - 1
+            FROM unnest($1::INTEGER[]) WITH ORDINALITY AS o (id, position)
+            WHERE t.id = o.id
+            "#
+        ),
+        ids
+    )
+    .map(|_| ())
+    .map_err(|e| {
+        let err = format!("Error while moving {table} entry: {e:?}");
+        tracing::error!("{err}");
+        ServerFnError::new("Could not move entry, try again later")
+    })
+}
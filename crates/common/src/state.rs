@@ -0,0 +1,79 @@
+//! Who is looking at an app, shared by every app behind the auth cookie.
+
+use leptos::prelude::*;
+use reactive_stores::Store;
+
+use crate::{apps::Apps, models::Profile};
+
+#[server(UserAction, "/api", "GetJson", endpoint = "profile")]
+#[cfg_attr(feature = "back", tracing::instrument)]
+pub async fn get_user() -> Result<Option<Profile>, ServerFnError> {
+    use crate::models::User;
+    use axum::extract::Extension;
+    use leptos_axum::extract;
+
+    let Some(user) = extract::<Extension<User>>().await.ok() else {
+        return Ok(None);
+    };
+
+    Ok(Some(user.profile()))
+}
+
+#[derive(Clone, Debug, Default, Store)]
+pub struct GlobalState {
+    logged_in: bool,
+    user: Option<Profile>,
+}
+
+/// Provides the [`GlobalState`] and shows its children to admins only, everyone else
+/// is sent to log in and come back to `app`.
+#[component]
+pub fn AdminOnly(app: Apps, children: ChildrenFn) -> impl IntoView {
+    let store = Store::new(GlobalState::default());
+
+    let user = Resource::new(
+        move || store.logged_in().get(),
+        |_| async { get_user().await },
+    );
+
+    provide_context(store);
+
+    let login = move || {
+        let path = window().location().pathname().unwrap_or_default();
+
+        format!("{}/login?return_url={}{path}", Apps::Auth.url(), app.url(),)
+    };
+
+    view! {
+        <Suspense fallback=|| view! { <p class="p-6">"Loading..."</p> }>
+            {move || {
+                user.get()
+                    .map(|user| match user {
+                        Ok(Some(user)) if user.is_admin => {
+                            store.user().set(Some(user));
+                            children().into_any()
+                        }
+                        _ => {
+                            view! {
+                                <div class="grid h-screen place-content-center px-4 text-center">
+                                    <h1 class="text-2xl font-bold">"Admins only"</h1>
+                                    <a
+                                        class="mt-6 inline-block rounded bg-nf-color px-5 py-3 text-sm font-medium text-white"
+                                        on:click={
+                                            let login = login.clone();
+                                            move |_| {
+                                                let _ = window().location().set_href(&login());
+                                            }
+                                        }
+                                    >
+                                        "Log in"
+                                    </a>
+                                </div>
+                            }
+                                .into_any()
+                        }
+                    })
+            }}
+        </Suspense>
+    }
+}
@@ -89,14 +89,7 @@ pub fn Footer() -> impl IntoView {
 
     let email = obfuscate_email();
 
-    let (socials, _set_socials) = signal(vec![
-        ("instagram", "https://www.instagram.com/nic_ol_ass"),
-        ("bluesky", "https://bsky.app/profile/nicolas-frey.com"),
-        ("youtube", "https://www.youtube.com/@microwonk"),
-        ("github", "https://www.github.com/Microwonk"),
-        ("itch-io", "https://microwonk.itch.io"),
-        ("discord", "https://discordapp.com/users/444924590913749002"),
-    ]);
+    let socials = Resource::new(|| (), |_| crate::portfolio::get_social_links());
 
     view! {
         <footer class="tornpaper-effect md:flex md:items-center md:justify-between shadow rounded-lg p-4 md:p-6 xl:p-8 w-full">
@@ -117,15 +110,223 @@ pub fn Footer() -> impl IntoView {
                 </li>
             </ul>
             <div class="flex sm:justify-center space-x-6">
-                <For each=socials key=|state| state.0 let:child>
-                    <a href=child.1 target="_blank" class="hover:animate-pulse">
-                        <img
-                            src=move || { format!("/assets/{}.svg", child.0) }
-                            class="w-[24px] mx-4"
-                        />
-                    </a>
-                </For>
+                <Transition>
+                    <For
+                        each=move || socials.get().and_then(Result::ok).unwrap_or_default()
+                        key=|social| social.id
+                        let:social
+                    >
+                        <a href=social.url target="_blank" class="hover:animate-pulse">
+                            <img src=format!("/assets/{}.svg", social.name) class="w-[24px] mx-4" />
+                        </a>
+                    </For>
+                </Transition>
             </div>
         </footer>
     }
 }
+
+/// The way from the root down to where the user is, as labels and links.
+#[component]
+pub fn Breadcrumbs(#[prop(into)] crumbs: Signal<Vec<(String, String)>>) -> impl IntoView {
+    view! {
+        <nav aria-label="Breadcrumbs" class="flex flex-wrap items-center gap-1 text-sm">
+            {move || {
+                let crumbs = crumbs.get();
+                let last = crumbs.len().saturating_sub(1);
+                crumbs
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (label, href))| {
+                        view! {
+                            {(i > 0).then(|| view! { <span class="text-gray-500">"/"</span> })}
+                            <a
+                                href=href
+                                class="hover:underline"
+                                class:font-bold=move || i == last
+                                aria-current=move || (i == last).then_some("page")
+                            >
+                                {label}
+                            </a>
+                        }
+                    })
+                    .collect_view()
+            }}
+        </nav>
+    }
+}
+
+/// Takes files dropped onto it or picked from the file dialog by clicking it.
+#[component]
+pub fn DropZone(
+    #[prop(into)] on_files: Callback<Vec<web_sys::File>>,
+    /// like the `accept` of a file input
+    #[prop(optional)]
+    accept: &'static str,
+    #[prop(optional)] multiple: bool,
+    children: Children,
+) -> impl IntoView {
+    let (hovering, set_hovering) = signal(false);
+
+    let take = move |list: Option<web_sys::FileList>| {
+        let files = list
+            .map(|list| {
+                (0..list.length())
+                    .filter_map(|i| list.get(i))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        if !files.is_empty() {
+            on_files.run(files);
+        }
+    };
+
+    view! {
+        <label
+            class="block cursor-pointer rounded border-2 border-dashed border-gray-400 p-6 text-center transition"
+            class:border-nf-color=hovering
+            class:bg-white=hovering
+            on:dragover=move |ev| {
+                ev.prevent_default();
+                set_hovering(true);
+            }
+            on:dragleave=move |_| set_hovering(false)
+            on:drop=move |ev| {
+                ev.prevent_default();
+                set_hovering(false);
+                take(ev.data_transfer().and_then(|data| data.files()));
+            }
+        >
+            {children()}
+            <input
+                type="file"
+                class="hidden"
+                accept=accept
+                multiple=multiple
+                on:change=move |ev| {
+                    let input = event_target::<web_sys::HtmlInputElement>(&ev);
+                    take(input.files());
+                    // picking the same files again should upload them again
+                    input.set_value("");
+                }
+            />
+        </label>
+    }
+}
+
+/// An upload shown in an [`UploadList`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Upload {
+    pub id: usize,
+    pub name: String,
+    /// in bytes
+    pub sent: f64,
+    pub total: f64,
+    /// missing while it is still going
+    pub result: Option<Result<(), String>>,
+}
+
+/// Sends files like [`crate::client::upload`] while showing its progress in `uploads`.
+pub fn track_upload(
+    uploads: RwSignal<Vec<Upload>>,
+    method: &'static str,
+    url: String,
+    files: Vec<web_sys::File>,
+    on_done: impl FnOnce(Result<String, String>) + 'static,
+) {
+    let id = uploads.with_untracked(|uploads| uploads.iter().map(|u| u.id + 1).max().unwrap_or(0));
+    let name = files
+        .iter()
+        .map(|file| file.name())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let total = files.iter().map(|file| file.size()).sum();
+
+    uploads.update(|uploads| {
+        uploads.push(Upload {
+            id,
+            name,
+            sent: 0.0,
+            total,
+            result: None,
+        })
+    });
+
+    let update = move |f: &dyn Fn(&mut Upload)| {
+        uploads.update(|uploads| {
+            if let Some(upload) = uploads.iter_mut().find(|u| u.id == id) {
+                f(upload);
+            }
+        })
+    };
+
+    leptos::task::spawn_local(async move {
+        let result = crate::client::upload(method, &url, &files, move |sent, total| {
+            update(&|upload| {
+                upload.sent = sent;
+                upload.total = total;
+            })
+        })
+        .await;
+
+        update(&|upload| {
+            upload.result = Some(result.as_ref().map(|_| ()).map_err(Clone::clone));
+            if result.is_ok() {
+                upload.sent = upload.total;
+            }
+        });
+
+        on_done(result);
+    });
+}
+
+/// Progress bars of [`track_upload`]s, finished ones can be cleared.
+#[component]
+pub fn UploadList(uploads: RwSignal<Vec<Upload>>) -> impl IntoView {
+    view! {
+        <Show when=move || uploads.with(|uploads| !uploads.is_empty())>
+            <ul class="mt-4 flex flex-col gap-2 text-sm">
+                <For
+                    each=move || uploads.get()
+                    key=|upload| (upload.id, upload.sent as u64, upload.result.clone())
+                    let:upload
+                >
+                    <li>
+                        <div class="flex justify-between gap-2">
+                            <span class="truncate">{upload.name.clone()}</span>
+                            <span>
+                                {match &upload.result {
+                                    None => {
+                                        format!(
+                                            "{:.0} %",
+                                            100.0 * upload.sent / upload.total.max(1.0),
+                                        )
+                                    }
+                                    Some(Ok(())) => "done".to_string(),
+                                    Some(Err(_)) => "failed".to_string(),
+                                }}
+                            </span>
+                        </div>
+                        <progress
+                            class="w-full"
+                            max=upload.total.max(1.0)
+                            value=upload.sent
+                        ></progress>
+                        {upload
+                            .result
+                            .clone()
+                            .and_then(Result::err)
+                            .map(|e| view! { <p class="whitespace-pre-line text-red-700">{e}</p> })}
+                    </li>
+                </For>
+            </ul>
+            <button
+                class="mt-2 text-sm underline"
+                on:click=move |_| uploads.update(|uploads| uploads.retain(|u| u.result.is_none()))
+            >
+                "Clear finished"
+            </button>
+        </Show>
+    }
+}
@@ -0,0 +1,54 @@
+use leptos::prelude::*;
+use leptos_meta::{MetaTags, Stylesheet, Title, provide_meta_context};
+use leptos_router::{
+    components::{Route, Router, Routes},
+    path,
+};
+
+use crate::browser::BrowserPage;
+use common::{Apps, state::AdminOnly};
+
+pub fn shell(options: LeptosOptions) -> impl IntoView {
+    view! {
+        <!DOCTYPE html>
+        <html class="files" lang="en">
+            <head>
+                <meta charset="utf-8" />
+                <meta name="viewport" content="width=device-width, initial-scale=1" />
+                <AutoReload options=options.clone() />
+                <HydrationScripts options />
+                <MetaTags />
+            </head>
+            <body class="bg-nf-white">
+                <App />
+            </body>
+        </html>
+    }
+}
+
+#[component]
+pub fn App() -> impl IntoView {
+    provide_meta_context();
+
+    view! {
+        <Stylesheet id="leptos" href="/pkg/microweb.css" />
+
+        <Title text="File Browser" />
+
+        <Router>
+            <Routes fallback=|| view! { <p class="p-6">"Nothing here."</p> }>
+                <Route path=path!("browse") view=Browser />
+                <Route path=path!("browse/*path") view=Browser />
+            </Routes>
+        </Router>
+    }
+}
+
+#[component]
+fn Browser() -> impl IntoView {
+    view! {
+        <AdminOnly app=Apps::Files>
+            <BrowserPage />
+        </AdminOnly>
+    }
+}
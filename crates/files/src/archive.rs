@@ -0,0 +1,235 @@
+use async_zip::{Compression, ZipDateTimeBuilder, ZipEntryBuilder, tokio::write::ZipFileWriter};
+use axum::{
+    Extension,
+    body::Body,
+    extract::Path,
+    http::{HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use futures::{AsyncWriteExt, StreamExt};
+use tokio::io::DuplexStream;
+use tokio_util::io::ReaderStream;
+
+use common::{
+    api::{ApiError, ApiResult},
+    models::{Directory, File, User, Visibility},
+};
+
+use super::{PRIVATE, blob, download, share, storage::storage};
+
+/// How much of the zip is buffered while waiting for the client to read on.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Streams a zip of the directory with everything below it.
+///
+/// Entries are written while the client reads, so only [`BUFFER_SIZE`] of it is ever
+/// in memory. Anyone but admins only gets public directories, with whatever is not
+/// public below them left out.
+#[tracing::instrument(skip(user))]
+pub async fn zip_by_id(Path(id): Path<i32>, user: Option<Extension<User>>) -> ApiResult<Response> {
+    let admin = user.is_some_and(|u| u.admin);
+
+    // the root is handed out with id 0
+    let (id, dir) = match id {
+        0 => (None, Directory::root()),
+        id => (
+            Some(id),
+            common::db_query_as!(
+                Directory,
+                fetch_one,
+                "SELECT * FROM directories WHERE id = $1 AND deleted_at IS NULL",
+                id
+            )
+            .map_err(|_| ApiError::not_found())?,
+        ),
+    };
+
+    let visibility = share::directory_visibility(id).await?;
+    if !admin && visibility != Visibility::Public {
+        return Err(ApiError::not_found());
+    }
+
+    let subtree = r#"
+        WITH RECURSIVE subtree AS (
+            SELECT id, dir_path,
+                COALESCE(visibility, CASE WHEN dir_name = $3 THEN 'private'::visibility END, $2)
+                    AS effective
+            FROM directories
+            WHERE (id = $1 OR ($1::INTEGER IS NULL AND parent_id IS NULL)) AND deleted_at IS NULL
+            UNION ALL
+            SELECT d.id, d.dir_path,
+                COALESCE(d.visibility, CASE WHEN d.dir_name = $3 THEN 'private'::visibility END,
+                    s.effective)
+            FROM directories d
+            JOIN subtree s ON d.parent_id = s.id
+            WHERE d.deleted_at IS NULL
+        )
+    "#;
+
+    let directories = common::db_query_scalar!(
+        String,
+        fetch_all,
+        &format!(
+            r#"
+            {subtree}
+            SELECT dir_path FROM subtree
+            WHERE $4 OR effective = 'public'
+            ORDER BY dir_path
+            "#
+        ),
+        id,
+        visibility,
+        PRIVATE,
+        admin,
+    )?;
+
+    let files = common::db_query_as!(
+        File,
+        fetch_all,
+        &format!(
+            r#"
+            {subtree}
+            SELECT f.* FROM files f
+            LEFT JOIN subtree s ON f.directory_id = s.id
+            WHERE f.deleted_at IS NULL
+                AND (s.id IS NOT NULL OR ($1::INTEGER IS NULL AND f.directory_id IS NULL))
+                AND ($4 OR COALESCE(f.visibility, s.effective, $2) = 'public')
+            ORDER BY f.file_path
+            "#
+        ),
+        id,
+        visibility,
+        PRIVATE,
+        admin,
+    )?;
+
+    // everything is named relative to the directory, inside a folder of its name
+    let (base, name) = match id {
+        Some(_) => (dir.dir_path.clone(), dir.dir_name.clone()),
+        None => (format!("/{}", dir.dir_path), "files".to_string()),
+    };
+    let relative = move |path: &str| {
+        path.strip_prefix(&base)
+            .map(|rest| format!("{name}{rest}"))
+            .unwrap_or_default()
+    };
+
+    let directories = directories
+        .iter()
+        .map(|path| format!("{}/", relative(path)))
+        .collect::<Vec<_>>();
+    let files = files
+        .into_iter()
+        .map(|file| (relative(&file.file_path), file))
+        .collect::<Vec<_>>();
+
+    let (writer, reader) = tokio::io::duplex(BUFFER_SIZE);
+
+    tokio::spawn(async move {
+        // the client only sees a cut off zip, there is no way to tell it more
+        if let Err(e) = write_zip(writer, directories, files).await {
+            tracing::error!("Error while writing zip: {e}");
+        }
+    });
+
+    Ok((
+        StatusCode::OK,
+        [
+            (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/zip"),
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                HeaderValue::from_str(&download::content_disposition(
+                    "attachment",
+                    &format!("{}.zip", dir.dir_name),
+                ))
+                .map_err(|_| ApiError::internal_server_error())?,
+            ),
+        ],
+        Body::from_stream(ReaderStream::new(reader)),
+    )
+        .into_response())
+}
+
+async fn write_zip(
+    writer: DuplexStream,
+    directories: Vec<String>,
+    files: Vec<(String, File)>,
+) -> Result<(), String> {
+    let mut zip = ZipFileWriter::with_tokio(writer);
+
+    // empty ones would be missing otherwise
+    for path in directories {
+        zip.write_entry_whole(ZipEntryBuilder::new(path.into(), Compression::Stored), &[])
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    for (path, file) in files {
+        write_file(&mut zip, path, &file).await?;
+    }
+
+    zip.close().await.map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+async fn write_file(
+    zip: &mut ZipFileWriter<DuplexStream>,
+    path: String,
+    file: &File,
+) -> Result<(), String> {
+    let entry = ZipEntryBuilder::new(path.into(), compression(&file.mime_type))
+        .last_modification_date(zip_date(file.uploaded_at));
+
+    let mut content = storage()
+        .get(&blob::content_key(file))
+        .await
+        .map_err(|e| format!("{}: {e}", file.file_path))?;
+
+    let mut entry = zip
+        .write_entry_stream(entry)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    while let Some(chunk) = content.next().await {
+        let chunk = chunk.map_err(|e| format!("{}: {e}", file.file_path))?;
+        entry.write_all(&chunk).await.map_err(|e| e.to_string())?;
+    }
+
+    entry.close().await.map_err(|e| e.to_string())
+}
+
+/// Already compressed formats would only get bigger.
+fn compression(mime_type: &str) -> Compression {
+    let compressed = ["image/", "video/", "audio/"]
+        .iter()
+        .any(|prefix| mime_type.starts_with(prefix))
+        || [
+            "application/zip",
+            "application/gzip",
+            "application/x-7z-compressed",
+            "application/pdf",
+        ]
+        .contains(&mime_type);
+
+    if compressed && mime_type != "image/svg+xml" {
+        Compression::Stored
+    } else {
+        Compression::Deflate
+    }
+}
+
+fn zip_date(date: NaiveDateTime) -> async_zip::ZipDateTime {
+    ZipDateTimeBuilder::new()
+        .year(date.year())
+        .month(date.month())
+        .day(date.day())
+        .hour(date.hour())
+        .minute(date.minute())
+        .second(date.second())
+        .build()
+}
@@ -0,0 +1,427 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use axum::extract::multipart::Field;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::{fs::File as FsFile, io::AsyncReadExt, io::AsyncWriteExt};
+use uuid::Uuid;
+
+use common::{
+    db::sqlx,
+    jobs::{self, Job},
+    models::File,
+};
+
+use super::{DIRECTORY, storage::storage};
+
+/// content, stored by its SHA-256, in the [`storage`]
+static BLOB_DIR: &str = "blobs";
+/// unfinished uploads, inside of [`DIRECTORY`] on the local disk
+static TMP_DIR: &str = "tmp";
+
+/// Unreferenced blobs used more recently than this are left alone,
+/// an upload might be about to point a file at them.
+const GC_GRACE_MINUTES: i32 = 60;
+/// How much of an upload [`put_field`] hands to its check.
+pub const HEAD_BYTES: usize = 8192;
+/// Temp files older than this belong to uploads that never finished.
+const TMP_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Storage key of the blob with this hash, spread over prefixes by its first byte.
+pub fn blob_key(sha256: &str) -> String {
+    format!("{BLOB_DIR}/{}/{sha256}", &sha256[..2.min(sha256.len())])
+}
+
+/// Storage key of the content of a file, files not yet moved by
+/// [`MigrateLegacyFilesJob`] are still stored under their id.
+pub fn content_key(file: &File) -> String {
+    match &file.sha256 {
+        Some(sha256) => blob_key(sha256),
+        None => file.id.to_string(),
+    }
+}
+
+/// Streams a multipart field into the blob store and returns its size, SHA-256
+/// and what `check` made of the first [`HEAD_BYTES`] of it.
+///
+/// Chunks go to a local temp file first as the hash is only known at the end,
+/// it is then handed to the storage, so a blob is never visible half written.
+/// The upload is stopped as soon as `check` fails or it grows past `max_size`.
+pub async fn put_field<T>(
+    mut field: Field<'_>,
+    max_size: i64,
+    check: impl FnOnce(&[u8]) -> Result<T, String>,
+) -> Result<(i64, String, T), String> {
+    let tmp_path = tmp_path().await?;
+    let mut check = Some(check);
+    let mut checked = None;
+
+    let written = async {
+        let mut file = FsFile::create(&tmp_path)
+            .await
+            .map_err(|e| format!("Create error: {e}"))?;
+        let mut hasher = Sha256::new();
+        let mut size = 0;
+        let mut head = Vec::with_capacity(HEAD_BYTES);
+
+        while let Some(chunk) = field.chunk().await.map_err(|e| e.to_string())? {
+            size += chunk.len() as i64;
+            if size > max_size {
+                return Err(format!("Larger than the {max_size} bytes allowed."));
+            }
+
+            if head.len() < HEAD_BYTES {
+                head.extend_from_slice(&chunk[..chunk.len().min(HEAD_BYTES - head.len())]);
+            }
+            if head.len() == HEAD_BYTES
+                && let Some(check) = check.take()
+            {
+                checked = Some(check(&head)?);
+            }
+
+            hasher.update(&chunk);
+            file.write_all(&chunk)
+                .await
+                .map_err(|e| format!("Write error: {e}"))?;
+        }
+
+        // shorter than the head
+        if let Some(check) = check.take() {
+            checked = Some(check(&head)?);
+        }
+
+        file.sync_all()
+            .await
+            .map_err(|e| format!("Write error: {e}"))?;
+
+        Ok::<_, String>((size, hex::encode(hasher.finalize())))
+    }
+    .await;
+
+    let (size, sha256) = commit(&tmp_path, written).await?;
+
+    Ok((size, sha256, checked.ok_or("Content was not checked.")?))
+}
+
+/// Stores content that is already in memory and returns its size and SHA-256.
+pub async fn put(data: &[u8]) -> Result<(i64, String), String> {
+    let tmp_path = tmp_path().await?;
+
+    let written = tokio::fs::write(&tmp_path, data)
+        .await
+        .map(|_| (data.len() as i64, hex::encode(Sha256::digest(data))))
+        .map_err(|e| format!("Write error: {e}"));
+
+    commit(&tmp_path, written).await
+}
+
+async fn tmp_path() -> Result<PathBuf, String> {
+    let tmp_dir = PathBuf::from(DIRECTORY).join(TMP_DIR);
+    tokio::fs::create_dir_all(&tmp_dir)
+        .await
+        .map_err(|e| format!("Create error: {e}"))?;
+
+    Ok(tmp_dir.join(format!("{}.part", Uuid::new_v4())))
+}
+
+/// Moves a written temp file into the storage as its blob.
+///
+/// The row is registered first, which waits for a running garbage collection
+/// to be done with the blob and keeps the next one away from it.
+/// Identical content might already be there, it simply gets replaced.
+async fn commit(
+    tmp_path: &Path,
+    written: Result<(i64, String), String>,
+) -> Result<(i64, String), String> {
+    let result = async {
+        let (size, sha256) = written?;
+
+        register(&sha256, size)
+            .await
+            .map_err(|e| format!("DB error: {e}"))?;
+
+        storage()
+            .put_file(&blob_key(&sha256), tmp_path)
+            .await
+            .map_err(|e| format!("Store error: {e}"))?;
+
+        Ok((size, sha256))
+    }
+    .await;
+
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(tmp_path).await;
+    }
+
+    result
+}
+
+async fn register(sha256: &str, size: i64) -> Result<(), sqlx::Error> {
+    common::db_query!(
+        execute,
+        r#"
+        INSERT INTO blobs (sha256, size) VALUES ($1, $2)
+        ON CONFLICT (sha256) DO UPDATE SET
+            last_used_at = NOW(),
+            delete_attempts = 0,
+            delete_error = NULL,
+            delete_attempted_at = NULL
+        "#,
+        sha256,
+        size,
+    )
+    .map(|_| ())
+}
+
+/// Removes blobs no file points to anymore, along with abandoned uploads.
+///
+/// Blobs the storage fails to delete are noted on their row and skipped for the rest
+/// of the run, the job then fails so the queue retries it later.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CollectGarbageJob {}
+
+impl Job for CollectGarbageJob {
+    const KIND: &'static str = "files::collect_garbage";
+
+    async fn run(self) -> Result<(), String> {
+        // the same clock as the attempts are recorded with
+        let started = common::db_query_scalar!(NaiveDateTime, fetch_one, "SELECT NOW()::TIMESTAMP")
+            .map_err(|e| format!("DB error: {e}"))?;
+        let mut errors = Vec::new();
+
+        loop {
+            match collect_one(started).await {
+                Ok(Collected::Deleted) => {}
+                Ok(Collected::Failed(e)) => errors.push(e),
+                Ok(Collected::Nothing) => break,
+                Err(e) => {
+                    errors.push(e);
+                    break;
+                }
+            }
+        }
+
+        if let Err(e) = remove_stale_tmp_files().await {
+            errors.push(e);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join(", "))
+        }
+    }
+}
+
+/// Queues a [`CollectGarbageJob`], to be called after files were deleted.
+pub async fn collect_garbage() {
+    if let Err(e) = jobs::enqueue_once(&CollectGarbageJob {}).await {
+        tracing::error!("Error while queueing garbage collection: {e:?}");
+    }
+}
+
+enum Collected {
+    Deleted,
+    /// the row is kept with the error, to be retried by a later run
+    Failed(String),
+    Nothing,
+}
+
+/// Deletes a single unreferenced blob not yet tried since `started`.
+///
+/// The row stays locked until the content is gone, see [`commit`].
+async fn collect_one(started: NaiveDateTime) -> Result<Collected, String> {
+    let mut tx = common::db::db()
+        .begin()
+        .await
+        .map_err(|e| format!("DB error: {e}"))?;
+
+    let Some(sha256) = sqlx::query_scalar::<_, String>(
+        r#"
+        DELETE FROM blobs
+        WHERE sha256 = (
+            SELECT sha256 FROM blobs
+            WHERE ref_count <= 0 AND last_used_at < NOW() - make_interval(mins => $1)
+                AND (delete_attempted_at IS NULL OR delete_attempted_at < $2)
+            FOR UPDATE SKIP LOCKED
+            LIMIT 1
+        )
+        RETURNING sha256
+        "#,
+    )
+    .bind(GC_GRACE_MINUTES)
+    .bind(started)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| format!("DB error: {e}"))?
+    else {
+        return Ok(Collected::Nothing);
+    };
+
+    if let Err(e) = storage().delete(&blob_key(&sha256)).await {
+        let err = format!("Could not remove blob {sha256}: {e}");
+        tracing::warn!("{err}");
+
+        // the row comes back with the rollback, only the attempt is recorded
+        drop(tx);
+        common::db_query!(
+            execute,
+            r#"
+            UPDATE blobs
+            SET delete_attempts = delete_attempts + 1, delete_error = $2,
+                delete_attempted_at = NOW()
+            WHERE sha256 = $1
+            "#,
+            &sha256,
+            &err,
+        )
+        .map_err(|e| format!("DB error: {e}"))?;
+
+        return Ok(Collected::Failed(err));
+    }
+
+    tx.commit().await.map_err(|e| format!("DB error: {e}"))?;
+
+    Ok(Collected::Deleted)
+}
+
+async fn remove_stale_tmp_files() -> Result<(), String> {
+    let mut entries = match tokio::fs::read_dir(PathBuf::from(DIRECTORY).join(TMP_DIR)).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.to_string()),
+    };
+
+    while let Some(entry) = entries.next_entry().await.map_err(|e| e.to_string())? {
+        let stale = entry
+            .metadata()
+            .await
+            .and_then(|m| m.modified())
+            .is_ok_and(|modified| {
+                SystemTime::now()
+                    .duration_since(modified)
+                    .is_ok_and(|age| age > TMP_MAX_AGE)
+            });
+
+        if stale {
+            let _ = tokio::fs::remove_file(entry.path()).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Moves files stored under their id before blobs existed into the blob store.
+///
+/// Those only ever lived on the local disk, whatever the storage is now.
+/// Queued on every start, once all files are moved there is nothing left to do.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MigrateLegacyFilesJob {}
+
+impl Job for MigrateLegacyFilesJob {
+    const KIND: &'static str = "files::migrate_legacy";
+
+    async fn run(self) -> Result<(), String> {
+        let legacy =
+            common::db_query_as!(File, fetch_all, "SELECT * FROM files WHERE sha256 IS NULL")
+                .map_err(|e| format!("DB error: {e}"))?;
+
+        if legacy.is_empty() {
+            return Ok(());
+        }
+
+        tracing::info!("Moving {} files into the blob store", legacy.len());
+
+        let mut errors = Vec::new();
+
+        for file in legacy {
+            if let Err(e) = migrate_legacy_file(&file).await {
+                errors.push(format!("{}: {e}", file.file_path));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join(", "))
+        }
+    }
+}
+
+/// Queues a [`MigrateLegacyFilesJob`], called once on start.
+pub async fn migrate_legacy_files() {
+    if let Err(e) = jobs::enqueue_once(&MigrateLegacyFilesJob {}).await {
+        tracing::error!("Error while queueing file migration: {e:?}");
+    }
+}
+
+async fn migrate_legacy_file(file: &File) -> Result<(), String> {
+    let legacy_path = PathBuf::from(DIRECTORY).join(file.id.to_string());
+
+    let mut legacy = FsFile::open(&legacy_path)
+        .await
+        .map_err(|e| format!("Open error: {e}"))?;
+    let mut hasher = Sha256::new();
+    let mut size = 0;
+    let mut buf = vec![0; 64 * 1024];
+
+    loop {
+        let read = legacy
+            .read(&mut buf)
+            .await
+            .map_err(|e| format!("Read error: {e}"))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+        size += read as i64;
+    }
+
+    let sha256 = hex::encode(hasher.finalize());
+
+    register(&sha256, size)
+        .await
+        .map_err(|e| format!("DB error: {e}"))?;
+
+    // copied, the legacy file is only gone once the row points at the blob
+    let tmp_path = tmp_path().await?;
+    let copied = async {
+        tokio::fs::copy(&legacy_path, &tmp_path)
+            .await
+            .map_err(|e| format!("Copy error: {e}"))?;
+        storage()
+            .put_file(&blob_key(&sha256), &tmp_path)
+            .await
+            .map_err(|e| format!("Store error: {e}"))
+    }
+    .await;
+
+    if copied.is_err() {
+        let _ = tokio::fs::remove_file(&tmp_path).await;
+    }
+    copied?;
+
+    common::db_query!(
+        execute,
+        "UPDATE files SET sha256 = $1, size = $2 WHERE id = $3",
+        &sha256,
+        size,
+        file.id,
+    )
+    .map_err(|e| format!("DB error: {e}"))?;
+
+    let _ = tokio::fs::remove_file(&legacy_path).await;
+
+    super::encoding::queue(&File {
+        sha256: Some(sha256),
+        size: Some(size),
+        ..file.clone()
+    })
+    .await;
+
+    Ok(())
+}
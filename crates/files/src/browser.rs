@@ -0,0 +1,850 @@
+//! Browsing the files through the json api, with everything it offers.
+
+use gloo_net::http::Request;
+use leptos::{prelude::*, task::spawn_local};
+use leptos_router::hooks::{use_navigate, use_params_map};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, utf8_percent_encode};
+use web_sys::KeyboardEvent;
+
+use common::{
+    client,
+    models::{
+        Directory, DirectoryContents, DirectoryPolicy, File, PolicyResponse, ShareLink,
+        ShareRequest, StorageUsage, Trash, Visibility,
+    },
+    ui::{Breadcrumbs, DropZone, Upload, UploadList, track_upload},
+};
+
+/// Characters left as they are in a segment of a path.
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+fn encode(path: &str) -> String {
+    path.split('/')
+        .map(|segment| utf8_percent_encode(segment, SEGMENT).to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn encode_query(value: &str) -> String {
+    utf8_percent_encode(value, NON_ALPHANUMERIC).to_string()
+}
+
+/// Where the api finds a directory given by its path below the root.
+fn api_path(path: &str) -> String {
+    if path.is_empty() {
+        "~".to_string()
+    } else {
+        format!("~/{}", encode(path))
+    }
+}
+
+/// Where the browser shows a directory given by its path below the root.
+fn browse_url(path: &str) -> String {
+    if path.is_empty() {
+        "/browse".to_string()
+    } else {
+        format!("/browse/{}", encode(path))
+    }
+}
+
+fn file_url(file: &File) -> String {
+    format!("/f{}", encode(&file.file_path))
+}
+
+#[derive(Clone, Debug)]
+enum Entry {
+    Directory(Directory),
+    File(File),
+}
+
+impl Entry {
+    fn name(&self) -> &str {
+        match self {
+            Entry::Directory(dir) => &dir.dir_name,
+            Entry::File(file) => &file.file_name,
+        }
+    }
+
+    /// As in the routes of the api, like `/d_id/{id}`.
+    fn kind(&self) -> &'static str {
+        match self {
+            Entry::Directory(_) => "d",
+            Entry::File(_) => "f",
+        }
+    }
+
+    fn id(&self) -> String {
+        match self {
+            Entry::Directory(dir) => dir.id.to_string(),
+            Entry::File(file) => file.id.to_string(),
+        }
+    }
+
+    fn visibility(&self) -> Option<Visibility> {
+        match self {
+            Entry::Directory(dir) => dir.visibility,
+            Entry::File(file) => file.visibility,
+        }
+    }
+
+    fn icon(&self) -> &'static str {
+        match self {
+            Entry::Directory(_) => "📁",
+            Entry::File(_) => "📄",
+        }
+    }
+}
+
+/// Directories first, each sorted by name.
+fn entries(contents: DirectoryContents) -> Vec<Entry> {
+    let mut directories = contents.directories;
+    directories.sort_by(|a, b| a.dir_name.cmp(&b.dir_name));
+    let mut files = contents.files;
+    files.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+
+    directories
+        .into_iter()
+        .map(Entry::Directory)
+        .chain(files.into_iter().map(Entry::File))
+        .collect()
+}
+
+fn prompt(message: &str, default: &str) -> Option<String> {
+    window()
+        .prompt_with_message_and_default(message, default)
+        .ok()
+        .flatten()
+}
+
+fn confirm(message: &str) -> bool {
+    window().confirm_with_message(message).unwrap_or(false)
+}
+
+/// Runs an action against the api, showing what went wrong or loading everything again.
+fn run(reload: RwSignal<u32>, action: impl Future<Output = Result<(), String>> + 'static) {
+    spawn_local(async move {
+        match action.await {
+            Ok(()) => reload.update(|n| *n += 1),
+            Err(e) => {
+                let _ = window().alert_with_message(&e);
+            }
+        }
+    });
+}
+
+async fn rename(entry: Entry, directory_id: Option<i32>) -> Result<(), String> {
+    let Some(name) = prompt("Rename to", entry.name()).filter(|name| name != entry.name()) else {
+        return Ok(());
+    };
+
+    let target = directory_id
+        .map(|id| format!("directory_id={id}&"))
+        .unwrap_or_default();
+
+    client::send(Request::post(&format!(
+        "/{}_id/{}/move?{target}name={}",
+        entry.kind(),
+        entry.id(),
+        encode_query(&name),
+    )))
+    .await
+    .map(|_| ())
+}
+
+/// Moves or copies, `action` is `move` or `copy`.
+async fn transfer(entry: Entry, action: &'static str, path: String) -> Result<(), String> {
+    let Some(target) = prompt(&format!("To {action} into, below ~"), &path) else {
+        return Ok(());
+    };
+    let Some(name) = prompt("Name", entry.name()) else {
+        return Ok(());
+    };
+
+    let target = target.trim_matches('/');
+    let directory =
+        client::fetch::<Directory>(Request::get(&format!("/d/{}", api_path(target)))).await?;
+
+    client::send(Request::post(&format!(
+        "/{}_id/{}/{action}?directory_id={}&name={}",
+        entry.kind(),
+        entry.id(),
+        directory.id,
+        encode_query(&name),
+    )))
+    .await
+    .map(|_| ())
+}
+
+async fn set_visibility(entry: Entry) -> Result<(), String> {
+    let current = entry
+        .visibility()
+        .map(|v| v.to_string())
+        .unwrap_or_default();
+
+    let Some(visibility) = prompt(
+        "Visibility: public, unlisted, private or empty to inherit",
+        &current,
+    ) else {
+        return Ok(());
+    };
+
+    let query = match visibility.trim() {
+        "" => String::new(),
+        visibility => format!("?visibility={}", encode_query(visibility)),
+    };
+
+    client::send(Request::post(&format!(
+        "/{}_id/{}/visibility{query}",
+        entry.kind(),
+        entry.id(),
+    )))
+    .await
+    .map(|_| ())
+}
+
+async fn share(entry: Entry) -> Result<(), String> {
+    let Some(hours) = prompt("Expires after hours, empty for never", "24") else {
+        return Ok(());
+    };
+    let Some(downloads) = prompt("Maximum downloads, empty for unlimited", "") else {
+        return Ok(());
+    };
+    let Some(password) = prompt("Password, empty for none", "") else {
+        return Ok(());
+    };
+
+    let request = ShareRequest {
+        expires_in_hours: hours.trim().parse().ok(),
+        max_downloads: downloads.trim().parse().ok(),
+        password: Some(password).filter(|p| !p.is_empty()),
+    };
+
+    let link: ShareLink = client::fetch_with(
+        Request::post(&format!("/{}_id/{}/share", entry.kind(), entry.id())),
+        &request,
+    )
+    .await?;
+
+    let origin = window().location().origin().unwrap_or_default();
+    prompt("Share link", &format!("{origin}{}", link.url));
+
+    Ok(())
+}
+
+/// Empty answers inherit from the parent directory.
+async fn edit_policy(id: i32) -> Result<(), String> {
+    let PolicyResponse { own, effective } =
+        client::fetch(Request::get(&format!("/d_id/{id}/policy"))).await?;
+
+    let list = |types: &Option<Vec<String>>| types.clone().unwrap_or_default().join(", ");
+    let parse = |answer: String| {
+        let types = answer
+            .split(',')
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>();
+        (!types.is_empty()).then_some(types)
+    };
+
+    let Some(allowed) = prompt(
+        &format!(
+            "Allowed types, like image/* (now: {})",
+            Some(list(&effective.allowed_mime_types))
+                .filter(|l| !l.is_empty())
+                .unwrap_or("anything".into())
+        ),
+        &list(&own.allowed_mime_types),
+    ) else {
+        return Ok(());
+    };
+    let Some(denied) = prompt(
+        &format!(
+            "Denied types (now: {})",
+            Some(list(&effective.denied_mime_types))
+                .filter(|l| !l.is_empty())
+                .unwrap_or("nothing".into())
+        ),
+        &list(&own.denied_mime_types),
+    ) else {
+        return Ok(());
+    };
+    let Some(max_size) = prompt(
+        &format!(
+            "Maximum file size in MB (now: {})",
+            effective.max_file_size.unwrap_or_default() as f64 / 1e6
+        ),
+        &own.max_file_size
+            .map(|size| (size as f64 / 1e6).to_string())
+            .unwrap_or_default(),
+    ) else {
+        return Ok(());
+    };
+
+    let policy = DirectoryPolicy {
+        allowed_mime_types: parse(allowed),
+        denied_mime_types: parse(denied),
+        max_file_size: max_size
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .map(|mb| (mb * 1e6).round() as i64),
+    };
+
+    client::fetch_with::<_, DirectoryPolicy>(Request::post(&format!("/d_id/{id}/policy")), &policy)
+        .await
+        .map(|_| ())
+}
+
+async fn delete(entry: Entry) -> Result<(), String> {
+    client::send(Request::delete(&format!(
+        "/{}_id/{}",
+        entry.kind(),
+        entry.id()
+    )))
+    .await
+    .map(|_| ())
+}
+
+#[component]
+pub fn BrowserPage() -> impl IntoView {
+    let params = use_params_map();
+    let navigate = use_navigate();
+
+    // below the root, empty for the root itself
+    let path = Memo::new(move |_| {
+        params
+            .read()
+            .get("path")
+            .unwrap_or_default()
+            .trim_matches('/')
+            .to_string()
+    });
+
+    // bumped after every change to load everything again
+    let reload = RwSignal::new(0u32);
+    let selected = RwSignal::new(None::<usize>);
+    let uploads = RwSignal::new(Vec::<Upload>::new());
+
+    let directory = LocalResource::new(move || {
+        let path = path.get();
+        reload.track();
+
+        async move {
+            let api = api_path(&path);
+            let directory = client::fetch::<Directory>(Request::get(&format!("/d/{api}"))).await?;
+            let contents = client::fetch::<DirectoryContents>(Request::get(&format!(
+                "/d/{api}?contents=true"
+            )))
+            .await?;
+
+            Ok::<_, String>((directory, contents))
+        }
+    });
+
+    let entries = Signal::derive(move || {
+        directory
+            .get()
+            .and_then(Result::ok)
+            .map(|(_, contents)| entries(contents))
+            .unwrap_or_default()
+    });
+
+    // the root is handed out with id 0
+    let directory_id = Signal::derive(move || {
+        directory
+            .get()
+            .and_then(Result::ok)
+            .map(|(directory, _)| directory.id)
+            .filter(|id| *id != 0)
+    });
+
+    let crumbs = Signal::derive(move || {
+        let path = path.get();
+        let mut crumbs = vec![("~".to_string(), browse_url(""))];
+        let mut below = Vec::new();
+
+        for segment in path.split('/').filter(|s| !s.is_empty()) {
+            below.push(segment);
+            crumbs.push((segment.to_string(), browse_url(&below.join("/"))));
+        }
+
+        crumbs
+    });
+
+    Effect::new(move |_| {
+        path.track();
+        selected.set(None);
+    });
+
+    let selected_entry = Signal::derive(move || {
+        selected
+            .get()
+            .and_then(|i| entries.with(|e| e.get(i).cloned()))
+    });
+
+    let open = {
+        let navigate = navigate.clone();
+        move |entry: Entry| match entry {
+            Entry::Directory(dir) => {
+                let below = dir.dir_path.trim_start_matches("/~").trim_matches('/');
+                navigate(&browse_url(below), Default::default());
+            }
+            Entry::File(file) => {
+                let _ = window().open_with_url_and_target(&file_url(&file), "_blank");
+            }
+        }
+    };
+
+    let parent = {
+        let navigate = navigate.clone();
+        move || {
+            let path = path.get_untracked();
+            if !path.is_empty() {
+                let parent = path.rsplit_once('/').map_or("", |(parent, _)| parent);
+                navigate(&browse_url(parent), Default::default());
+            }
+        }
+    };
+
+    let on_key = {
+        let open = open.clone();
+        move |ev: KeyboardEvent| {
+            let len = entries.with(Vec::len);
+            let last = len.saturating_sub(1);
+
+            match ev.key().as_str() {
+                "ArrowDown" | "j" => {
+                    selected.update(|s| *s = Some(s.map_or(0, |i| (i + 1).min(last))))
+                }
+                "ArrowUp" | "k" => {
+                    selected.update(|s| *s = Some(s.map_or(0, |i| i.saturating_sub(1))))
+                }
+                "Home" => selected.set(Some(0)),
+                "End" => selected.set(Some(last)),
+                "Enter" => {
+                    if let Some(entry) = selected_entry.get_untracked() {
+                        open(entry);
+                    }
+                }
+                "Backspace" => parent(),
+                "F2" => {
+                    if let Some(entry) = selected_entry.get_untracked() {
+                        run(reload, rename(entry, directory_id.get_untracked()));
+                    }
+                }
+                "Delete" => {
+                    if let Some(entry) = selected_entry.get_untracked() {
+                        run(reload, delete(entry));
+                    }
+                }
+                _ => return,
+            }
+
+            ev.prevent_default();
+            if len == 0 {
+                selected.set(None);
+            }
+        }
+    };
+
+    // keeps the selection in view while moving it with the keyboard
+    Effect::new(move |_| {
+        if let Some(i) = selected.get()
+            && let Some(row) = document().get_element_by_id(&format!("entry-{i}"))
+        {
+            row.scroll_into_view_with_bool(false);
+        }
+    });
+
+    let on_files = Callback::new(move |files: Vec<web_sys::File>| {
+        let query = directory_id
+            .get_untracked()
+            .map(|id| format!("?directory_id={id}"))
+            .unwrap_or_default();
+
+        // one request each, so every file gets its own progress
+        for file in files {
+            track_upload(
+                uploads,
+                "POST",
+                format!("/upload/f{query}"),
+                vec![file],
+                move |_| reload.update(|n| *n += 1),
+            );
+        }
+    });
+
+    let new_directory = RwSignal::new(String::new());
+    let create_directory = move |ev: leptos::ev::SubmitEvent| {
+        ev.prevent_default();
+
+        let name = new_directory.get_untracked();
+        let parent = directory_id
+            .get_untracked()
+            .map(|id| format!("parent_id={id}&"))
+            .unwrap_or_default();
+
+        run(reload, async move {
+            client::send(Request::post(&format!(
+                "/upload/d?{parent}name={}",
+                encode_query(&name)
+            )))
+            .await?;
+            new_directory.set(String::new());
+            Ok(())
+        });
+    };
+
+    view! {
+        <div class="mx-auto flex max-w-6xl flex-col gap-6 p-6">
+            <header class="flex flex-wrap items-center justify-between gap-4">
+                <h1 class="text-2xl font-bold">"File Browser"</h1>
+                <Usage reload />
+            </header>
+
+            <Breadcrumbs crumbs />
+
+            <div class="grid gap-6 md:grid-cols-3">
+                <section class="md:col-span-2 rounded bg-white p-4 shadow">
+                    <form class="mb-4 flex gap-2" on:submit=create_directory>
+                        <input
+                            type="text"
+                            required
+                            placeholder="New directory"
+                            class="flex-grow rounded border border-gray-300 px-2 py-1"
+                            bind:value=new_directory
+                        />
+                        <button type="submit" class="rounded bg-nf-color px-3 py-1 text-white">
+                            "+"
+                        </button>
+                    </form>
+
+                    <p class="mb-2 text-xs text-gray-500">
+                        "Arrow keys select, Enter opens, Backspace goes up, F2 renames and Delete moves to the trash."
+                    </p>
+
+                    {move || {
+                        directory
+                            .get()
+                            .and_then(Result::err)
+                            .map(|e| view! { <p class="text-red-700">{e}</p> })
+                    }}
+
+                    <ul
+                        tabindex="0"
+                        class="flex max-h-[60vh] flex-col overflow-y-auto focus:outline-none focus:ring"
+                        aria-label="Directory contents"
+                        on:keydown=on_key
+                    >
+                        {move || {
+                            let open = open.clone();
+                            entries
+                                .get()
+                                .into_iter()
+                                .enumerate()
+                                .map(|(i, entry)| {
+                                    let open = open.clone();
+                                    view! {
+                                        <EntryRow
+                                            entry
+                                            index=i
+                                            selected
+                                            reload
+                                            path
+                                            directory_id
+                                            on_open=Callback::new(open)
+                                        />
+                                    }
+                                })
+                                .collect_view()
+                        }}
+                    </ul>
+
+                    <div class="mt-4">
+                        <DropZone on_files multiple=true>
+                            <span>"Drop files here or click to choose them"</span>
+                        </DropZone>
+                        <UploadList uploads />
+                    </div>
+                </section>
+
+                <aside class="flex flex-col gap-6">
+                    <section class="rounded bg-white p-4 shadow">
+                        <h2 class="mb-2 font-bold">"Preview"</h2>
+                        {move || match selected_entry.get() {
+                            Some(Entry::File(file)) => view! { <Preview file /> }.into_any(),
+                            Some(Entry::Directory(dir)) => {
+                                view! { <p>{format!("📁 {}", dir.dir_path)}</p> }.into_any()
+                            }
+                            None => view! { <p class="text-gray-500">"Nothing selected."</p> }.into_any(),
+                        }}
+                    </section>
+
+                    <TrashSection reload />
+                </aside>
+            </div>
+        </div>
+    }
+}
+
+/// What clicking one of the buttons of an entry does.
+type Action = Box<dyn Fn() + Send + Sync>;
+
+#[component]
+fn EntryRow(
+    entry: Entry,
+    index: usize,
+    selected: RwSignal<Option<usize>>,
+    reload: RwSignal<u32>,
+    path: Memo<String>,
+    directory_id: Signal<Option<i32>>,
+    on_open: Callback<Entry>,
+) -> impl IntoView {
+    let visibility = match entry.visibility() {
+        Some(Visibility::Private) => "🔒",
+        Some(Visibility::Unlisted) => "🙈",
+        _ => "👁️",
+    };
+
+    let actions = {
+        let entry = entry.clone();
+        let mut actions: Vec<(&'static str, &'static str, Action)> = vec![
+            ("✏️", "Rename", {
+                let entry = entry.clone();
+                Box::new(move || run(reload, rename(entry.clone(), directory_id.get_untracked())))
+            }),
+            ("➡️", "Move", {
+                let entry = entry.clone();
+                Box::new(move || {
+                    run(
+                        reload,
+                        transfer(entry.clone(), "move", path.get_untracked()),
+                    )
+                })
+            }),
+            ("📋", "Copy", {
+                let entry = entry.clone();
+                Box::new(move || {
+                    run(
+                        reload,
+                        transfer(entry.clone(), "copy", path.get_untracked()),
+                    )
+                })
+            }),
+            (visibility, "Visibility", {
+                let entry = entry.clone();
+                Box::new(move || run(reload, set_visibility(entry.clone())))
+            }),
+            ("🔗", "Share", {
+                let entry = entry.clone();
+                Box::new(move || run(reload, share(entry.clone())))
+            }),
+        ];
+
+        if let Entry::Directory(dir) = &entry {
+            let id = dir.id;
+            actions.push((
+                "📦",
+                "Download as zip",
+                Box::new(move || {
+                    let _ = window().location().set_href(&format!("/d_id/{id}/zip"));
+                }),
+            ));
+            actions.push((
+                "⚙️",
+                "Upload policy",
+                Box::new(move || run(reload, edit_policy(id))),
+            ));
+        }
+
+        actions.push(("❌", "Move to the trash", {
+            let entry = entry.clone();
+            Box::new(move || run(reload, delete(entry.clone())))
+        }));
+
+        actions
+    };
+
+    let label = format!("{} {}", entry.icon(), entry.name());
+
+    view! {
+        <li
+            id=format!("entry-{index}")
+            class="flex items-center justify-between gap-2 rounded px-2 py-1 cursor-pointer"
+            class:bg-nf-white=move || selected.get() == Some(index)
+            on:click=move |_| selected.set(Some(index))
+            on:dblclick={
+                let entry = entry.clone();
+                move |_| on_open.run(entry.clone())
+            }
+        >
+            <span class="truncate">{label}</span>
+            <span class="flex shrink-0 gap-1">
+                {actions
+                    .into_iter()
+                    .map(|(icon, title, action)| {
+                        view! {
+                            <button
+                                title=title
+                                aria-label=title
+                                on:click=move |ev| {
+                                    ev.stop_propagation();
+                                    action();
+                                }
+                            >
+                                {icon}
+                            </button>
+                        }
+                    })
+                    .collect_view()}
+            </span>
+        </li>
+    }
+}
+
+/// What a file looks like, as far as the browser can show it.
+#[component]
+fn Preview(file: File) -> impl IntoView {
+    let url = file_url(&file);
+    let mime = file.mime_type.clone();
+
+    // the files app makes smaller versions of these, see `variant`
+    let resizable = matches!(mime.as_str(), "image/jpeg" | "image/png" | "image/webp");
+
+    let preview = if resizable {
+        view! { <img class="max-h-80 w-full object-contain" src=format!("{url}?w=480&fmt=webp") /> }
+            .into_any()
+    } else if mime.starts_with("image/") {
+        view! { <img class="max-h-80 w-full object-contain" src=url.clone() /> }.into_any()
+    } else if mime.starts_with("video/") {
+        view! { <video class="w-full" controls src=url.clone()></video> }.into_any()
+    } else if mime.starts_with("audio/") {
+        view! { <audio class="w-full" controls src=url.clone()></audio> }.into_any()
+    } else if mime.starts_with("text/") || mime == "application/pdf" || mime == "application/json" {
+        // uploaded html must not run with the origin of the files app
+        view! { <iframe class="h-80 w-full border" sandbox="" src=url.clone()></iframe> }.into_any()
+    } else {
+        view! { <p class="text-gray-500">"No preview for this type."</p> }.into_any()
+    };
+
+    view! {
+        <div class="flex flex-col gap-2 text-sm">
+            {preview}
+            <a class="font-bold underline" href=url target="_blank">
+                {file.file_name.clone()}
+            </a>
+            <span>{mime}</span>
+            <span>
+                {file.size.map(|size| format!("{:.1} MB", size as f64 / 1e6)).unwrap_or_default()}
+            </span>
+            <span>{format!("Uploaded {}", file.uploaded_at.format("%Y-%m-%d %H:%M"))}</span>
+        </div>
+    }
+}
+
+#[component]
+fn Usage(reload: RwSignal<u32>) -> impl IntoView {
+    let usage = LocalResource::new(move || {
+        reload.track();
+        async move { client::fetch::<StorageUsage>(Request::get("/usage")).await }
+    });
+
+    let mb = |bytes: i64| format!("{:.1} MB", bytes as f64 / 1e6);
+
+    view! {
+        <p class="text-sm italic">
+            {move || {
+                usage
+                    .get()
+                    .and_then(Result::ok)
+                    .map(|usage| match usage.quota {
+                        Some(quota) => format!("{} of {} used", mb(usage.used), mb(quota)),
+                        None => format!("{} used", mb(usage.used)),
+                    })
+            }}
+        </p>
+    }
+}
+
+#[component]
+fn TrashSection(reload: RwSignal<u32>) -> impl IntoView {
+    let trash = LocalResource::new(move || {
+        reload.track();
+        async move { client::fetch::<Trash>(Request::get("/trash")).await }
+    });
+
+    let restore = move |kind: &'static str, id: String| {
+        run(reload, async move {
+            client::send(Request::post(&format!("/{kind}_id/{id}/restore")))
+                .await
+                .map(|_| ())
+        })
+    };
+
+    let empty = move |_| {
+        if !confirm("Delete everything in the trash for good?") {
+            return;
+        }
+
+        run(reload, async {
+            client::send(Request::delete("/trash")).await.map(|_| ())
+        });
+    };
+
+    view! {
+        <section class="rounded bg-white p-4 shadow">
+            <h2 class="mb-2 font-bold">"🗑️ Trash"</h2>
+            {move || {
+                trash
+                    .get()
+                    .and_then(Result::ok)
+                    .map(|trash| {
+                        let items = trash
+                            .directories
+                            .into_iter()
+                            .map(|d| ("d", d.id.to_string(), format!("📁 {}", d.dir_path), d.deleted_at))
+                            .chain(
+                                trash
+                                    .files
+                                    .into_iter()
+                                    .map(|f| ("f", f.id.to_string(), format!("📄 {}", f.file_path), f.deleted_at)),
+                            )
+                            .collect::<Vec<_>>();
+
+                        view! {
+                            <p class="mb-2 text-sm italic">
+                                {format!("Deleted files are kept for {} days.", trash.retention_days)}
+                            </p>
+                            <ul class="flex flex-col gap-1 text-sm">
+                                {items
+                                    .into_iter()
+                                    .map(|(kind, id, label, deleted_at)| {
+                                        view! {
+                                            <li class="flex justify-between gap-2">
+                                                <span class="truncate" title=deleted_at.map(|d| d.to_string())>
+                                                    {label}
+                                                </span>
+                                                <button
+                                                    class="shrink-0 underline"
+                                                    on:click=move |_| restore(kind, id.clone())
+                                                >
+                                                    "Restore"
+                                                </button>
+                                            </li>
+                                        }
+                                    })
+                                    .collect_view()}
+                            </ul>
+                        }
+                    })
+            }}
+            <button class="mt-4 rounded bg-red-700 px-3 py-1 text-white" on:click=empty>
+                "Empty trash"
+            </button>
+        </section>
+    }
+}
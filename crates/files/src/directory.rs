@@ -1,5 +1,3 @@
-use std::path::PathBuf;
-
 use axum::{
     Extension, Json,
     extract::{Path, Query},
@@ -7,14 +5,21 @@ use axum::{
     response::IntoResponse,
 };
 
-use serde::Deserialize;
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
 
 use common::{
     api::{ApiError, ApiResult},
+    db::sqlx,
+    jobs::Job,
     models::{Directory, User},
 };
 
-use super::{DIRECTORY, ROOT, get_directory_contents, get_full_path};
+use super::{
+    ROOT, TargetQuery, blob, check_name, conflict, get_directory_contents, get_full_path,
+    get_subtree, policy, resolve_target, trash,
+};
 
 #[derive(Deserialize, Debug)]
 pub struct DirectoryCreateQuery {
@@ -35,7 +40,7 @@ pub async fn create(
         && common::db_query_as!(
             Directory,
             fetch_optional,
-            "SELECT * FROM directories WHERE id = $1",
+            "SELECT * FROM directories WHERE id = $1 AND deleted_at IS NULL",
             id
         )
         .unwrap_or(None)
@@ -68,40 +73,276 @@ pub async fn create(
     .map_err(Into::into)
 }
 
+/// Removes a directory with everything below it, run through the job queue
+/// as there is no telling how much is in there.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeleteDirectoryJob {
+    pub id: i32,
+}
+
+impl Job for DeleteDirectoryJob {
+    const KIND: &'static str = "files::delete_directory";
+
+    async fn run(self) -> Result<(), String> {
+        delete_recursive(self.id).await.map_err(|e| match e {
+            ApiError::MultipleMessages(_, errors) => errors.join(", "),
+            e => e.to_string(),
+        })
+    }
+}
+
 #[tracing::instrument(skip(user))]
-pub async fn delete_by_id(Path(id): Path<i32>, user: Option<Extension<User>>) -> ApiResult<()> {
+pub async fn delete_by_id(
+    Path(id): Path<i32>,
+    user: Option<Extension<User>>,
+) -> ApiResult<impl IntoResponse> {
     if !user.as_ref().is_some_and(|u| u.admin) {
         return Err(ApiError::unauthorized());
     };
 
-    let mut errors = Vec::new();
+    trash::trash_recursive(id).await?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Removes the directory, its subdirectories and their files in one transaction,
+/// their content is left to the garbage collection.
+pub async fn delete_recursive(id: i32) -> ApiResult<()> {
+    let mut tx = common::db::db().begin().await?;
+
+    let subtree = get_subtree(&mut *tx, id).await?;
+
+    let files = sqlx::query("DELETE FROM files WHERE directory_id = ANY($1)")
+        .bind(&subtree)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
 
-    let contents = get_directory_contents(Some(id)).await?;
+    sqlx::query("DELETE FROM directories WHERE id = ANY($1)")
+        .bind(&subtree)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    tracing::info!("Deleted {} directories with {files} files", subtree.len());
+
+    blob::collect_garbage().await;
+
+    Ok(())
+}
 
-    for dir in contents.directories {
-        Box::pin(delete_by_id(Path(dir.id), user.clone())).await?;
+/// The directory and the new path for it, after making sure it is not moved into itself.
+async fn prepare_target(
+    id: i32,
+    target: &TargetQuery,
+) -> ApiResult<(Directory, Option<i32>, String)> {
+    let dir = common::db_query_as!(
+        Directory,
+        fetch_one,
+        "SELECT * FROM directories WHERE id = $1 AND deleted_at IS NULL",
+        id
+    )
+    .map_err(|_| ApiError::not_found())?;
+
+    let (parent_id, parent_path) = resolve_target(target).await?;
+    let name = target.name.clone().unwrap_or(dir.dir_name.clone());
+    check_name(&name)?;
+
+    if parent_path == dir.dir_path || parent_path.starts_with(&format!("{}/", dir.dir_path)) {
+        return Err(ApiError::Message(
+            StatusCode::BAD_REQUEST,
+            "A directory can not go inside of itself.".to_string(),
+        ));
     }
 
-    let success = common::db_query!(execute, "DELETE FROM directories WHERE id = $1", "", id)
-        .map_or(0, |r| r.rows_affected());
+    let path = format!("{parent_path}/{name}");
 
-    if success == 1 {
-        for file in contents.files {
-            let file_path: PathBuf = [DIRECTORY, &file.id.to_string()].iter().collect();
-            if let Err(e) = tokio::fs::remove_file(file_path).await {
-                errors.push(e.to_string());
-            }
-        }
+    Ok((dir, parent_id, path))
+}
+
+/// Moves and/or renames a directory, the paths of everything below it are
+/// rewritten in the same transaction.
+#[tracing::instrument(skip(user))]
+pub async fn move_by_id(
+    Path(id): Path<i32>,
+    user: Option<Extension<User>>,
+    Query(target): Query<TargetQuery>,
+) -> ApiResult<Json<Directory>> {
+    if !user.is_some_and(|u| u.admin) {
+        return Err(ApiError::unauthorized());
+    };
+
+    let (dir, parent_id, path) = prepare_target(id, &target).await?;
+    let name = path.rsplit('/').next().unwrap_or_default();
+
+    let mut tx = common::db::db().begin().await?;
+
+    // the old path is taken from the locked row, in case it moved in the meantime
+    let old_path = sqlx::query_scalar::<_, String>(
+        "SELECT dir_path FROM directories WHERE id = $1 FOR UPDATE",
+    )
+    .bind(dir.id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|_| ApiError::not_found())?;
+
+    let moved = sqlx::query_as::<_, Directory>(
+        r#"
+        UPDATE directories SET parent_id = $2, dir_name = $3, dir_path = $4
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(dir.id)
+    .bind(parent_id)
+    .bind(name)
+    .bind(&path)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| conflict(e, &path))?;
+
+    let subtree = get_subtree(&mut *tx, dir.id).await?;
+
+    // nothing can be below a path that did not exist, so these do not conflict
+    sqlx::query(
+        r#"
+        UPDATE directories SET dir_path = $2 || substr(dir_path, length($1) + 1)
+        WHERE id = ANY($3) AND id <> $4
+        "#,
+    )
+    .bind(&old_path)
+    .bind(&path)
+    .bind(&subtree)
+    .bind(dir.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| conflict(e, &path))?;
+
+    sqlx::query(
+        r#"
+        UPDATE files SET file_path = $2 || substr(file_path, length($1) + 1)
+        WHERE directory_id = ANY($3)
+        "#,
+    )
+    .bind(&old_path)
+    .bind(&path)
+    .bind(&subtree)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| conflict(e, &path))?;
+
+    // the policies moving along still apply below them, everything else comes from the target
+    policy::check_directories(&mut tx, &subtree).await?;
+
+    tx.commit().await?;
+
+    Ok(Json(moved))
+}
+
+/// Copies a directory with everything below it, files share their blobs with the originals.
+#[tracing::instrument(skip(user))]
+pub async fn copy_by_id(
+    Path(id): Path<i32>,
+    user: Option<Extension<User>>,
+    Query(target): Query<TargetQuery>,
+) -> ApiResult<Json<Directory>> {
+    if !user.is_some_and(|u| u.admin) {
+        return Err(ApiError::unauthorized());
+    };
+
+    let (dir, parent_id, path) = prepare_target(id, &target).await?;
+    let name = path.rsplit('/').next().unwrap_or_default();
+
+    let mut tx = common::db::db().begin().await?;
+
+    let ids = get_subtree(&mut *tx, dir.id).await?;
+
+    // parents come first in the subtree, so they are always copied before their children,
+    // the trash is left behind
+    let subtree = sqlx::query_as::<_, Directory>(
+        r#"
+        SELECT * FROM directories WHERE id = ANY($1) AND deleted_at IS NULL
+        ORDER BY array_position($1, id)
+        FOR SHARE
+        "#,
+    )
+    .bind(&ids)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let legacy = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM files WHERE sha256 IS NULL AND directory_id = ANY($1)",
+    )
+    .bind(&ids)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    if legacy > 0 {
+        return Err(ApiError::Message(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Files are still being moved to the blob store, try again later.".into(),
+        ));
     }
 
-    if errors.is_empty() {
-        Ok(())
-    } else {
-        Err(ApiError::MultipleMessages(
-            StatusCode::INTERNAL_SERVER_ERROR,
-            errors,
-        ))
+    let mut copies = HashMap::new();
+    let mut root = None;
+
+    for original in subtree {
+        let (new_parent, new_name) = if original.id == dir.id {
+            (parent_id, name.to_string())
+        } else {
+            (
+                original.parent_id.and_then(|p| copies.get(&p).copied()),
+                original.dir_name.clone(),
+            )
+        };
+        let new_path = format!("{path}{}", &original.dir_path[dir.dir_path.len()..]);
+
+        let copy = sqlx::query_as::<_, Directory>(
+            r#"
+            INSERT INTO directories (parent_id, dir_name, dir_path, visibility)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+        )
+        .bind(new_parent)
+        .bind(&new_name)
+        .bind(&new_path)
+        .bind(original.visibility)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| conflict(e, &new_path))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO files (
+                id, directory_id, file_name, file_path, mime_type, uploaded_at, size, sha256,
+                visibility
+            )
+            SELECT gen_random_uuid(), $2, file_name, $3 || '/' || file_name, mime_type, NOW(),
+                size, sha256, visibility
+            FROM files WHERE directory_id = $1 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(original.id)
+        .bind(copy.id)
+        .bind(&new_path)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| conflict(e, &new_path))?;
+
+        copies.insert(original.id, copy.id);
+        root.get_or_insert(copy);
     }
+
+    // the copies have no policies of their own, so the target decides for all of them
+    let copied = copies.into_values().collect::<Vec<_>>();
+    policy::check_directories(&mut tx, &copied).await?;
+
+    tx.commit().await?;
+
+    root.map(Json).ok_or(ApiError::not_found())
 }
 
 #[derive(Deserialize, Debug)]
@@ -134,7 +375,7 @@ pub async fn get_by_id(
     let dir = common::db_query_as!(
         Directory,
         fetch_optional,
-        "SELECT * FROM directories WHERE id = $1",
+        "SELECT * FROM directories WHERE id = $1 AND deleted_at IS NULL",
         id
     )
     .unwrap_or(None);
@@ -177,7 +418,7 @@ pub async fn traverse(
     if let Some(dir) = common::db_query_as!(
         Directory,
         fetch_optional,
-        "SELECT * FROM directories WHERE dir_path = $1",
+        "SELECT * FROM directories WHERE dir_path = $1 AND deleted_at IS NULL",
         format!("/{dir_path}")
     )
     .unwrap_or(None)
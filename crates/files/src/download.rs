@@ -0,0 +1,455 @@
+use std::{ops::RangeInclusive, time::SystemTime};
+
+use axum::{
+    body::{Body, Bytes},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use futures::{StreamExt, stream};
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use uuid::Uuid;
+
+use common::{
+    api::{ApiError, ApiResult},
+    models::File,
+};
+
+use super::{
+    blob,
+    encoding::{self, Encoded},
+    storage::{ByteStream, storage},
+};
+
+/// Requests asking for more ranges than this get the whole file instead.
+const MAX_RANGES: usize = 16;
+
+/// For files with a [`hashed`] name, which may not be public.
+pub const IMMUTABLE_PRIVATE: &str = "private, max-age=31536000, immutable";
+pub const IMMUTABLE_PUBLIC: &str = "public, max-age=31536000, immutable";
+
+/// Streams a stored file, honouring `Range`, `If-None-Match` and `If-Modified-Since`.
+///
+/// Compressed versions made by [`encoding`] are sent to clients accepting them,
+/// ranges then refer to the compressed bytes.
+#[tracing::instrument(skip(headers), fields(id = %file.id))]
+pub async fn serve(file: &File, headers: &HeaderMap) -> ApiResult<Response> {
+    let encoded = encoding::negotiate(file, headers).await?;
+
+    let key = match &encoded {
+        Some(encoded) => blob::blob_key(&encoded.sha256),
+        None => blob::content_key(file),
+    };
+
+    let len = storage()
+        .size(&key)
+        .await
+        .map_err(|_| ApiError::not_found())?;
+
+    let etag = etag(file, encoded.as_ref());
+    let last_modified = SystemTime::from(file.uploaded_at.and_utc());
+    let mime_type = mime_type(file);
+
+    let mut common_headers = HeaderMap::new();
+    common_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    common_headers.insert(header::ETAG, header_value(&etag)?);
+    common_headers.insert(
+        header::LAST_MODIFIED,
+        header_value(&httpdate::fmt_http_date(last_modified))?,
+    );
+    if encoding::compressible(mime_type) {
+        common_headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+    }
+    if let Some(encoded) = &encoded {
+        common_headers.insert(
+            header::CONTENT_ENCODING,
+            HeaderValue::from_static(encoded.encoding.name()),
+        );
+    }
+    // the name changes along with the content
+    if hashed(&file.file_name) {
+        common_headers.insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static(IMMUTABLE_PRIVATE),
+        );
+    }
+
+    if not_modified(headers, &etag, last_modified) {
+        return Ok((StatusCode::NOT_MODIFIED, common_headers).into_response());
+    }
+
+    common_headers.insert(
+        header::CONTENT_DISPOSITION,
+        header_value(&content_disposition("inline", &file.file_name))?,
+    );
+
+    // a stale If-Range means the client wants the whole new file
+    let range = headers
+        .get(header::RANGE)
+        .filter(|_| {
+            headers
+                .get(header::IF_RANGE)
+                .is_none_or(|if_range| if_range.as_bytes() == etag.as_bytes())
+        })
+        .and_then(|range| range.to_str().ok());
+
+    let ranges = match range.map(|range| parse_ranges(range, len)) {
+        None | Some(Ranges::Ignored) => None,
+        Some(Ranges::Unsatisfiable) => {
+            common_headers.insert(
+                header::CONTENT_RANGE,
+                header_value(&format!("bytes */{len}"))?,
+            );
+            return Ok((StatusCode::RANGE_NOT_SATISFIABLE, common_headers).into_response());
+        }
+        Some(Ranges::Satisfiable(ranges)) => Some(ranges),
+    };
+
+    match ranges.as_deref() {
+        None => {
+            common_headers.insert(header::CONTENT_TYPE, header_value(mime_type)?);
+            common_headers.insert(header::CONTENT_LENGTH, len.into());
+
+            let body = Body::from_stream(
+                storage()
+                    .get(&key)
+                    .await
+                    .map_err(|_| ApiError::not_found())?,
+            );
+            Ok((StatusCode::OK, common_headers, body).into_response())
+        }
+        Some([range]) => {
+            common_headers.insert(header::CONTENT_TYPE, header_value(mime_type)?);
+            common_headers.insert(header::CONTENT_LENGTH, range_len(range).into());
+            common_headers.insert(
+                header::CONTENT_RANGE,
+                header_value(&format!("bytes {}-{}/{len}", range.start(), range.end()))?,
+            );
+
+            let body = Body::from_stream(open(&key, range).await?);
+            Ok((StatusCode::PARTIAL_CONTENT, common_headers, body).into_response())
+        }
+        Some(ranges) => {
+            let boundary = Uuid::new_v4().simple().to_string();
+
+            let mut parts = Vec::new();
+            let mut content_length = 0;
+
+            for (i, range) in ranges.iter().enumerate() {
+                let part_header = Bytes::from(format!(
+                    "{}--{boundary}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{len}\r\n\r\n",
+                    if i == 0 { "" } else { "\r\n" },
+                    mime_type,
+                    range.start(),
+                    range.end(),
+                ));
+                content_length += part_header.len() as u64 + range_len(range);
+
+                let body = open(&key, range).await?;
+                parts.push(
+                    stream::once(async move { Ok(part_header) })
+                        .chain(body)
+                        .boxed(),
+                );
+            }
+
+            let closing = Bytes::from(format!("\r\n--{boundary}--\r\n"));
+            content_length += closing.len() as u64;
+            parts.push(stream::once(async move { Ok(closing) }).boxed());
+
+            common_headers.insert(
+                header::CONTENT_TYPE,
+                header_value(&format!("multipart/byteranges; boundary={boundary}"))?,
+            );
+            common_headers.insert(header::CONTENT_LENGTH, content_length.into());
+
+            let body = Body::from_stream(stream::iter(parts).flatten());
+            Ok((StatusCode::PARTIAL_CONTENT, common_headers, body).into_response())
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Ranges {
+    /// not something we understand, so it is treated like there was no range at all
+    Ignored,
+    Unsatisfiable,
+    Satisfiable(Vec<RangeInclusive<u64>>),
+}
+
+/// Parses a `bytes=` range header, see RFC 9110 section 14.1.
+fn parse_ranges(header: &str, len: u64) -> Ranges {
+    let Some(specs) = header.trim().strip_prefix("bytes=") else {
+        return Ranges::Ignored;
+    };
+
+    let mut ranges = Vec::new();
+
+    for spec in specs.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let Some((start, end)) = spec.split_once('-') else {
+            return Ranges::Ignored;
+        };
+
+        let range = match (start.trim(), end.trim()) {
+            ("", "") => return Ranges::Ignored,
+            // the last n bytes
+            ("", suffix) => match suffix.parse::<u64>() {
+                Ok(0) => continue,
+                Ok(suffix) if len > 0 => len.saturating_sub(suffix)..=len - 1,
+                Ok(_) => continue,
+                Err(_) => return Ranges::Ignored,
+            },
+            (start, end) => {
+                let Ok(start) = start.parse::<u64>() else {
+                    return Ranges::Ignored;
+                };
+                let end = match end {
+                    "" => u64::MAX,
+                    end => match end.parse::<u64>() {
+                        Ok(end) if end >= start => end,
+                        _ => return Ranges::Ignored,
+                    },
+                };
+
+                if start >= len {
+                    continue;
+                }
+                start..=end.min(len - 1)
+            }
+        };
+
+        ranges.push(range);
+    }
+
+    if ranges.len() > MAX_RANGES {
+        Ranges::Ignored
+    } else if ranges.is_empty() {
+        Ranges::Unsatisfiable
+    } else {
+        Ranges::Satisfiable(ranges)
+    }
+}
+
+fn range_len(range: &RangeInclusive<u64>) -> u64 {
+    range.end() - range.start() + 1
+}
+
+/// Streams just the bytes of the range from the storage.
+async fn open(key: &str, range: &RangeInclusive<u64>) -> ApiResult<ByteStream> {
+    storage()
+        .range(key, *range.start()..range.end() + 1)
+        .await
+        .map_err(|_| ApiError::not_found())
+}
+
+/// The content hash where there is one, so image variants and compressed versions
+/// of a file get their own.
+fn etag(file: &File, encoded: Option<&Encoded>) -> String {
+    if let Some(encoded) = encoded {
+        return format!("\"{}\"", encoded.sha256);
+    }
+
+    match &file.sha256 {
+        Some(sha256) => format!("\"{sha256}\""),
+        None => format!(
+            "\"{}-{}\"",
+            file.id.simple(),
+            file.uploaded_at.and_utc().timestamp_micros()
+        ),
+    }
+}
+
+/// Files from before types were sniffed might have WASM as unknown binary, which
+/// browsers refuse to compile while streaming.
+fn mime_type(file: &File) -> &str {
+    if file.mime_type == "application/octet-stream" && file.file_name.ends_with(".wasm") {
+        "application/wasm"
+    } else {
+        &file.mime_type
+    }
+}
+
+/// Whether a name carries a hash of the content in a segment of its own, like
+/// `app-3f9c2a1b.js` or `index.BmZ1a2Xc.css` from bundlers.
+///
+/// Such files are cached for good, so names like `Chapter1.pdf` must not count.
+pub fn hashed(file_name: &str) -> bool {
+    let stem = file_name
+        .rsplit_once('.')
+        .map_or(file_name, |(stem, _)| stem);
+
+    stem.split(['.', '-'])
+        .skip(1)
+        .any(|part| hex_hash(part) || base64_hash(part))
+}
+
+/// At least 8 hex digits in one case, with both letters and digits among them.
+fn hex_hash(part: &str) -> bool {
+    let lower = part.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+    let upper = part.bytes().all(|b| matches!(b, b'0'..=b'9' | b'A'..=b'F'));
+
+    (8..=64).contains(&part.len())
+        && (lower || upper)
+        && part.bytes().any(|b| b.is_ascii_digit())
+        && part.bytes().any(|b| b.is_ascii_alphabetic())
+}
+
+/// Bundlers using base64 default to 8 characters, mixing cases and digits,
+/// words with a number in them have runs of lowercase letters.
+fn base64_hash(part: &str) -> bool {
+    let bytes = part.as_bytes();
+
+    (8..=32).contains(&bytes.len())
+        && bytes
+            .iter()
+            .all(|b| b.is_ascii_alphanumeric() || *b == b'_')
+        && bytes.iter().any(u8::is_ascii_uppercase)
+        && bytes.iter().any(u8::is_ascii_lowercase)
+        && bytes.iter().any(u8::is_ascii_digit)
+        && !bytes
+            .windows(3)
+            .any(|run| run.iter().all(u8::is_ascii_lowercase))
+}
+
+/// `If-None-Match` wins over `If-Modified-Since` when both are sent.
+fn not_modified(headers: &HeaderMap, etag: &str, last_modified: SystemTime) -> bool {
+    if let Some(if_none_match) = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|h| h.to_str().ok())
+    {
+        return if_none_match
+            .split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == "*" || tag == etag);
+    }
+
+    headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| httpdate::parse_http_date(h).ok())
+        // http dates only have seconds
+        .is_some_and(|since| {
+            httpdate::parse_http_date(&httpdate::fmt_http_date(last_modified))
+                .is_ok_and(|last_modified| last_modified <= since)
+        })
+}
+
+/// Keeps the original name when downloading, with an ascii fallback for old clients.
+pub(crate) fn content_disposition(disposition: &str, file_name: &str) -> String {
+    let fallback = file_name
+        .chars()
+        .map(|c| match c {
+            ' '..='~' if c != '"' && c != '\\' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+
+    format!(
+        "{disposition}; filename=\"{fallback}\"; filename*=UTF-8''{}",
+        utf8_percent_encode(file_name, NON_ALPHANUMERIC)
+    )
+}
+
+fn header_value(value: &str) -> ApiResult<HeaderValue> {
+    HeaderValue::from_str(value).map_err(|_| ApiError::internal_server_error())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MAX_RANGES, Ranges, hashed, parse_ranges};
+
+    #[test]
+    fn hashed_names_from_bundlers() {
+        for name in [
+            "app-3f9c2a1b.js",
+            "index.BmZ1a2Xc.css",
+            "main.0123456789abcdef.js",
+            "chunk-D2f8KxQ1.js",
+            "vendor.3F9C2A1B.js",
+            "index-B_x2Yc7Q.js",
+        ] {
+            assert!(hashed(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn names_with_numbers_are_not_hashed() {
+        for name in [
+            "Chapter1.pdf",
+            "Holiday1.jpg",
+            "Player1.png",
+            "Book-Chapter1.pdf",
+            "summer.Holiday1.jpg",
+            "Team-Player12.png",
+            "3f9c2a1b.js",
+            "report-20240101.pdf",
+            "IMG_20240101.jpg",
+            "DSC-01234567.jpg",
+            "notes.txt",
+        ] {
+            assert!(!hashed(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn satisfiable_ranges() {
+        for (header, len, ranges) in [
+            ("bytes=0-499", 1000, vec![0..=499]),
+            ("bytes=500-", 1000, vec![500..=999]),
+            ("bytes=-200", 1000, vec![800..=999]),
+            ("bytes=-2000", 1000, vec![0..=999]),
+            ("bytes=900-2000", 1000, vec![900..=999]),
+            ("bytes=999-999", 1000, vec![999..=999]),
+            (" bytes=0-0, -1", 1000, vec![0..=0, 999..=999]),
+            ("bytes=0-1,,5-", 10, vec![0..=1, 5..=9]),
+            ("bytes=2000-, 0-9", 1000, vec![0..=9]),
+        ] {
+            assert_eq!(
+                parse_ranges(header, len),
+                Ranges::Satisfiable(ranges),
+                "{header}"
+            );
+        }
+    }
+
+    #[test]
+    fn unsatisfiable_ranges() {
+        for (header, len) in [
+            ("bytes=1000-", 1000),
+            ("bytes=1000-1999", 1000),
+            ("bytes=-0", 1000),
+            ("bytes=0-", 0),
+            ("bytes=-5", 0),
+            ("bytes=0-0", 0),
+        ] {
+            assert_eq!(parse_ranges(header, len), Ranges::Unsatisfiable, "{header}");
+        }
+    }
+
+    #[test]
+    fn ignored_ranges() {
+        let too_many = vec!["0-0"; MAX_RANGES + 1].join(",");
+
+        for (header, len) in [
+            ("items=0-10", 1000),
+            ("bytes=5", 1000),
+            ("bytes=-", 1000),
+            ("bytes=500-499", 1000),
+            ("bytes=a-b", 1000),
+            ("bytes=0-x", 1000),
+            ("bytes=-x", 1000),
+            (&format!("bytes={too_many}"), 1000),
+        ] {
+            assert_eq!(parse_ranges(header, len), Ranges::Ignored, "{header}");
+        }
+    }
+
+    #[test]
+    fn max_ranges_are_allowed() {
+        let header = format!("bytes={}", vec!["0-0"; MAX_RANGES].join(","));
+
+        assert_eq!(
+            parse_ranges(&header, 1000),
+            Ranges::Satisfiable(vec![0..=0; MAX_RANGES])
+        );
+    }
+}
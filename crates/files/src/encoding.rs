@@ -0,0 +1,273 @@
+use std::{io::Write, sync::Arc};
+
+use axum::http::{HeaderMap, header};
+use flate2::{Compression, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+
+use common::{
+    api::ApiResult,
+    jobs::{self, Job},
+    models::File,
+};
+
+use super::{blob, storage};
+
+/// Smaller content gains next to nothing from being compressed.
+const MIN_SOURCE_BYTES: i64 = 1024;
+/// Compression happens in memory.
+const MAX_SOURCE_BYTES: i64 = 256 * 1024 * 1024;
+/// Compressed content that saves less than this is not kept.
+const MAX_RATIO: f64 = 0.9;
+
+const BROTLI_QUALITY: u32 = 11;
+const BROTLI_WINDOW: u32 = 22;
+
+/// How content can be sent compressed, in order of preference.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 2] = [Encoding::Brotli, Encoding::Gzip];
+
+    /// As sent in `Content-Encoding`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    fn encode(self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+
+        match self {
+            Encoding::Brotli => {
+                let mut encoder =
+                    brotli::CompressorWriter::new(&mut out, 4096, BROTLI_QUALITY, BROTLI_WINDOW);
+                encoder.write_all(data)?;
+                // finishes the stream, which can not fail writing into memory
+                encoder.into_inner();
+            }
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(&mut out, Compression::best());
+                encoder.write_all(data)?;
+                encoder.finish()?;
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+/// Text in all its forms and WASM, everything else tends to be compressed already.
+pub fn compressible(mime_type: &str) -> bool {
+    let essence = mime_type.split(';').next().unwrap_or_default().trim();
+
+    essence.starts_with("text/")
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+        || matches!(
+            essence,
+            "application/javascript"
+                | "application/json"
+                | "application/wasm"
+                | "application/xml"
+                | "application/x-javascript"
+                | "font/otf"
+                | "font/ttf"
+        )
+}
+
+/// Queues compressing the content of a freshly uploaded file, if it is worth it.
+pub async fn queue(file: &File) {
+    let Some(sha256) = &file.sha256 else {
+        return;
+    };
+
+    if !compressible(&file.mime_type)
+        || file
+            .size
+            .is_none_or(|size| !(MIN_SOURCE_BYTES..=MAX_SOURCE_BYTES).contains(&size))
+    {
+        return;
+    }
+
+    if let Err(e) = jobs::enqueue(&CompressJob {
+        sha256: sha256.clone(),
+    })
+    .await
+    {
+        tracing::error!("Error while queueing compression of {sha256}: {e:?}");
+    }
+}
+
+/// Stores the [`Encoding`]s of a blob, each as a blob of its own.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CompressJob {
+    pub sha256: String,
+}
+
+impl Job for CompressJob {
+    const KIND: &'static str = "files::compress";
+
+    async fn run(self) -> Result<(), String> {
+        let done = common::db_query_scalar!(
+            String,
+            fetch_all,
+            "SELECT encoding FROM encodings WHERE source_sha256 = $1",
+            &self.sha256
+        )
+        .map_err(|e| format!("DB error: {e}"))?;
+
+        let data: Arc<[u8]> = storage::read(&blob::blob_key(&self.sha256))
+            .await
+            .map_err(|e| format!("Could not read blob {}: {e}", self.sha256))?
+            .into();
+
+        for encoding in Encoding::ALL {
+            if done.iter().any(|name| name == encoding.name()) {
+                continue;
+            }
+
+            let encoded = tokio::task::spawn_blocking({
+                let data = data.clone();
+                move || encoding.encode(&data)
+            })
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("Could not compress {}: {e}", self.sha256))?;
+
+            if encoded.len() as f64 > data.len() as f64 * MAX_RATIO {
+                continue;
+            }
+
+            let (size, sha256) = blob::put(&encoded).await?;
+
+            common::db_query!(
+                execute,
+                r#"
+                INSERT INTO encodings (source_sha256, encoding, sha256, size)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (source_sha256, encoding) DO NOTHING
+                "#,
+                &self.sha256,
+                encoding.name(),
+                &sha256,
+                size,
+            )
+            .map_err(|e| format!("DB error: {e}"))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Queues a [`CompressJob`] for every file uploaded before they were compressed on ingest.
+///
+/// Queued once by the migration that came with it.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CompressExistingJob {}
+
+impl Job for CompressExistingJob {
+    const KIND: &'static str = "files::compress_existing";
+
+    async fn run(self) -> Result<(), String> {
+        let files = common::db_query_as!(
+            File,
+            fetch_all,
+            r#"
+            SELECT DISTINCT ON (f.sha256) f.* FROM files f
+            WHERE f.sha256 IS NOT NULL
+                AND NOT EXISTS (SELECT 1 FROM encodings e WHERE e.source_sha256 = f.sha256)
+            "#
+        )
+        .map_err(|e| format!("DB error: {e}"))?;
+
+        for file in &files {
+            queue(file).await;
+        }
+
+        Ok(())
+    }
+}
+
+/// A compressed version of a file to send instead of it.
+#[derive(Debug, Clone)]
+pub struct Encoded {
+    pub encoding: Encoding,
+    pub sha256: String,
+    pub size: i64,
+}
+
+/// The preferred stored [`Encoding`] the client accepts, see RFC 9110 section 12.5.3.
+pub async fn negotiate(file: &File, headers: &HeaderMap) -> ApiResult<Option<Encoded>> {
+    let Some(source) = &file.sha256 else {
+        return Ok(None);
+    };
+
+    let accepted = accepted(headers);
+    if accepted.is_empty() {
+        return Ok(None);
+    }
+
+    let stored = common::db_query_as!(
+        (String, String, i64),
+        fetch_all,
+        "SELECT encoding, sha256, size FROM encodings WHERE source_sha256 = $1",
+        source
+    )?;
+
+    Ok(accepted.into_iter().find_map(|encoding| {
+        stored
+            .iter()
+            .find(|(name, _, _)| name == encoding.name())
+            .map(|(_, sha256, size)| Encoded {
+                encoding,
+                sha256: sha256.clone(),
+                size: *size,
+            })
+    }))
+}
+
+/// The encodings allowed by `Accept-Encoding`, best first, ties go to [`Encoding::ALL`].
+fn accepted(headers: &HeaderMap) -> Vec<Encoding> {
+    let Some(accept) = headers
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|h| h.to_str().ok())
+    else {
+        return Vec::new();
+    };
+
+    let weights = accept
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let coding = parts.next()?.trim().to_ascii_lowercase();
+            let q = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+            Some((coding, q))
+        })
+        .collect::<Vec<_>>();
+
+    let weight = |encoding: Encoding| {
+        weights
+            .iter()
+            .find(|(coding, _)| coding == encoding.name())
+            .or_else(|| weights.iter().find(|(coding, _)| coding == "*"))
+            .map_or(0.0, |(_, q)| *q)
+    };
+
+    let mut accepted = Encoding::ALL
+        .into_iter()
+        .map(|encoding| (encoding, weight(encoding)))
+        .filter(|(_, q)| *q > 0.0)
+        .collect::<Vec<_>>();
+    // stable, so equal weights keep the order of preference
+    accepted.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+    accepted.into_iter().map(|(encoding, _)| encoding).collect()
+}
@@ -1,20 +1,23 @@
 use axum::{
     Extension, Json,
     extract::{Multipart, Path, Query},
-    http::{StatusCode, header},
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
 };
 
 use chrono::Utc;
 use serde::Deserialize;
-use std::path::PathBuf;
 use uuid::Uuid;
 
-use common::models::{Directory, File, User};
+use common::models::{Directory, File, User, Visibility};
 
 use common::api::{ApiError, ApiResult};
 
-use super::{DIRECTORY, PRIVATE, get_full_path, save_to_disk};
+use super::{
+    TargetQuery, blob, check_name, conflict, download, encoding, get_full_path, policy,
+    resolve_target, share,
+    variant::{self, VariantQuery},
+};
 
 #[derive(Deserialize, Debug)]
 pub struct DirectoryQuery {
@@ -35,7 +38,7 @@ pub async fn upload(
         && common::db_query_as!(
             Directory,
             fetch_optional,
-            "SELECT * FROM directories WHERE id = $1",
+            "SELECT * FROM directories WHERE id = $1 AND deleted_at IS NULL",
             id
         )
         .unwrap_or(None)
@@ -47,13 +50,15 @@ pub async fn upload(
         ));
     }
 
+    let policy = policy::effective(directory.directory_id).await?;
+
     let mut uploaded_files = Vec::new();
     let mut errors = Vec::new();
 
-    let dir_path = PathBuf::from(DIRECTORY);
-
-    // Ensure the directory exists
-    tokio::fs::create_dir_all(&dir_path).await?;
+    let full_dir_path = get_full_path(directory.directory_id)
+        .await
+        .iter()
+        .fold(String::new(), |acc, dir| format!("{acc}/{}", dir.dir_name));
 
     while let Some(field) = multipart.next_field().await.unwrap_or(None) {
         let id = Uuid::new_v4();
@@ -63,36 +68,40 @@ pub async fn upload(
             .map(|s| s.to_string())
             .unwrap_or(format!("file_{id}"));
 
-        let mime_type = field
-            .content_type()
-            .map(|s| s.to_string())
-            .unwrap_or("application/octet-stream".into());
+        // earlier files of the request count towards the quota as well
+        let max_size = match policy::remaining().await? {
+            Some(remaining) if remaining <= 0 => {
+                errors.push(format!("{file_name}: The storage quota is used up."));
+                continue;
+            }
+            remaining => remaining.into_iter().chain(policy.max_file_size).min(),
+        }
+        .unwrap_or(i64::MAX);
 
-        let data = match field.bytes().await {
-            Ok(d) => d,
+        // the type the client sends is not trusted
+        let (size, sha256, mime_type) = match blob::put_field(field, max_size, |head| {
+            let mime_type = policy::sniff(head, &file_name);
+            policy::check_type(&policy, &mime_type).map(|_| mime_type)
+        })
+        .await
+        {
+            Ok(written) => written,
             Err(err) => {
-                errors.push(err.to_string());
+                errors.push(format!("{file_name}: {err}"));
                 continue;
             }
         };
 
-        let file_path = dir_path.join(id.to_string());
-
-        let full_path = format!(
-            "{}/{}",
-            get_full_path(directory.directory_id)
-                .await
-                .iter()
-                .fold(String::new(), |acc, dir| format!("{acc}/{}", dir.dir_name)),
-            file_name,
-        );
+        let full_path = format!("{full_dir_path}/{file_name}");
 
         match common::db_query_as!(
             File,
             fetch_one,
             r#"
-            INSERT INTO files (id, directory_id, file_name, file_path, mime_type, uploaded_at)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            INSERT INTO files (
+                id, directory_id, file_name, file_path, mime_type, uploaded_at, size, sha256
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
             RETURNING *
             "#,
             id,
@@ -100,18 +109,16 @@ pub async fn upload(
             &file_name,
             &full_path,
             mime_type,
-            Utc::now()
+            Utc::now(),
+            size,
+            sha256,
         ) {
-            Ok(res) => uploaded_files.push(res),
-            Err(err) => {
-                errors.push(err.to_string());
-                continue;
+            Ok(res) => {
+                encoding::queue(&res).await;
+                uploaded_files.push(res);
             }
+            Err(err) => errors.push(err.to_string()),
         };
-
-        if let Err(e) = save_to_disk(file_path, &data).await {
-            errors.push(e);
-        }
     }
 
     if uploaded_files.is_empty() {
@@ -127,58 +134,181 @@ pub async fn delete_by_id(Path(id): Path<Uuid>, user: Option<Extension<User>>) -
         return Err(ApiError::unauthorized());
     };
 
-    if common::db_query!(execute, "DELETE FROM files WHERE id = $1", id)
-        .map_or(0, |r| r.rows_affected())
+    // into the trash, the content stays until it is purged from there
+    if common::db_query!(
+        execute,
+        "UPDATE files SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL",
+        id
+    )
+    .map_or(0, |r| r.rows_affected())
         == 1
     {
-        tokio::fs::remove_file([DIRECTORY, &id.to_string()].iter().collect::<PathBuf>())
-            .await
-            .map_err(Into::into)
+        Ok(())
     } else {
         Err(ApiError::bad_request())
     }
 }
 
+/// Moves and/or renames a file, the content stays where it is.
 #[tracing::instrument(skip(user))]
-pub async fn get_by_id(
+pub async fn move_by_id(
     Path(id): Path<Uuid>,
     user: Option<Extension<User>>,
-) -> ApiResult<impl IntoResponse> {
+    Query(target): Query<TargetQuery>,
+) -> ApiResult<Json<File>> {
     if !user.is_some_and(|u| u.admin) {
         return Err(ApiError::unauthorized());
     };
 
-    let file = common::db_query_as!(File, fetch_one, "SELECT * FROM files WHERE id = $1", id)
-        .map_err(|_| ApiError::not_found())?;
+    let file = common::db_query_as!(
+        File,
+        fetch_one,
+        "SELECT * FROM files WHERE id = $1 AND deleted_at IS NULL",
+        id
+    )
+    .map_err(|_| ApiError::not_found())?;
 
-    let file_path: PathBuf = [DIRECTORY, &file.id.to_string()].iter().collect();
-    tokio::fs::read(file_path)
-        .await
-        .map(|bytes| ([(header::CONTENT_TYPE, file.mime_type)], bytes))
-        .map_err(|_| ApiError::not_found())
+    let (directory_id, dir_path) = resolve_target(&target).await?;
+
+    // the target might not have let the file in as an upload
+    policy::check_file(&policy::effective(directory_id).await?, &file).map_err(|err| {
+        ApiError::Message(
+            StatusCode::BAD_REQUEST,
+            format!("{}: {err}", file.file_name),
+        )
+    })?;
+
+    let name = target.name.unwrap_or(file.file_name);
+    check_name(&name)?;
+
+    let path = format!("{dir_path}/{name}");
+
+    common::db_query_as!(
+        File,
+        fetch_one,
+        r#"
+        UPDATE files SET directory_id = $2, file_name = $3, file_path = $4
+        WHERE id = $1
+        RETURNING *
+        "#,
+        id,
+        directory_id,
+        &name,
+        &path,
+    )
+    .map(Json)
+    .map_err(|e| conflict(e, &path))
 }
 
+/// Copies a file, both point at the same blob afterwards.
 #[tracing::instrument(skip(user))]
-pub async fn traverse(
-    Path(file_path): Path<String>,
+pub async fn copy_by_id(
+    Path(id): Path<Uuid>,
     user: Option<Extension<User>>,
-) -> ApiResult<impl IntoResponse> {
-    // if it is in any folder containing .private, user must be admin
-    if file_path.contains(PRIVATE) && !user.is_some_and(|u| u.admin) {
+    Query(target): Query<TargetQuery>,
+) -> ApiResult<Json<File>> {
+    if !user.is_some_and(|u| u.admin) {
         return Err(ApiError::unauthorized());
     };
 
     let file = common::db_query_as!(
         File,
         fetch_one,
-        "SELECT * FROM files WHERE file_path = $1",
+        "SELECT * FROM files WHERE id = $1 AND deleted_at IS NULL",
+        id
+    )
+    .map_err(|_| ApiError::not_found())?;
+
+    if file.sha256.is_none() {
+        return Err(ApiError::Message(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "File is still being moved to the blob store, try again later.".into(),
+        ));
+    }
+
+    let (directory_id, dir_path) = resolve_target(&target).await?;
+
+    // the target might not have let the file in as an upload
+    policy::check_file(&policy::effective(directory_id).await?, &file).map_err(|err| {
+        ApiError::Message(
+            StatusCode::BAD_REQUEST,
+            format!("{}: {err}", file.file_name),
+        )
+    })?;
+
+    let name = target.name.unwrap_or(file.file_name);
+    check_name(&name)?;
+
+    let path = format!("{dir_path}/{name}");
+
+    common::db_query_as!(
+        File,
+        fetch_one,
+        r#"
+        INSERT INTO files (
+            id, directory_id, file_name, file_path, mime_type, uploaded_at, size, sha256,
+            visibility
+        )
+        SELECT $2, $3, $4, $5, mime_type, $6, size, sha256, visibility FROM files WHERE id = $1
+        RETURNING *
+        "#,
+        id,
+        Uuid::new_v4(),
+        directory_id,
+        &name,
+        &path,
+        Utc::now(),
+    )
+    .map(Json)
+    .map_err(|e| conflict(e, &path))
+}
+
+#[tracing::instrument(skip(user, headers))]
+pub async fn get_by_id(
+    Path(id): Path<Uuid>,
+    user: Option<Extension<User>>,
+    Query(variant): Query<VariantQuery>,
+    headers: HeaderMap,
+) -> ApiResult<impl IntoResponse> {
+    let file = common::db_query_as!(
+        File,
+        fetch_one,
+        "SELECT * FROM files WHERE id = $1 AND deleted_at IS NULL",
+        id
+    )
+    .map_err(|_| ApiError::not_found())?;
+
+    // knowing the id is enough for unlisted files
+    if !user.is_some_and(|u| u.admin) && share::visibility(&file).await? == Visibility::Private {
+        return Err(ApiError::not_found());
+    };
+
+    let file = variant::apply(file, &variant).await?;
+
+    download::serve(&file, &headers).await
+}
+
+#[tracing::instrument(skip(user, headers))]
+pub async fn traverse(
+    Path(file_path): Path<String>,
+    user: Option<Extension<User>>,
+    Query(variant): Query<VariantQuery>,
+    headers: HeaderMap,
+) -> ApiResult<impl IntoResponse> {
+    let file = common::db_query_as!(
+        File,
+        fetch_one,
+        "SELECT * FROM files WHERE file_path = $1 AND deleted_at IS NULL",
         format!("/{file_path}")
     )
     .map_err(|_| ApiError::not_found())?;
 
-    let file_path: PathBuf = [DIRECTORY, &file.id.to_string()].iter().collect();
-    tokio::fs::read(file_path)
-        .await
-        .map(|bytes| ([(header::CONTENT_TYPE, file.mime_type)], bytes))
-        .map_err(|_| ApiError::not_found())
+    // not found rather than unauthorized, paths of hidden files should not be confirmed
+    if !user.is_some_and(|u| u.admin) && share::visibility(&file).await? != Visibility::Public {
+        return Err(ApiError::not_found());
+    };
+
+    let file = variant::apply(file, &variant).await?;
+
+    download::serve(&file, &headers).await
 }
@@ -1,20 +1,47 @@
-use std::{fs::File as FsFile, io::Write, path::PathBuf};
+#![recursion_limit = "256"]
 
+#[cfg(feature = "back")]
 use axum::{
-    Extension, Router,
+    Router,
     extract::DefaultBodyLimit,
     http::StatusCode,
-    response::{Html, IntoResponse},
-    routing::{get, post},
+    routing::{delete, get, post},
 };
+#[cfg(feature = "back")]
+use serde::Deserialize;
+#[cfg(feature = "back")]
 use tower_http::cors::{Any, CorsLayer};
 
+pub mod app;
+#[cfg(feature = "back")]
+pub mod archive;
+#[cfg(feature = "back")]
+pub mod blob;
+mod browser;
+#[cfg(feature = "back")]
 pub mod directory;
+#[cfg(feature = "back")]
+pub mod download;
+#[cfg(feature = "back")]
+pub mod encoding;
+#[cfg(feature = "back")]
 pub mod file;
+#[cfg(feature = "back")]
+pub mod policy;
+#[cfg(feature = "back")]
+pub mod share;
+#[cfg(feature = "back")]
+pub mod storage;
+#[cfg(feature = "back")]
+pub mod trash;
+#[cfg(feature = "back")]
+pub mod variant;
 
-use common::models::{Directory, DirectoryContents, File, User};
+#[cfg(feature = "back")]
 use common::{
     api::{ApiError, ApiResult},
+    db::sqlx,
+    models::{Directory, DirectoryContents, File},
     trace::TraceExt,
 };
 
@@ -22,9 +49,10 @@ pub static DIRECTORY: &str = "files";
 pub static ROOT: &str = "~";
 pub static PRIVATE: &str = ".private";
 
+/// The json api, the file browser in [`app`] is served next to it.
+#[cfg(feature = "back")]
 pub fn router() -> Router {
     Router::new()
-        .route("/browse", get(file_browser_html))
         .route(
             "/upload/f",
             // 1 Gigabyte file size limit
@@ -39,6 +67,32 @@ pub fn router() -> Router {
             "/d_id/{id}",
             get(directory::get_by_id).delete(directory::delete_by_id),
         )
+        .route("/f_id/{id}/move", post(file::move_by_id))
+        .route("/f_id/{id}/copy", post(file::copy_by_id))
+        .route("/d_id/{id}/move", post(directory::move_by_id))
+        .route("/d_id/{id}/copy", post(directory::copy_by_id))
+        .route("/d_id/{id}/zip", get(archive::zip_by_id))
+        .route("/f_id/{id}/restore", post(trash::restore_file))
+        .route("/d_id/{id}/restore", post(trash::restore_directory))
+        .route("/trash", get(trash::list).delete(trash::empty))
+        .route(
+            "/d_id/{id}/policy",
+            get(policy::get_by_id).post(policy::set_by_id),
+        )
+        .route("/usage", get(policy::get_usage))
+        .route("/f_id/{id}/visibility", post(share::set_file_visibility))
+        .route(
+            "/d_id/{id}/visibility",
+            post(share::set_directory_visibility),
+        )
+        .route("/f_id/{id}/share", post(share::share_file))
+        .route("/d_id/{id}/share", post(share::share_directory))
+        .route("/share/{id}", delete(share::delete_by_id))
+        .route("/s/{token}", get(share::open).post(share::unlock))
+        .route(
+            "/s/{token}/{*path}",
+            get(share::open_path).post(share::unlock),
+        )
         .route("/d/{*dir_path}", get(directory::traverse))
         .route("/f/{*file_path}", get(file::traverse))
         .with_tracing()
@@ -46,63 +100,131 @@ pub fn router() -> Router {
         .layer(CorsLayer::new().allow_origin(Any))
 }
 
-#[tracing::instrument(skip(user))]
-async fn file_browser_html(user: Option<Extension<User>>) -> impl IntoResponse {
-    if !user.is_some_and(|u| u.admin) {
-        return StatusCode::UNAUTHORIZED.into_response();
+/// The directories from the root down to and including `directory_id`.
+#[cfg(feature = "back")]
+pub async fn get_full_path(directory_id: Option<i32>) -> Vec<Directory> {
+    let mut path = match directory_id {
+        Some(id) => common::db_query_as!(
+            Directory,
+            fetch_all,
+            r#"
+            WITH RECURSIVE ancestors AS (
+                SELECT *, 0 AS depth FROM directories WHERE id = $1
+                UNION ALL
+                SELECT d.*, a.depth + 1 FROM directories d
+                JOIN ancestors a ON d.id = a.parent_id
+            )
+            SELECT id, parent_id, dir_name, dir_path, visibility, deleted_at FROM ancestors
+            ORDER BY depth DESC
+            "#,
+            id
+        )
+        .unwrap_or_default(),
+        None => Vec::new(),
     };
 
-    Html(include_str!("file_browser.html")).into_response()
+    path.insert(
+        0,
+        Directory {
+            dir_name: ROOT.to_string(),
+            dir_path: ROOT.to_string(),
+            ..Directory::root()
+        },
+    );
+
+    path
 }
 
-pub async fn save_to_disk(file_path: PathBuf, data: &[u8]) -> Result<(), String> {
-    match FsFile::create(&file_path) {
-        Ok(mut file) => {
-            if let Err(e) = file.write_all(data) {
-                Err(format!("Write error: {e}"))
-            } else {
-                Ok(())
-            }
-        }
-        Err(err) => Err(format!("Create error: {err}")),
-    }
+/// Ids of a directory and all directories below it, parents before their children.
+#[cfg(feature = "back")]
+pub async fn get_subtree<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+    id: i32,
+) -> Result<Vec<i32>, sqlx::Error> {
+    sqlx::query_scalar::<_, i32>(
+        r#"
+        WITH RECURSIVE subtree AS (
+            SELECT id, 0 AS depth FROM directories WHERE id = $1
+            UNION ALL
+            SELECT d.id, s.depth + 1 FROM directories d
+            JOIN subtree s ON d.parent_id = s.id
+        )
+        SELECT id FROM subtree ORDER BY depth
+        "#,
+    )
+    .bind(id)
+    .fetch_all(executor)
+    .await
 }
 
-pub async fn get_full_path(mut directory_id: Option<i32>) -> Vec<Directory> {
-    let mut path = Vec::new();
+/// Where a file or directory should be moved or copied to.
+#[cfg(feature = "back")]
+#[derive(Deserialize, Debug)]
+pub struct TargetQuery {
+    /// the root if missing
+    directory_id: Option<i32>,
+    /// keeps the current name if missing
+    name: Option<String>,
+}
 
-    while let Some(id) = directory_id {
-        if let Some(dir) = common::db_query_as!(
-            Directory,
-            fetch_optional,
-            "SELECT * FROM directories WHERE id = $1",
+/// Checks the target directory exists and returns its id and path.
+#[cfg(feature = "back")]
+async fn resolve_target(target: &TargetQuery) -> ApiResult<(Option<i32>, String)> {
+    // the root is handed out with id 0
+    let directory_id = target.directory_id.filter(|id| *id != 0);
+
+    if let Some(id) = directory_id
+        && common::db_query_scalar!(
+            i64,
+            fetch_one,
+            "SELECT COUNT(*) FROM directories WHERE id = $1 AND deleted_at IS NULL",
             id
-        )
-        .unwrap_or(None)
-        {
-            directory_id = dir.parent_id;
-            path.push(dir);
-        } else {
-            break;
-        }
+        )? == 0
+    {
+        return Err(ApiError::Message(
+            StatusCode::BAD_REQUEST,
+            "Target directory does not exist.".to_string(),
+        ));
     }
 
-    path.push(Directory {
-        id: 0,
-        parent_id: None,
-        dir_name: ROOT.to_string(),
-        dir_path: ROOT.to_string(),
-    });
+    let path = get_full_path(directory_id)
+        .await
+        .iter()
+        .fold(String::new(), |acc, dir| format!("{acc}/{}", dir.dir_name));
 
-    path.reverse();
-    path
+    Ok((directory_id, path))
+}
+
+/// Names end up in paths, so they can not be empty or contain a slash.
+#[cfg(feature = "back")]
+fn check_name(name: &str) -> ApiResult<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        return Err(ApiError::Message(
+            StatusCode::BAD_REQUEST,
+            format!("Invalid name: {name}"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Paths are unique, taking one that is already used is a conflict instead of a bad request.
+#[cfg(feature = "back")]
+pub fn conflict(err: sqlx::Error, path: &str) -> ApiError {
+    match err.as_database_error() {
+        Some(e) if e.is_unique_violation() => {
+            ApiError::Message(StatusCode::CONFLICT, format!("{path} already exists."))
+        }
+        _ => err.into(),
+    }
 }
 
+#[cfg(feature = "back")]
 pub async fn get_directory_contents(id: Option<i32>) -> ApiResult<DirectoryContents> {
     let files = common::db_query_as!(
         File,
         fetch_all,
-        "SELECT * FROM files WHERE directory_id IS NOT DISTINCT FROM $1",
+        "SELECT * FROM files WHERE directory_id IS NOT DISTINCT FROM $1 AND deleted_at IS NULL",
         id
     )
     .map_err(|err| ApiError::Message(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
@@ -110,7 +232,7 @@ pub async fn get_directory_contents(id: Option<i32>) -> ApiResult<DirectoryConte
     let directories = common::db_query_as!(
         Directory,
         fetch_all,
-        "SELECT * FROM directories WHERE parent_id IS NOT DISTINCT FROM $1",
+        "SELECT * FROM directories WHERE parent_id IS NOT DISTINCT FROM $1 AND deleted_at IS NULL",
         id
     )
     .map_err(|err| ApiError::Message(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
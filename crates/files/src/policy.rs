@@ -0,0 +1,299 @@
+use axum::{Extension, Json, extract::Path, http::StatusCode};
+
+use common::{
+    api::{ApiError, ApiResult},
+    db::sqlx,
+    models::{DirectoryPolicy, File, PolicyResponse, StorageUsage, User},
+};
+
+/// Used when no directory sets a limit, the same as the limit of an upload request.
+const DEFAULT_MAX_FILE_SIZE: i64 = 1_000_000_000;
+
+/// Largest file anywhere without a policy saying otherwise.
+pub fn max_file_size() -> i64 {
+    std::env::var("UPLOAD_MAX_FILE_SIZE")
+        .ok()
+        .and_then(|size| size.parse().ok())
+        .unwrap_or(DEFAULT_MAX_FILE_SIZE)
+}
+
+/// Total bytes the storage may hold, unlimited if not set.
+pub fn quota() -> Option<i64> {
+    std::env::var("STORAGE_QUOTA_BYTES")
+        .ok()
+        .and_then(|quota| quota.parse().ok())
+}
+
+/// The rules for uploads into a directory, every field from the nearest directory setting it.
+pub async fn effective(directory_id: Option<i32>) -> ApiResult<DirectoryPolicy> {
+    Ok(effective_in(common::db::db(), directory_id).await?)
+}
+
+/// [`effective`] as seen from inside a transaction.
+pub async fn effective_in<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+    directory_id: Option<i32>,
+) -> Result<DirectoryPolicy, sqlx::Error> {
+    let policy = match directory_id {
+        Some(id) => {
+            sqlx::query_as::<_, DirectoryPolicy>(
+                r#"
+            WITH RECURSIVE ancestors AS (
+                SELECT id, parent_id, 0 AS depth FROM directories WHERE id = $1
+                UNION ALL
+                SELECT d.id, d.parent_id, a.depth + 1 FROM directories d
+                JOIN ancestors a ON d.id = a.parent_id
+            ), policies AS (
+                SELECT p.*, a.depth FROM ancestors a
+                JOIN directory_policies p ON p.directory_id = a.id
+            )
+            SELECT
+                (SELECT allowed_mime_types FROM policies
+                    WHERE allowed_mime_types IS NOT NULL ORDER BY depth LIMIT 1)
+                    AS allowed_mime_types,
+                (SELECT denied_mime_types FROM policies
+                    WHERE denied_mime_types IS NOT NULL ORDER BY depth LIMIT 1)
+                    AS denied_mime_types,
+                (SELECT max_file_size FROM policies
+                    WHERE max_file_size IS NOT NULL ORDER BY depth LIMIT 1)
+                    AS max_file_size
+            "#,
+            )
+            .bind(id)
+            .fetch_one(executor)
+            .await?
+        }
+        None => DirectoryPolicy::default(),
+    };
+
+    Ok(DirectoryPolicy {
+        max_file_size: policy.max_file_size.or(Some(max_file_size())),
+        ..policy
+    })
+}
+
+/// Refuses types the policy denies or does not allow.
+pub fn check_type(policy: &DirectoryPolicy, mime_type: &str) -> Result<(), String> {
+    let matches = |pattern: &String| {
+        pattern == "*"
+            || pattern == mime_type
+            || pattern
+                .strip_suffix("/*")
+                .is_some_and(|group| mime_type.split('/').next() == Some(group))
+    };
+
+    if policy.denied_mime_types.iter().flatten().any(matches)
+        || policy
+            .allowed_mime_types
+            .as_ref()
+            .is_some_and(|allowed| !allowed.iter().any(matches))
+    {
+        return Err(format!("{mime_type} is not allowed here."));
+    }
+
+    Ok(())
+}
+
+/// Refuses a file that could not have been uploaded into a directory with the policy.
+pub fn check_file(policy: &DirectoryPolicy, file: &File) -> Result<(), String> {
+    check_type(policy, &file.mime_type)?;
+
+    match (file.size, policy.max_file_size) {
+        (Some(size), Some(max_size)) if size > max_size => {
+            Err(format!("Larger than the {max_size} bytes allowed."))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Refuses the files in the directories if any of them breaks the policy where it is,
+/// meant to run in the transaction that put them there before it is committed.
+pub async fn check_directories(tx: &mut sqlx::PgConnection, ids: &[i32]) -> ApiResult<()> {
+    let mut errors = Vec::new();
+
+    for id in ids {
+        let policy = effective_in(&mut *tx, Some(*id)).await?;
+
+        let files = sqlx::query_as::<_, File>(
+            "SELECT * FROM files WHERE directory_id = $1 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        errors.extend(files.iter().filter_map(|file| {
+            check_file(&policy, file)
+                .err()
+                .map(|err| format!("{}: {err}", file.file_path))
+        }));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::MultipleMessages(StatusCode::BAD_REQUEST, errors))
+    }
+}
+
+/// The type of a file going by its content rather than what the client claims.
+///
+/// Text has no magic numbers, so for it the name decides between the kinds of text.
+pub fn sniff(head: &[u8], file_name: &str) -> String {
+    if let Some(kind) = infer::get(head) {
+        return kind.mime_type().to_string();
+    }
+
+    if !looks_like_text(head) {
+        return "application/octet-stream".into();
+    }
+
+    mime_guess::from_path(file_name)
+        .iter()
+        .find(|mime| {
+            mime.type_() == mime_guess::mime::TEXT
+                || mime.suffix().is_some()
+                || matches!(mime.subtype().as_str(), "json" | "javascript" | "xml")
+        })
+        .map_or("text/plain".into(), |mime| mime.to_string())
+}
+
+/// No null bytes and valid UTF-8, apart from a character cut off at the end.
+fn looks_like_text(head: &[u8]) -> bool {
+    !head.contains(&0)
+        && std::str::from_utf8(head).map_or_else(|e| e.error_len().is_none(), |_| true)
+}
+
+/// What is left of the [`quota`], if there is one.
+pub async fn remaining() -> ApiResult<Option<i64>> {
+    match quota() {
+        Some(quota) => Ok(Some(quota - usage().await?.used)),
+        None => Ok(None),
+    }
+}
+
+/// Refuses adding `size` more bytes once the [`quota`] would be exceeded.
+pub async fn check_quota(size: i64) -> ApiResult<()> {
+    if remaining().await?.is_some_and(|remaining| size > remaining) {
+        return Err(ApiError::Message(
+            StatusCode::INSUFFICIENT_STORAGE,
+            "The storage quota is used up.".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+pub async fn usage() -> ApiResult<StorageUsage> {
+    // files not yet moved to the blob store are counted by themselves
+    let (used, trash, files) = common::db_query_as!(
+        (i64, i64, i64),
+        fetch_one,
+        r#"
+        SELECT
+            (SELECT COALESCE(SUM(size), 0) FROM blobs)::BIGINT
+                + (SELECT COALESCE(SUM(size), 0) FROM files WHERE sha256 IS NULL)::BIGINT,
+            (SELECT COALESCE(SUM(b.size), 0) FROM blobs b
+                WHERE EXISTS (
+                    SELECT 1 FROM files f WHERE f.sha256 = b.sha256 AND f.deleted_at IS NOT NULL
+                )
+                AND NOT EXISTS (
+                    SELECT 1 FROM files f WHERE f.sha256 = b.sha256 AND f.deleted_at IS NULL
+                ))::BIGINT,
+            (SELECT COUNT(*) FROM files WHERE deleted_at IS NULL)
+        "#
+    )?;
+
+    Ok(StorageUsage {
+        used,
+        quota: quota(),
+        trash,
+        files,
+    })
+}
+
+#[tracing::instrument(skip(user))]
+pub async fn get_by_id(
+    Path(id): Path<i32>,
+    user: Option<Extension<User>>,
+) -> ApiResult<Json<PolicyResponse>> {
+    if !user.is_some_and(|u| u.admin) {
+        return Err(ApiError::unauthorized());
+    };
+
+    // the root is handed out with id 0 and has no policy of its own
+    let directory_id = Some(id).filter(|id| *id != 0);
+
+    let own = match directory_id {
+        Some(id) => common::db_query_as!(
+            DirectoryPolicy,
+            fetch_optional,
+            "SELECT * FROM directory_policies WHERE directory_id = $1",
+            id
+        )?
+        .unwrap_or_default(),
+        None => DirectoryPolicy::default(),
+    };
+
+    Ok(Json(PolicyResponse {
+        own,
+        effective: effective(directory_id).await?,
+    }))
+}
+
+#[tracing::instrument(skip(user))]
+pub async fn set_by_id(
+    Path(id): Path<i32>,
+    user: Option<Extension<User>>,
+    Json(policy): Json<DirectoryPolicy>,
+) -> ApiResult<Json<DirectoryPolicy>> {
+    if !user.is_some_and(|u| u.admin) {
+        return Err(ApiError::unauthorized());
+    };
+
+    if policy.max_file_size.is_some_and(|size| size < 1) {
+        return Err(ApiError::Message(
+            StatusCode::BAD_REQUEST,
+            "The maximum file size has to be positive.".into(),
+        ));
+    }
+
+    let normalize = |types: Option<Vec<String>>| {
+        types.map(|types| {
+            types
+                .into_iter()
+                .map(|t| t.trim().to_lowercase())
+                .filter(|t| !t.is_empty())
+                .collect::<Vec<_>>()
+        })
+    };
+
+    common::db_query_as!(
+        DirectoryPolicy,
+        fetch_one,
+        r#"
+        INSERT INTO directory_policies
+            (directory_id, allowed_mime_types, denied_mime_types, max_file_size)
+        SELECT id, $2, $3, $4 FROM directories WHERE id = $1 AND deleted_at IS NULL
+        ON CONFLICT (directory_id) DO UPDATE SET
+            allowed_mime_types = EXCLUDED.allowed_mime_types,
+            denied_mime_types = EXCLUDED.denied_mime_types,
+            max_file_size = EXCLUDED.max_file_size
+        RETURNING *
+        "#,
+        id,
+        normalize(policy.allowed_mime_types),
+        normalize(policy.denied_mime_types),
+        policy.max_file_size,
+    )
+    .map(Json)
+    .map_err(|_| ApiError::not_found())
+}
+
+#[tracing::instrument(skip(user))]
+pub async fn get_usage(user: Option<Extension<User>>) -> ApiResult<Json<StorageUsage>> {
+    if !user.is_some_and(|u| u.admin) {
+        return Err(ApiError::unauthorized());
+    };
+
+    usage().await.map(Json)
+}
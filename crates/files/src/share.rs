@@ -0,0 +1,470 @@
+use std::collections::HashMap;
+
+use axum::{
+    Extension, Form, Json,
+    extract::{Path, Query},
+    http::{HeaderMap, HeaderValue, StatusCode, Uri, header},
+    response::{Html, IntoResponse, Redirect, Response},
+};
+use axum_extra::headers::{Cookie, HeaderMapExt};
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use common::{
+    api::{ApiError, ApiResult},
+    auth::{
+        bcrypt::{DEFAULT_COST, hash},
+        verify_password,
+    },
+    models::{Directory, File, Share, ShareLink, ShareRequest, User, Visibility},
+};
+
+use super::{PRIVATE, download, get_directory_contents};
+
+/// Bytes of the signature kept in a share token.
+const SIGNATURE_LEN: usize = 16;
+/// How long the password of a share is remembered after it was entered.
+const UNLOCK_MINUTES: i64 = 60;
+/// Holds `{expiry}.{signature}` for the share it is scoped to by its path.
+const UNLOCK_COOKIE: &str = "share_unlocked";
+
+/// Visibility of a file, the nearest setting up the tree wins.
+pub async fn visibility(file: &File) -> ApiResult<Visibility> {
+    match file.visibility {
+        Some(visibility) => Ok(visibility),
+        None => directory_visibility(file.directory_id).await,
+    }
+}
+
+/// Visibility of a directory, [`PRIVATE`] directories without a setting of their own
+/// are private and the root is public.
+pub async fn directory_visibility(id: Option<i32>) -> ApiResult<Visibility> {
+    let Some(id) = id else {
+        return Ok(Visibility::Public);
+    };
+
+    Ok(common::db_query_scalar!(
+        Visibility,
+        fetch_optional,
+        r#"
+        WITH RECURSIVE ancestors AS (
+            SELECT parent_id, dir_name, visibility, 0 AS depth FROM directories WHERE id = $1
+            UNION ALL
+            SELECT d.parent_id, d.dir_name, d.visibility, a.depth + 1 FROM directories d
+            JOIN ancestors a ON d.id = a.parent_id
+        )
+        SELECT COALESCE(
+            visibility,
+            CASE WHEN dir_name = $2 THEN 'private'::visibility END
+        )
+        FROM ancestors
+        WHERE visibility IS NOT NULL OR dir_name = $2
+        ORDER BY depth
+        LIMIT 1
+        "#,
+        id,
+        PRIVATE,
+    )?
+    .unwrap_or_default())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct VisibilityQuery {
+    /// inherits from the parent if missing
+    visibility: Option<Visibility>,
+}
+
+#[tracing::instrument(skip(user))]
+pub async fn set_file_visibility(
+    Path(id): Path<Uuid>,
+    user: Option<Extension<User>>,
+    Query(q): Query<VisibilityQuery>,
+) -> ApiResult<Json<File>> {
+    if !user.is_some_and(|u| u.admin) {
+        return Err(ApiError::unauthorized());
+    };
+
+    common::db_query_as!(
+        File,
+        fetch_one,
+        "UPDATE files SET visibility = $2 WHERE id = $1 AND deleted_at IS NULL RETURNING *",
+        id,
+        q.visibility,
+    )
+    .map(Json)
+    .map_err(|_| ApiError::not_found())
+}
+
+#[tracing::instrument(skip(user))]
+pub async fn set_directory_visibility(
+    Path(id): Path<i32>,
+    user: Option<Extension<User>>,
+    Query(q): Query<VisibilityQuery>,
+) -> ApiResult<Json<Directory>> {
+    if !user.is_some_and(|u| u.admin) {
+        return Err(ApiError::unauthorized());
+    };
+
+    common::db_query_as!(
+        Directory,
+        fetch_one,
+        "UPDATE directories SET visibility = $2 WHERE id = $1 AND deleted_at IS NULL RETURNING *",
+        id,
+        q.visibility,
+    )
+    .map(Json)
+    .map_err(|_| ApiError::not_found())
+}
+
+#[tracing::instrument(skip(user, request))]
+pub async fn share_file(
+    Path(id): Path<Uuid>,
+    user: Option<Extension<User>>,
+    Json(request): Json<ShareRequest>,
+) -> ApiResult<Json<ShareLink>> {
+    if !user.is_some_and(|u| u.admin) {
+        return Err(ApiError::unauthorized());
+    };
+
+    create(Some(id), None, request).await
+}
+
+#[tracing::instrument(skip(user, request))]
+pub async fn share_directory(
+    Path(id): Path<i32>,
+    user: Option<Extension<User>>,
+    Json(request): Json<ShareRequest>,
+) -> ApiResult<Json<ShareLink>> {
+    if !user.is_some_and(|u| u.admin) {
+        return Err(ApiError::unauthorized());
+    };
+
+    create(None, Some(id), request).await
+}
+
+async fn create(
+    file_id: Option<Uuid>,
+    directory_id: Option<i32>,
+    request: ShareRequest,
+) -> ApiResult<Json<ShareLink>> {
+    if request.max_downloads.is_some_and(|max| max < 1)
+        || request.expires_in_hours.is_some_and(|hours| hours < 1)
+    {
+        return Err(ApiError::Message(
+            StatusCode::BAD_REQUEST,
+            "Expiry and download limit have to be positive.".into(),
+        ));
+    }
+
+    let expires_at = request
+        .expires_in_hours
+        .and_then(Duration::try_hours)
+        .and_then(|expires_in| Utc::now().naive_utc().checked_add_signed(expires_in));
+
+    let password_hash = match request.password.filter(|p| !p.is_empty()) {
+        Some(password) => Some(hash(password, DEFAULT_COST).map_err(ApiError::any)?),
+        None => None,
+    };
+
+    let share = common::db_query_as!(
+        Share,
+        fetch_one,
+        r#"
+        INSERT INTO shares (file_id, directory_id, expires_at, max_downloads, password_hash)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING *
+        "#,
+        file_id,
+        directory_id,
+        expires_at,
+        request.max_downloads,
+        password_hash,
+    )
+    .map_err(|_| ApiError::not_found())?;
+
+    Ok(Json(ShareLink {
+        url: format!("/s/{}", token(share.id)),
+        share,
+    }))
+}
+
+#[tracing::instrument(skip(user))]
+pub async fn delete_by_id(Path(id): Path<Uuid>, user: Option<Extension<User>>) -> ApiResult<()> {
+    if !user.is_some_and(|u| u.admin) {
+        return Err(ApiError::unauthorized());
+    };
+
+    if common::db_query!(execute, "DELETE FROM shares WHERE id = $1", id)
+        .map_or(0, |r| r.rows_affected())
+        == 1
+    {
+        Ok(())
+    } else {
+        Err(ApiError::not_found())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PasswordForm {
+    password: String,
+}
+
+/// Checks the password of a share and remembers it in a cookie for [`UNLOCK_MINUTES`],
+/// so the password is never part of a url and does not have to be sent again.
+#[tracing::instrument(skip(form))]
+pub async fn unlock(
+    Path(params): Path<HashMap<String, String>>,
+    uri: Uri,
+    Form(form): Form<PasswordForm>,
+) -> ApiResult<Response> {
+    let token = params.get("token").ok_or(ApiError::not_found())?;
+    let share = find(token).await?;
+
+    if let Some(password_hash) = &share.password_hash
+        && !verify_password(&form.password, password_hash)
+    {
+        return Ok(Redirect::to(&format!("{}?wrong", uri.path())).into_response());
+    }
+
+    let expires = Utc::now().timestamp() + UNLOCK_MINUTES * 60;
+    let cookie = format!(
+        "{UNLOCK_COOKIE}={expires}.{}; Path=/s/{token}; Max-Age={}; SameSite=Lax; HttpOnly{}",
+        hex::encode(unlock_mac(share.id, expires).finalize().into_bytes()),
+        UNLOCK_MINUTES * 60,
+        if cfg!(debug_assertions) {
+            ""
+        } else {
+            "; Secure"
+        },
+    );
+
+    let mut response = Redirect::to(uri.path()).into_response();
+    response.headers_mut().append(
+        header::SET_COOKIE,
+        HeaderValue::from_str(&cookie).map_err(ApiError::any)?,
+    );
+
+    Ok(response)
+}
+
+/// The shared file, or the contents of the shared directory.
+#[tracing::instrument(skip(headers))]
+pub async fn open(Path(token): Path<String>, headers: HeaderMap) -> ApiResult<Response> {
+    let share = match load(&token, &headers).await? {
+        Ok(share) => share,
+        Err(form) => return Ok(form),
+    };
+
+    match (share.file_id, share.directory_id) {
+        (Some(file_id), _) => {
+            let file = common::db_query_as!(
+                File,
+                fetch_one,
+                "SELECT * FROM files WHERE id = $1 AND deleted_at IS NULL",
+                file_id
+            )
+            .map_err(|_| ApiError::not_found())?;
+
+            serve_counted(&share, &file, &headers).await
+        }
+        (None, Some(directory_id)) => get_directory_contents(Some(directory_id))
+            .await
+            .map(|contents| Json(contents).into_response()),
+        (None, None) => Err(ApiError::not_found()),
+    }
+}
+
+/// A file or directory somewhere below a shared directory.
+#[tracing::instrument(skip(headers))]
+pub async fn open_path(
+    Path((token, path)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> ApiResult<Response> {
+    let share = match load(&token, &headers).await? {
+        Ok(share) => share,
+        Err(form) => return Ok(form),
+    };
+
+    let shared = common::db_query_as!(
+        Directory,
+        fetch_one,
+        "SELECT * FROM directories WHERE id = $1 AND deleted_at IS NULL",
+        share.directory_id
+    )
+    .map_err(|_| ApiError::not_found())?;
+
+    // paths are built from their ancestors, so a prefix match means it is inside
+    let full_path = format!("{}/{}", shared.dir_path, path.trim_end_matches('/'));
+
+    if let Some(file) = common::db_query_as!(
+        File,
+        fetch_optional,
+        "SELECT * FROM files WHERE file_path = $1 AND deleted_at IS NULL",
+        &full_path
+    )? {
+        return serve_counted(&share, &file, &headers).await;
+    }
+
+    let dir = common::db_query_as!(
+        Directory,
+        fetch_one,
+        "SELECT * FROM directories WHERE dir_path = $1 AND deleted_at IS NULL",
+        &full_path
+    )
+    .map_err(|_| ApiError::not_found())?;
+
+    get_directory_contents(Some(dir.id))
+        .await
+        .map(|contents| Json(contents).into_response())
+}
+
+/// Checks the token and the state of the share, asking for the password if there is one
+/// and it was not entered recently.
+async fn load(token: &str, headers: &HeaderMap) -> ApiResult<Result<Share, Response>> {
+    let share = find(token).await?;
+
+    if share.password_hash.is_some() && !unlocked(share.id, headers) {
+        return Ok(Err((
+            StatusCode::UNAUTHORIZED,
+            Html(include_str!("share_password.html")),
+        )
+            .into_response()));
+    }
+
+    Ok(Ok(share))
+}
+
+/// The share behind a token, as long as it can still be used.
+async fn find(token: &str) -> ApiResult<Share> {
+    let id = verify(token).ok_or(ApiError::not_found())?;
+
+    let share = common::db_query_as!(Share, fetch_one, "SELECT * FROM shares WHERE id = $1", id)
+        .map_err(|_| ApiError::not_found())?;
+
+    if share
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= Utc::now().naive_utc())
+    {
+        return Err(ApiError::Message(
+            StatusCode::GONE,
+            "This link has expired.".into(),
+        ));
+    }
+
+    if used_up(&share) {
+        return Err(ApiError::Message(
+            StatusCode::GONE,
+            "This link has been used up.".into(),
+        ));
+    }
+
+    Ok(share)
+}
+
+/// Whether the request carries an unexpired [`UNLOCK_COOKIE`] for the share.
+fn unlocked(id: Uuid, headers: &HeaderMap) -> bool {
+    let Some(cookie) = headers.typed_get::<Cookie>() else {
+        return false;
+    };
+
+    let Some((expires, signature)) = cookie
+        .get(UNLOCK_COOKIE)
+        .and_then(|value| value.split_once('.'))
+    else {
+        return false;
+    };
+
+    let (Ok(expires), Ok(signature)) = (expires.parse::<i64>(), hex::decode(signature)) else {
+        return false;
+    };
+
+    expires > Utc::now().timestamp() && unlock_mac(id, expires).verify_slice(&signature).is_ok()
+}
+
+fn used_up(share: &Share) -> bool {
+    share
+        .max_downloads
+        .is_some_and(|max| share.downloads >= max)
+}
+
+/// Serves the file and counts it against the download limit.
+///
+/// Only responses starting at the first byte count, so resuming or seeking
+/// in a download does not use up the link.
+async fn serve_counted(share: &Share, file: &File, headers: &HeaderMap) -> ApiResult<Response> {
+    let response = download::serve(file, headers).await?;
+
+    let from_start = match response.status() {
+        StatusCode::OK => true,
+        StatusCode::PARTIAL_CONTENT => response
+            .headers()
+            .get(header::CONTENT_RANGE)
+            .is_none_or(|range| range.as_bytes().starts_with(b"bytes 0-")),
+        _ => false,
+    };
+
+    if from_start && share.max_downloads.is_some() {
+        let counted = common::db_query!(
+            execute,
+            r#"
+            UPDATE shares SET downloads = downloads + 1
+            WHERE id = $1 AND downloads < max_downloads
+            "#,
+            share.id
+        )?
+        .rows_affected();
+
+        // someone else got the last download in the meantime
+        if counted == 0 {
+            return Err(ApiError::Message(
+                StatusCode::GONE,
+                "This link has been used up.".into(),
+            ));
+        }
+    }
+
+    Ok(response)
+}
+
+/// `{id}.{signature}`, so made up ids are turned away without asking the database.
+fn token(id: Uuid) -> String {
+    format!(
+        "{}.{}",
+        id.simple(),
+        hex::encode(&mac(id).finalize().into_bytes()[..SIGNATURE_LEN])
+    )
+}
+
+fn verify(token: &str) -> Option<Uuid> {
+    let (id, signature) = token.split_once('.')?;
+    let id = Uuid::parse_str(id).ok()?;
+    let signature = hex::decode(signature).ok()?;
+
+    if signature.len() != SIGNATURE_LEN {
+        return None;
+    }
+
+    mac(id).verify_truncated_left(&signature).ok().map(|_| id)
+}
+
+fn mac(id: Uuid) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(
+        std::env::var("SECRET_KEY")
+            .expect("no secret key specified")
+            .as_bytes(),
+    )
+    .expect("hmac accepts keys of any size");
+    mac.update(b"share.");
+    mac.update(id.as_bytes());
+    mac
+}
+
+fn unlock_mac(id: Uuid, expires: i64) -> Hmac<Sha256> {
+    let mut mac = mac(id);
+    mac.update(b".unlocked.");
+    mac.update(&expires.to_be_bytes());
+    mac
+}
@@ -0,0 +1,381 @@
+use std::{
+    future::Future,
+    io::{self, SeekFrom},
+    ops::Range,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::OnceLock,
+};
+
+use axum::body::Bytes;
+use futures::{StreamExt, TryStreamExt, stream::BoxStream};
+use object_store::{
+    GetOptions, GetRange, ObjectStore, WriteMultipart,
+    aws::{AmazonS3, AmazonS3Builder},
+    path::Path as ObjectPath,
+};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+use super::DIRECTORY;
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+pub type ByteStream = BoxStream<'static, io::Result<Bytes>>;
+
+static STORAGE: OnceLock<Box<dyn StorageBackend>> = OnceLock::new();
+
+/// Where file contents are kept, addressed by keys like `blobs/ab/abcd...`.
+///
+/// Missing keys are reported as [`io::ErrorKind::NotFound`].
+pub trait StorageBackend: Send + Sync {
+    fn put<'a>(&'a self, key: &'a str, data: ByteStream) -> BoxFuture<'a, io::Result<()>>;
+
+    /// Stores a local file, which is gone afterwards.
+    fn put_file<'a>(&'a self, key: &'a str, path: &'a Path) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let file = tokio::fs::File::open(path).await?;
+            self.put(key, ReaderStream::new(file).boxed()).await?;
+            tokio::fs::remove_file(path).await
+        })
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<ByteStream>>;
+
+    /// Like [`StorageBackend::get`] but only the bytes in `range`.
+    fn range<'a>(
+        &'a self,
+        key: &'a str,
+        range: Range<u64>,
+    ) -> BoxFuture<'a, io::Result<ByteStream>>;
+
+    fn size<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<u64>>;
+
+    /// Deleting what is not there is not an error.
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<()>>;
+
+    /// All keys starting with `prefix`.
+    fn list<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, io::Result<Vec<String>>>;
+}
+
+/// Creates the backend called `name` from the environment.
+///
+/// - `local`: files below `STORAGE_PATH`, defaults to [`DIRECTORY`]
+/// - `s3`: any S3 compatible store, configured with `S3_ENDPOINT`, `S3_BUCKET`,
+///   `S3_REGION`, `S3_ACCESS_KEY_ID` and `S3_SECRET_ACCESS_KEY`
+pub fn from_config(name: &str) -> io::Result<Box<dyn StorageBackend>> {
+    match name {
+        "local" => Ok(Box::new(LocalStorage::new(
+            std::env::var("STORAGE_PATH").unwrap_or(DIRECTORY.to_string()),
+        ))),
+        "s3" => S3Storage::from_env().map(|s| Box::new(s) as Box<dyn StorageBackend>),
+        name => Err(io::Error::other(format!("unknown storage backend: {name}"))),
+    }
+}
+
+/// Sets up the backend named in `STORAGE_BACKEND`, the local disk if there is none.
+pub fn init_storage() -> io::Result<()> {
+    let backend = from_config(&std::env::var("STORAGE_BACKEND").unwrap_or("local".into()))?;
+
+    STORAGE
+        .set(backend)
+        .map_err(|_| io::Error::other("storage already initialized"))
+}
+
+pub fn storage<'a>() -> &'a dyn StorageBackend {
+    STORAGE.get().expect("storage uninitialized").as_ref()
+}
+
+/// Reads a whole object into memory, only meant for small files.
+pub async fn read(key: &str) -> io::Result<Vec<u8>> {
+    storage()
+        .get(key)
+        .await?
+        .try_fold(Vec::new(), |mut acc, chunk| async move {
+            acc.extend_from_slice(&chunk);
+            Ok(acc)
+        })
+        .await
+}
+
+/// Copies every blob missing in `to` over from `from`, returns how many were copied.
+pub async fn migrate(from: &dyn StorageBackend, to: &dyn StorageBackend) -> io::Result<usize> {
+    let mut copied = 0;
+
+    for key in from.list("blobs/").await? {
+        match to.size(&key).await {
+            Ok(_) => continue,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+
+        to.put(&key, from.get(&key).await?).await?;
+        copied += 1;
+
+        tracing::info!("Copied {key}");
+    }
+
+    Ok(copied)
+}
+
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> io::Result<PathBuf> {
+        // keys come from us, but better safe than sorry
+        if key.split('/').any(|part| part.is_empty() || part == "..") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid key: {key}"),
+            ));
+        }
+
+        Ok(self.root.join(key))
+    }
+
+    async fn create_parent(path: &Path) -> io::Result<()> {
+        match path.parent() {
+            Some(parent) => tokio::fs::create_dir_all(parent).await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl StorageBackend for LocalStorage {
+    fn put<'a>(&'a self, key: &'a str, mut data: ByteStream) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let tmp_dir = self.root.join("tmp");
+            tokio::fs::create_dir_all(&tmp_dir).await?;
+            let tmp_path = tmp_dir.join(format!("{}.part", Uuid::new_v4()));
+
+            let written = async {
+                let mut file = tokio::fs::File::create(&tmp_path).await?;
+                while let Some(chunk) = data.next().await {
+                    file.write_all(&chunk?).await?;
+                }
+                file.sync_all().await
+            }
+            .await;
+
+            match written {
+                Ok(()) => self.put_file(key, &tmp_path).await,
+                Err(e) => {
+                    let _ = tokio::fs::remove_file(&tmp_path).await;
+                    Err(e)
+                }
+            }
+        })
+    }
+
+    fn put_file<'a>(&'a self, key: &'a str, path: &'a Path) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let target = self.path(key)?;
+            Self::create_parent(&target).await?;
+
+            // atomic as long as both are on the same file system
+            if tokio::fs::rename(path, &target).await.is_err() {
+                tokio::fs::copy(path, &target).await?;
+                tokio::fs::remove_file(path).await?;
+            }
+
+            Ok(())
+        })
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<ByteStream>> {
+        Box::pin(async move {
+            let file = tokio::fs::File::open(self.path(key)?).await?;
+            Ok(ReaderStream::new(file).boxed())
+        })
+    }
+
+    fn range<'a>(
+        &'a self,
+        key: &'a str,
+        range: Range<u64>,
+    ) -> BoxFuture<'a, io::Result<ByteStream>> {
+        Box::pin(async move {
+            let mut file = tokio::fs::File::open(self.path(key)?).await?;
+            file.seek(SeekFrom::Start(range.start)).await?;
+            Ok(ReaderStream::new(file.take(range.end.saturating_sub(range.start))).boxed())
+        })
+    }
+
+    fn size<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<u64>> {
+        Box::pin(async move { Ok(tokio::fs::metadata(self.path(key)?).await?.len()) })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            match tokio::fs::remove_file(self.path(key)?).await {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            }
+        })
+    }
+
+    fn list<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, io::Result<Vec<String>>> {
+        Box::pin(async move {
+            let mut keys = Vec::new();
+            let mut dirs = vec![self.root.clone()];
+
+            while let Some(dir) = dirs.pop() {
+                let mut entries = match tokio::fs::read_dir(&dir).await {
+                    Ok(entries) => entries,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                    Err(e) => return Err(e),
+                };
+
+                while let Some(entry) = entries.next_entry().await? {
+                    if entry.file_type().await?.is_dir() {
+                        dirs.push(entry.path());
+                        continue;
+                    }
+
+                    let key = entry
+                        .path()
+                        .strip_prefix(&self.root)
+                        .map(|p| p.to_string_lossy().replace('\\', "/"))
+                        .unwrap_or_default();
+
+                    if key.starts_with(prefix) {
+                        keys.push(key);
+                    }
+                }
+            }
+
+            Ok(keys)
+        })
+    }
+}
+
+pub struct S3Storage {
+    store: AmazonS3,
+}
+
+impl S3Storage {
+    pub fn from_env() -> io::Result<Self> {
+        let var = |name: &str| {
+            std::env::var(name).map_err(|_| io::Error::other(format!("{name} is not set")))
+        };
+
+        let store = AmazonS3Builder::new()
+            .with_endpoint(var("S3_ENDPOINT")?)
+            .with_bucket_name(var("S3_BUCKET")?)
+            .with_region(var("S3_REGION").unwrap_or("us-east-1".into()))
+            .with_access_key_id(var("S3_ACCESS_KEY_ID")?)
+            .with_secret_access_key(var("S3_SECRET_ACCESS_KEY")?)
+            // self hosted stores like MinIO neither have https nor bucket subdomains
+            .with_allow_http(true)
+            .with_virtual_hosted_style_request(false)
+            .build()
+            .map_err(io::Error::other)?;
+
+        Ok(Self { store })
+    }
+
+    async fn get_stream(&self, key: &str, range: Option<GetRange>) -> io::Result<ByteStream> {
+        let options = GetOptions {
+            range,
+            ..Default::default()
+        };
+
+        Ok(self
+            .store
+            .get_opts(&ObjectPath::from(key), options)
+            .await
+            .map_err(to_io)?
+            .into_stream()
+            .map_err(to_io)
+            .boxed())
+    }
+}
+
+impl StorageBackend for S3Storage {
+    fn put<'a>(&'a self, key: &'a str, mut data: ByteStream) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            let upload = self
+                .store
+                .put_multipart(&ObjectPath::from(key))
+                .await
+                .map_err(to_io)?;
+            let mut writer = WriteMultipart::new(upload);
+
+            while let Some(chunk) = data.next().await {
+                match chunk {
+                    Ok(chunk) => writer.write(&chunk),
+                    Err(e) => {
+                        let _ = writer.abort().await;
+                        return Err(e);
+                    }
+                }
+            }
+
+            writer.finish().await.map(|_| ()).map_err(to_io)
+        })
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<ByteStream>> {
+        Box::pin(self.get_stream(key, None))
+    }
+
+    fn range<'a>(
+        &'a self,
+        key: &'a str,
+        range: Range<u64>,
+    ) -> BoxFuture<'a, io::Result<ByteStream>> {
+        Box::pin(async move {
+            if range.is_empty() {
+                return Ok(futures::stream::empty().boxed());
+            }
+            self.get_stream(key, Some(GetRange::Bounded(range))).await
+        })
+    }
+
+    fn size<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<u64>> {
+        Box::pin(async move {
+            self.store
+                .head(&ObjectPath::from(key))
+                .await
+                .map(|meta| meta.size)
+                .map_err(to_io)
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, io::Result<()>> {
+        Box::pin(async move {
+            match self.store.delete(&ObjectPath::from(key)).await {
+                Err(object_store::Error::NotFound { .. }) | Ok(()) => Ok(()),
+                Err(e) => Err(to_io(e)),
+            }
+        })
+    }
+
+    fn list<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, io::Result<Vec<String>>> {
+        Box::pin(async move {
+            // object store prefixes are whole path segments
+            let dir = prefix.rsplit_once('/').map(|(dir, _)| dir);
+
+            self.store
+                .list(dir.map(ObjectPath::from).as_ref())
+                .map_ok(|meta| meta.location.to_string())
+                .try_filter(|key| futures::future::ready(key.starts_with(prefix)))
+                .try_collect()
+                .await
+                .map_err(to_io)
+        })
+    }
+}
+
+fn to_io(e: object_store::Error) -> io::Error {
+    match e {
+        object_store::Error::NotFound { .. } => io::Error::new(io::ErrorKind::NotFound, e),
+        e => io::Error::other(e),
+    }
+}
@@ -0,0 +1,281 @@
+use std::time::Duration;
+
+use axum::{Extension, Json, extract::Path, http::StatusCode, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use common::{
+    api::{ApiError, ApiResult},
+    db::sqlx,
+    jobs::{self, Job},
+    models::{Directory, File, Trash, User},
+};
+
+use super::{blob, conflict, get_subtree};
+
+/// How often the trash is checked for things past their retention.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const DEFAULT_RETENTION_DAYS: i32 = 30;
+
+/// Days things stay in the trash before they are gone for good.
+pub fn retention_days() -> i32 {
+    std::env::var("TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|days| days.parse().ok())
+        .unwrap_or(DEFAULT_RETENTION_DAYS)
+}
+
+#[tracing::instrument(skip(user))]
+pub async fn list(user: Option<Extension<User>>) -> ApiResult<Json<Trash>> {
+    if !user.is_some_and(|u| u.admin) {
+        return Err(ApiError::unauthorized());
+    };
+
+    // sharing the deleted_at with the parent means it went along with it
+    let files = common::db_query_as!(
+        File,
+        fetch_all,
+        r#"
+        SELECT f.* FROM files f
+        LEFT JOIN directories d ON f.directory_id = d.id
+        WHERE f.deleted_at IS NOT NULL AND d.deleted_at IS DISTINCT FROM f.deleted_at
+        ORDER BY f.deleted_at DESC
+        "#
+    )?;
+
+    let directories = common::db_query_as!(
+        Directory,
+        fetch_all,
+        r#"
+        SELECT d.* FROM directories d
+        LEFT JOIN directories p ON d.parent_id = p.id
+        WHERE d.deleted_at IS NOT NULL AND p.deleted_at IS DISTINCT FROM d.deleted_at
+        ORDER BY d.deleted_at DESC
+        "#
+    )?;
+
+    Ok(Json(Trash {
+        files,
+        directories,
+        retention_days: retention_days(),
+    }))
+}
+
+/// Moves a directory with everything below it to the trash.
+///
+/// Whatever was in the trash already keeps its own deleted_at, so it is not restored
+/// along with the directory.
+pub async fn trash_recursive(id: i32) -> ApiResult<()> {
+    let mut tx = common::db::db().begin().await?;
+
+    // NOW() is the same throughout the transaction, which ties the subtree together
+    let trashed = sqlx::query(
+        "UPDATE directories SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    if trashed == 0 {
+        return Err(ApiError::not_found());
+    }
+
+    let subtree = get_subtree(&mut *tx, id).await?;
+
+    sqlx::query(
+        "UPDATE directories SET deleted_at = NOW() WHERE id = ANY($1) AND deleted_at IS NULL",
+    )
+    .bind(&subtree)
+    .execute(&mut *tx)
+    .await?;
+
+    let files = sqlx::query(
+        "UPDATE files SET deleted_at = NOW() WHERE directory_id = ANY($1) AND deleted_at IS NULL",
+    )
+    .bind(&subtree)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    tx.commit().await?;
+
+    tracing::info!(
+        "Moved {} directories with {files} files to the trash",
+        subtree.len()
+    );
+
+    Ok(())
+}
+
+#[tracing::instrument(skip(user))]
+pub async fn restore_file(
+    Path(id): Path<Uuid>,
+    user: Option<Extension<User>>,
+) -> ApiResult<Json<File>> {
+    if !user.is_some_and(|u| u.admin) {
+        return Err(ApiError::unauthorized());
+    };
+
+    let file = common::db_query_as!(
+        File,
+        fetch_one,
+        "SELECT * FROM files WHERE id = $1 AND deleted_at IS NOT NULL",
+        id
+    )
+    .map_err(|_| ApiError::not_found())?;
+
+    check_parent(file.directory_id).await?;
+
+    common::db_query_as!(
+        File,
+        fetch_one,
+        "UPDATE files SET deleted_at = NULL WHERE id = $1 RETURNING *",
+        id
+    )
+    .map(Json)
+    .map_err(|e| conflict(e, &file.file_path))
+}
+
+/// Brings back the directory along with what was deleted with it.
+#[tracing::instrument(skip(user))]
+pub async fn restore_directory(
+    Path(id): Path<i32>,
+    user: Option<Extension<User>>,
+) -> ApiResult<Json<Directory>> {
+    if !user.is_some_and(|u| u.admin) {
+        return Err(ApiError::unauthorized());
+    };
+
+    let mut tx = common::db::db().begin().await?;
+
+    let dir = sqlx::query_as::<_, Directory>(
+        "SELECT * FROM directories WHERE id = $1 AND deleted_at IS NOT NULL FOR UPDATE",
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or(ApiError::not_found())?;
+
+    check_parent(dir.parent_id).await?;
+
+    let subtree = get_subtree(&mut *tx, id).await?;
+
+    sqlx::query("UPDATE directories SET deleted_at = NULL WHERE id = ANY($1) AND deleted_at = $2")
+        .bind(&subtree)
+        .bind(dir.deleted_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| conflict(e, &dir.dir_path))?;
+
+    sqlx::query(
+        "UPDATE files SET deleted_at = NULL WHERE directory_id = ANY($1) AND deleted_at = $2",
+    )
+    .bind(&subtree)
+    .bind(dir.deleted_at)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| conflict(e, &dir.dir_path))?;
+
+    tx.commit().await?;
+
+    Ok(Json(Directory {
+        deleted_at: None,
+        ..dir
+    }))
+}
+
+/// Restoring into a deleted directory would leave it unreachable.
+async fn check_parent(parent_id: Option<i32>) -> ApiResult<()> {
+    if let Some(id) = parent_id
+        && common::db_query_scalar!(
+            i64,
+            fetch_one,
+            "SELECT COUNT(*) FROM directories WHERE id = $1 AND deleted_at IS NULL",
+            id
+        )? == 0
+    {
+        return Err(ApiError::Message(
+            StatusCode::CONFLICT,
+            "The directory it was in is in the trash as well, restore that first.".into(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Deletes everything in the trash right away.
+#[tracing::instrument(skip(user))]
+pub async fn empty(user: Option<Extension<User>>) -> ApiResult<impl IntoResponse> {
+    if !user.is_some_and(|u| u.admin) {
+        return Err(ApiError::unauthorized());
+    };
+
+    purge(0).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Removes what has been in the trash for longer than `older_than_days`,
+/// their content is left to the garbage collection.
+pub async fn purge(older_than_days: i32) -> Result<(), sqlx::Error> {
+    let mut tx = common::db::db().begin().await?;
+
+    let files =
+        sqlx::query("DELETE FROM files WHERE deleted_at <= NOW() - make_interval(days => $1)")
+            .bind(older_than_days)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+
+    // everything below a deleted directory was deleted no later than it, so the cascade
+    // only takes what is due anyway
+    let directories = sqlx::query(
+        "DELETE FROM directories WHERE deleted_at <= NOW() - make_interval(days => $1)",
+    )
+    .bind(older_than_days)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    tx.commit().await?;
+
+    if files > 0 || directories > 0 {
+        tracing::info!("Purged {directories} directories and {files} files from the trash");
+        blob::collect_garbage().await;
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PurgeTrashJob {
+    pub older_than_days: i32,
+}
+
+impl Job for PurgeTrashJob {
+    const KIND: &'static str = "files::purge_trash";
+
+    async fn run(self) -> Result<(), String> {
+        purge(self.older_than_days)
+            .await
+            .map_err(|e| format!("DB error: {e}"))
+    }
+}
+
+/// Queues a purge every [`PURGE_INTERVAL`], spawned once at startup.
+pub async fn run_purge() {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = jobs::enqueue_once(&PurgeTrashJob {
+            older_than_days: retention_days(),
+        })
+        .await
+        {
+            tracing::error!("Error while queueing trash purge: {e:?}");
+        }
+    }
+}
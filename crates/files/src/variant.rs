@@ -0,0 +1,311 @@
+use std::io::Cursor;
+
+use axum::http::StatusCode;
+use image::{
+    DynamicImage, ImageFormat, ImageReader, Limits,
+    codecs::{avif::AvifEncoder, jpeg::JpegEncoder, webp::WebPEncoder},
+    imageops::FilterType,
+};
+use percent_encoding::percent_decode_str;
+use serde::Deserialize;
+
+use common::{
+    Apps,
+    api::{ApiError, ApiResult},
+    models::{File, Visibility},
+};
+
+use super::{blob, share, storage};
+
+/// Requested sizes are rounded up to one of these, so there is a bounded number of variants.
+pub const WIDTHS: [u32; 8] = [160, 320, 480, 640, 960, 1280, 1920, 2560];
+/// Larger sources are served as they are.
+const MAX_SOURCE_BYTES: i64 = 50 * 1024 * 1024;
+/// Stops decompression bombs from taking all the memory.
+const MAX_DECODE_BYTES: u64 = 512 * 1024 * 1024;
+
+const JPEG_QUALITY: u8 = 85;
+const AVIF_SPEED: u8 = 8;
+const AVIF_QUALITY: u8 = 70;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Fit {
+    /// fills the box and crops what sticks out
+    Cover,
+    /// fits into the box, keeping the aspect ratio
+    Contain,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// always lossless, the encoder has no lossy mode
+    Webp,
+    Avif,
+    Png,
+    Jpeg,
+}
+
+impl Format {
+    fn image_format(self) -> ImageFormat {
+        match self {
+            Format::Webp => ImageFormat::WebP,
+            Format::Avif => ImageFormat::Avif,
+            Format::Png => ImageFormat::Png,
+            Format::Jpeg => ImageFormat::Jpeg,
+        }
+    }
+
+    fn from_image_format(format: ImageFormat) -> Option<Self> {
+        match format {
+            ImageFormat::WebP => Some(Format::Webp),
+            ImageFormat::Avif => Some(Format::Avif),
+            ImageFormat::Png => Some(Format::Png),
+            ImageFormat::Jpeg => Some(Format::Jpeg),
+            _ => None,
+        }
+    }
+}
+
+/// `?w=640&h=480&fit=cover&fmt=webp`, all optional.
+#[derive(Deserialize, Debug, Default)]
+pub struct VariantQuery {
+    w: Option<u32>,
+    h: Option<u32>,
+    fit: Option<Fit>,
+    fmt: Option<Format>,
+}
+
+impl VariantQuery {
+    fn is_empty(&self) -> bool {
+        self.w.is_none() && self.h.is_none() && self.fmt.is_none()
+    }
+}
+
+/// The file as asked for in the query, resized and/or converted.
+///
+/// Variants are stored as blobs of their own, derived from the blob of the file, so each
+/// is only rendered once and goes away with the garbage collection along with its source.
+/// Anything that is not a raster image is returned as it is.
+pub async fn apply(file: File, q: &VariantQuery) -> ApiResult<File> {
+    if q.is_empty() {
+        return Ok(file);
+    }
+
+    let (Some(source), Some(source_format)) = (
+        file.sha256.clone(),
+        ImageFormat::from_mime_type(&file.mime_type),
+    ) else {
+        return Ok(file);
+    };
+
+    // gifs would lose their animation
+    if source_format == ImageFormat::Gif && q.fmt.is_none() {
+        return Ok(file);
+    }
+
+    if file.size.is_none_or(|size| size > MAX_SOURCE_BYTES) {
+        return Ok(file);
+    }
+
+    let Some(format) = q.fmt.or(Format::from_image_format(source_format)) else {
+        return Ok(file);
+    };
+
+    let width = q.w.map(snap);
+    let height = q.h.map(snap);
+    let fit = q.fit.unwrap_or(Fit::Contain);
+
+    let params = format!(
+        "w={}&h={}&fit={fit:?}&fmt={format:?}",
+        width.unwrap_or(0),
+        height.unwrap_or(0)
+    )
+    .to_lowercase();
+
+    if let Some((sha256, mime_type, size)) = common::db_query_as!(
+        (String, String, i64),
+        fetch_optional,
+        r#"
+        SELECT sha256, mime_type, size FROM variants
+        WHERE source_sha256 = $1 AND params = $2
+        "#,
+        &source,
+        &params,
+    )? {
+        return Ok(with_variant(file, sha256, mime_type, size, format));
+    }
+
+    let data = storage::read(&blob::content_key(&file)).await?;
+
+    let rendered = tokio::task::spawn_blocking(move || {
+        render(&data, source_format, width, height, fit, format)
+    })
+    .await
+    .map_err(|_| ApiError::internal_server_error())?
+    .map_err(|e| ApiError::Message(StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))?;
+
+    let (size, sha256) = blob::put(&rendered)
+        .await
+        .map_err(|e| ApiError::Message(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let mime_type = format.image_format().to_mime_type().to_string();
+
+    // rendering is deterministic, whoever came first wins
+    common::db_query!(
+        execute,
+        r#"
+        INSERT INTO variants (source_sha256, params, sha256, mime_type, size)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (source_sha256, params) DO NOTHING
+        "#,
+        &source,
+        &params,
+        &sha256,
+        &mime_type,
+        size,
+    )?;
+
+    Ok(with_variant(file, sha256, mime_type, size, format))
+}
+
+/// Width in pixels of the public image behind a `/f/` url of the files app.
+///
+/// Measured from the header of the image the first time and kept with its blob.
+pub async fn width(url: &str) -> ApiResult<Option<u32>> {
+    let Some(path) = url.strip_prefix(&format!("{}/f", Apps::Files.url())) else {
+        return Ok(None);
+    };
+    let path = percent_decode_str(path).decode_utf8_lossy();
+
+    let Some(file) = common::db_query_as!(
+        File,
+        fetch_optional,
+        "SELECT * FROM files WHERE file_path = $1 AND deleted_at IS NULL",
+        path.as_ref()
+    )?
+    else {
+        return Ok(None);
+    };
+
+    let (Some(sha256), Some(source_format)) = (
+        file.sha256.clone(),
+        ImageFormat::from_mime_type(&file.mime_type),
+    ) else {
+        return Ok(None);
+    };
+
+    if share::visibility(&file).await? != Visibility::Public
+        || file.size.is_none_or(|size| size > MAX_SOURCE_BYTES)
+    {
+        return Ok(None);
+    }
+
+    if let Some(width) = common::db_query_scalar!(
+        Option<i32>,
+        fetch_one,
+        "SELECT width FROM blobs WHERE sha256 = $1",
+        &sha256
+    )? {
+        return Ok(Some(width as u32));
+    }
+
+    let data = storage::read(&blob::content_key(&file)).await?;
+
+    let Ok(Ok((width, _))) = tokio::task::spawn_blocking(move || {
+        ImageReader::with_format(Cursor::new(data), source_format).into_dimensions()
+    })
+    .await
+    else {
+        return Ok(None);
+    };
+
+    common::db_query!(
+        execute,
+        "UPDATE blobs SET width = $2 WHERE sha256 = $1",
+        &sha256,
+        width as i32,
+    )?;
+
+    Ok(Some(width))
+}
+
+/// Rounds up to the next of [`WIDTHS`].
+fn snap(size: u32) -> u32 {
+    WIDTHS
+        .into_iter()
+        .find(|w| *w >= size)
+        .unwrap_or(WIDTHS[WIDTHS.len() - 1])
+}
+
+fn render(
+    data: &[u8],
+    source_format: ImageFormat,
+    width: Option<u32>,
+    height: Option<u32>,
+    fit: Fit,
+    format: Format,
+) -> image::ImageResult<Vec<u8>> {
+    let mut limits = Limits::default();
+    limits.max_alloc = Some(MAX_DECODE_BYTES);
+
+    let mut reader = ImageReader::with_format(Cursor::new(data), source_format);
+    reader.limits(limits);
+    let image = reader.decode()?;
+
+    let image = match (width, height) {
+        (Some(w), Some(h)) if fit == Fit::Cover => {
+            // never upscaled, a box larger than the image shrinks with its aspect ratio kept
+            let scale = (image.width() as f64 / w as f64)
+                .min(image.height() as f64 / h as f64)
+                .min(1.0);
+            let (w, h) = (
+                ((w as f64 * scale) as u32).max(1),
+                ((h as f64 * scale) as u32).max(1),
+            );
+            image.resize_to_fill(w, h, FilterType::Lanczos3)
+        }
+        // a missing side follows the aspect ratio
+        (width, height) => {
+            let (w, h) = (width.unwrap_or(u32::MAX), height.unwrap_or(u32::MAX));
+            if w >= image.width() && h >= image.height() {
+                image
+            } else {
+                image.resize(w, h, FilterType::Lanczos3)
+            }
+        }
+    };
+
+    let mut out = Vec::new();
+
+    // both encoders only take 8 bit color
+    match format {
+        Format::Webp => DynamicImage::ImageRgba8(image.into_rgba8())
+            .write_with_encoder(WebPEncoder::new_lossless(&mut out))?,
+        Format::Avif => DynamicImage::ImageRgba8(image.into_rgba8()).write_with_encoder(
+            AvifEncoder::new_with_speed_quality(&mut out, AVIF_SPEED, AVIF_QUALITY),
+        )?,
+        // jpeg has no alpha channel
+        Format::Jpeg => DynamicImage::ImageRgb8(image.into_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut out, JPEG_QUALITY))?,
+        Format::Png => image.write_to(&mut Cursor::new(&mut out), ImageFormat::Png)?,
+    }
+
+    Ok(out)
+}
+
+fn with_variant(file: File, sha256: String, mime_type: String, size: i64, format: Format) -> File {
+    let stem = file
+        .file_name
+        .rsplit_once('.')
+        .map_or(file.file_name.as_str(), |(stem, _)| stem);
+    let file_name = format!("{stem}.{}", format.image_format().extensions_str()[0]);
+
+    File {
+        file_name,
+        mime_type,
+        size: Some(size),
+        sha256: Some(sha256),
+        ..file
+    }
+}
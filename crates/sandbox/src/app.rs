@@ -0,0 +1,53 @@
+use leptos::prelude::*;
+use leptos_meta::{MetaTags, Stylesheet, Title, provide_meta_context};
+use leptos_router::{
+    components::{Route, Router, Routes},
+    path,
+};
+
+use crate::manage::ManagePage;
+use common::{Apps, state::AdminOnly};
+
+pub fn shell(options: LeptosOptions) -> impl IntoView {
+    view! {
+        <!DOCTYPE html>
+        <html class="sandbox" lang="en">
+            <head>
+                <meta charset="utf-8" />
+                <meta name="viewport" content="width=device-width, initial-scale=1" />
+                <AutoReload options=options.clone() />
+                <HydrationScripts options />
+                <MetaTags />
+            </head>
+            <body class="bg-nf-white">
+                <App />
+            </body>
+        </html>
+    }
+}
+
+#[component]
+pub fn App() -> impl IntoView {
+    provide_meta_context();
+
+    view! {
+        <Stylesheet id="leptos" href="/pkg/microweb.css" />
+
+        <Title text="Sandbox Page Manager" />
+
+        <Router>
+            <Routes fallback=|| view! { <p class="p-6">"Nothing here."</p> }>
+                <Route path=path!("manage") view=Manage />
+            </Routes>
+        </Router>
+    }
+}
+
+#[component]
+fn Manage() -> impl IntoView {
+    view! {
+        <AdminOnly app=Apps::SandBox>
+            <ManagePage />
+        </AdminOnly>
+    }
+}
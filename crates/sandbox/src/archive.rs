@@ -0,0 +1,361 @@
+//! Checks keeping uploaded zips from writing outside of their page or unpacking
+//! to far more than was uploaded.
+
+use std::{
+    io::{Read, Seek},
+    str::FromStr,
+};
+
+use zip::{ZipArchive, read::ZipFile};
+
+const DEFAULT_MAX_UNPACKED_SIZE: u64 = 1_000_000_000;
+const DEFAULT_MAX_ENTRIES: usize = 10_000;
+const DEFAULT_MAX_COMPRESSION_RATIO: u64 = 100;
+
+/// How much an archive may unpack to, each configurable through the environment.
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    /// in bytes, of all entries together
+    pub max_unpacked_size: u64,
+    pub max_entries: usize,
+    /// unpacked size of an entry over its compressed size
+    pub max_compression_ratio: u64,
+}
+
+impl Limits {
+    pub fn from_env() -> Self {
+        Self {
+            max_unpacked_size: env_or("SANDBOX_MAX_UNPACKED_SIZE", DEFAULT_MAX_UNPACKED_SIZE),
+            max_entries: env_or("SANDBOX_MAX_ENTRIES", DEFAULT_MAX_ENTRIES),
+            max_compression_ratio: env_or(
+                "SANDBOX_MAX_COMPRESSION_RATIO",
+                DEFAULT_MAX_COMPRESSION_RATIO,
+            ),
+        }
+    }
+}
+
+fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Goes through the headers of every entry without unpacking any of them.
+///
+/// Returns the size everything unpacks to, or what is wrong with each offending entry.
+pub fn check<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    limits: &Limits,
+) -> Result<u64, Vec<String>> {
+    if archive.len() > limits.max_entries {
+        return Err(vec![format!(
+            "The zip has {} entries, at most {} are allowed.",
+            archive.len(),
+            limits.max_entries
+        )]);
+    }
+
+    let mut errors = Vec::new();
+    let mut unpacked: u64 = 0;
+
+    for i in 0..archive.len() {
+        let entry = match archive.by_index_raw(i) {
+            Ok(entry) => entry,
+            Err(e) => {
+                errors.push(format!("Entry {i}: {e}"));
+                continue;
+            }
+        };
+
+        if let Err(e) = check_entry(&entry, limits) {
+            errors.push(format!("{}: {e}", entry.name()));
+        }
+
+        unpacked = unpacked.saturating_add(entry.size());
+    }
+
+    if unpacked > limits.max_unpacked_size {
+        errors.push(format!(
+            "The zip unpacks to {unpacked} bytes, at most {} are allowed.",
+            limits.max_unpacked_size
+        ));
+    }
+
+    if errors.is_empty() {
+        Ok(unpacked)
+    } else {
+        Err(errors)
+    }
+}
+
+fn check_entry<R: Read>(entry: &ZipFile<'_, R>, limits: &Limits) -> Result<(), String> {
+    entry_path(entry.name())?;
+
+    if entry.is_symlink() {
+        return Err("symbolic links are not allowed".into());
+    }
+
+    if entry.encrypted() {
+        return Err("encrypted entries are not supported".into());
+    }
+
+    let ratio = entry.size() / entry.compressed_size().max(1);
+    if !entry.is_dir() && ratio > limits.max_compression_ratio {
+        return Err(format!(
+            "compressed {ratio}:1, at most {}:1 is allowed",
+            limits.max_compression_ratio
+        ));
+    }
+
+    Ok(())
+}
+
+/// The components of the path an entry unpacks to inside the page.
+///
+/// Anything that could point elsewhere is refused rather than cleaned up,
+/// directories keep their trailing slash out of it.
+pub fn entry_path(name: &str) -> Result<Vec<&str>, String> {
+    if name.starts_with('/') {
+        return Err("absolute paths are not allowed".into());
+    }
+
+    if name.contains('\\') {
+        return Err("backslashes are not allowed".into());
+    }
+
+    if name.chars().any(char::is_control) {
+        return Err("control characters are not allowed".into());
+    }
+
+    let components: Vec<&str> = name.strip_suffix('/').unwrap_or(name).split('/').collect();
+
+    // a drive letter makes it absolute on windows
+    if components[0].len() == 2 && components[0].ends_with(':') {
+        return Err("absolute paths are not allowed".into());
+    }
+
+    for component in &components {
+        match *component {
+            "" | "." => return Err("empty path components are not allowed".into()),
+            ".." => return Err("paths leaving the zip are not allowed".into()),
+            _ => {}
+        }
+    }
+
+    Ok(components)
+}
+
+/// Unpacks an entry, never more than its header claimed and [`check`] let through.
+pub fn read_entry<R: Read>(entry: &mut ZipFile<'_, R>, buf: &mut Vec<u8>) -> Result<(), String> {
+    let size = entry.size();
+
+    let read = entry
+        .by_ref()
+        .take(size.saturating_add(1))
+        .read_to_end(buf)
+        .map_err(|e| e.to_string())?;
+
+    if read as u64 > size {
+        return Err("unpacks to more than the zip claims".into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::{
+        AesMode, CompressionMethod, ZipWriter,
+        write::{FileOptions, SimpleFileOptions},
+    };
+
+    use super::*;
+
+    const LIMITS: Limits = Limits {
+        max_unpacked_size: 1_000,
+        max_entries: 3,
+        max_compression_ratio: 10,
+    };
+
+    fn stored() -> SimpleFileOptions {
+        SimpleFileOptions::default().compression_method(CompressionMethod::Stored)
+    }
+
+    fn build(write: impl FnOnce(&mut ZipWriter<Cursor<Vec<u8>>>)) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        write(&mut writer);
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn with_files(files: &[(&str, &[u8])]) -> Vec<u8> {
+        build(|writer| {
+            for (name, content) in files {
+                writer.start_file(*name, stored()).unwrap();
+                writer.write_all(content).unwrap();
+            }
+        })
+    }
+
+    fn check_zip(data: Vec<u8>) -> Result<u64, Vec<String>> {
+        check(&mut ZipArchive::new(Cursor::new(data)).unwrap(), &LIMITS)
+    }
+
+    #[test]
+    fn entry_path_splits_into_components() {
+        assert_eq!(entry_path("index.html"), Ok(vec!["index.html"]));
+        assert_eq!(
+            entry_path("assets/js/app.js"),
+            Ok(vec!["assets", "js", "app.js"])
+        );
+        assert_eq!(entry_path("assets/"), Ok(vec!["assets"]));
+        assert_eq!(entry_path("..hidden/a..b"), Ok(vec!["..hidden", "a..b"]));
+    }
+
+    #[test]
+    fn entry_path_refuses_leaving_the_page() {
+        for name in ["../index.html", "assets/../../x", "a/..", ".."] {
+            assert!(entry_path(name).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn entry_path_refuses_absolute_paths() {
+        for name in ["/etc/passwd", "C:/Windows/x", "c:", "C:\\Windows\\x"] {
+            assert!(entry_path(name).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn entry_path_refuses_backslashes() {
+        for name in ["assets\\app.js", "..\\index.html", "\\\\server\\share"] {
+            assert!(entry_path(name).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn entry_path_refuses_empty_components() {
+        for name in ["", "a//b", "./index.html", "a/./b", "a/b//"] {
+            assert!(entry_path(name).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn entry_path_refuses_control_characters() {
+        assert!(entry_path("index\n.html").is_err());
+        assert!(entry_path("index\0.html").is_err());
+    }
+
+    #[test]
+    fn check_sums_up_the_entries() {
+        let data = with_files(&[("index.html", b"<h1>hi</h1>"), ("a/b.txt", b"b")]);
+        assert_eq!(check_zip(data), Ok(12));
+    }
+
+    #[test]
+    fn check_reports_every_bad_entry() {
+        let data = with_files(&[("../a", b"a"), ("b", b"b"), ("/c", b"c")]);
+        let errors = check_zip(data).unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("../a: "));
+        assert!(errors[1].starts_with("/c: "));
+    }
+
+    #[test]
+    fn check_refuses_symlinks() {
+        let data = build(|writer| {
+            writer.add_symlink("link", "/etc/passwd", stored()).unwrap();
+        });
+
+        assert_eq!(
+            check_zip(data),
+            Err(vec!["link: symbolic links are not allowed".into()])
+        );
+    }
+
+    #[test]
+    fn check_refuses_encrypted_entries() {
+        let data = build(|writer| {
+            let options: FileOptions<()> =
+                stored().with_aes_encryption(AesMode::Aes256, "password");
+            writer.start_file("secret.txt", options).unwrap();
+            writer.write_all(b"secret").unwrap();
+        });
+
+        assert_eq!(
+            check_zip(data),
+            Err(vec![
+                "secret.txt: encrypted entries are not supported".into()
+            ])
+        );
+    }
+
+    #[test]
+    fn check_refuses_too_many_entries() {
+        let data = with_files(&[("a", b"a"), ("b", b"b"), ("c", b"c"), ("d", b"d")]);
+        let errors = check_zip(data).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("4 entries"));
+    }
+
+    #[test]
+    fn check_refuses_unpacking_to_too_much() {
+        let data = with_files(&[("a", &[b'a'; 600]), ("b", &[b'b'; 600])]);
+        let errors = check_zip(data).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("1200 bytes"));
+    }
+
+    #[test]
+    fn check_refuses_high_compression_ratios() {
+        let data = build(|writer| {
+            let options =
+                SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+            writer.start_file("zeros", options).unwrap();
+            writer.write_all(&[0; 900]).unwrap();
+        });
+        let errors = check_zip(data).unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("zeros: compressed"));
+    }
+
+    #[test]
+    fn read_entry_reads_what_the_header_claims() {
+        let mut zip = ZipArchive::new(Cursor::new(with_files(&[("a", b"abc")]))).unwrap();
+        let mut buf = Vec::new();
+
+        read_entry(&mut zip.by_index(0).unwrap(), &mut buf).unwrap();
+
+        assert_eq!(buf, b"abc");
+    }
+
+    #[test]
+    fn read_entry_refuses_more_than_the_header_claims() {
+        let mut data = with_files(&[("a", b"abcdef")]);
+
+        // claims 2 bytes in the local and the central header while 6 are stored
+        for signature in [&b"PK\x03\x04"[..], &b"PK\x01\x02"[..]] {
+            let header = data
+                .windows(4)
+                .position(|window| window == signature)
+                .unwrap();
+            let size = header + if signature[2] == 3 { 22 } else { 24 };
+            data[size..size + 4].copy_from_slice(&2u32.to_le_bytes());
+        }
+
+        let mut zip = ZipArchive::new(Cursor::new(data)).unwrap();
+        let mut buf = Vec::new();
+
+        assert_eq!(
+            read_entry(&mut zip.by_index(0).unwrap(), &mut buf),
+            Err("unpacks to more than the zip claims".into())
+        );
+    }
+}
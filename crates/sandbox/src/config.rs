@@ -0,0 +1,259 @@
+//! How a page wants to be served, read from a `sandbox.toml` at the root of its zip.
+//!
+//! ```toml
+//! entry = "index.html"
+//! spa_fallback = true
+//! not_found = "404.html"
+//!
+//! [[cache]]
+//! path = "assets/**"
+//! cache_control = "public, max-age=31536000, immutable"
+//! ```
+
+use std::io::{Read, Seek};
+
+use axum::http::HeaderValue;
+use serde::{Deserialize, Serialize};
+use zip::{ZipArchive, result::ZipError};
+
+use crate::archive;
+
+pub static CONFIG_FILE: &str = "sandbox.toml";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct PageConfig {
+    /// served at the root of the page, relative links in it are resolved from its directory
+    pub entry: String,
+    /// serve the entry for paths without a file, for pages routing on the client,
+    /// paths with an extension still end up as not found
+    pub spa_fallback: bool,
+    /// served with a 404 for paths without a file
+    pub not_found: Option<String>,
+    /// the first rule matching a path decides its Cache-Control
+    pub cache: Vec<CacheRule>,
+}
+
+impl Default for PageConfig {
+    fn default() -> Self {
+        Self {
+            entry: "index.html".into(),
+            spa_fallback: false,
+            not_found: None,
+            cache: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CacheRule {
+    /// relative to the page, `*` and `?` match within a directory and `**` across them
+    pub path: String,
+    pub cache_control: String,
+}
+
+impl PageConfig {
+    /// The config in the root of a zip, pages without one get the default.
+    pub fn read<R: Read + Seek>(zip: &mut ZipArchive<R>) -> Result<Self, String> {
+        let mut entry = match zip.by_name(CONFIG_FILE) {
+            Ok(entry) => entry,
+            Err(ZipError::FileNotFound) => return Ok(Self::default()),
+            Err(e) => return Err(format!("{CONFIG_FILE}: {e}")),
+        };
+
+        let mut buf = Vec::new();
+        archive::read_entry(&mut entry, &mut buf).map_err(|e| format!("{CONFIG_FILE}: {e}"))?;
+
+        let content =
+            String::from_utf8(buf).map_err(|_| format!("{CONFIG_FILE}: not valid UTF-8"))?;
+
+        Self::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let config: Self =
+            toml::from_str(content).map_err(|e| format!("{CONFIG_FILE}: {}", e.message()))?;
+
+        let mut errors = Vec::new();
+
+        for (key, path) in [
+            ("entry", Some(&config.entry)),
+            ("not_found", config.not_found.as_ref()),
+        ] {
+            if let Some(Err(e)) = path.map(|path| archive::entry_path(path)) {
+                errors.push(format!("{CONFIG_FILE}: {key}: {e}"));
+            }
+        }
+
+        for rule in &config.cache {
+            if let Err(e) = check_pattern(&rule.path) {
+                errors.push(format!("{CONFIG_FILE}: cache: {}: {e}", rule.path));
+            }
+            if HeaderValue::from_str(&rule.cache_control).is_err() {
+                errors.push(format!(
+                    "{CONFIG_FILE}: cache: {}: not a valid header value",
+                    rule.path
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(errors.join(", "))
+        }
+    }
+
+    /// The files the config points at, which have to be in the zip.
+    pub fn required_files(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.entry.as_str()).chain(self.not_found.as_deref())
+    }
+
+    pub fn cache_control(&self, path: &str) -> Option<&str> {
+        self.cache
+            .iter()
+            .find(|rule| glob_match(&rule.path, path))
+            .map(|rule| rule.cache_control.as_str())
+    }
+
+    /// Where relative links in the entry start from.
+    pub fn entry_dir(&self) -> &str {
+        self.entry.rsplit_once('/').map_or("", |(dir, _)| dir)
+    }
+}
+
+/// Refuses runs of wildcards, they match nothing a single one would not.
+fn check_pattern(pattern: &str) -> Result<(), String> {
+    let segments: Vec<&str> = pattern.trim_start_matches('/').split('/').collect();
+
+    if segments.iter().any(|s| s.contains("**") && *s != "**") {
+        return Err("`**` has to be a whole segment".into());
+    }
+    if segments.windows(2).any(|pair| pair == ["**", "**"]) {
+        return Err("`**` can not follow `**`".into());
+    }
+
+    Ok(())
+}
+
+fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.trim_start_matches('/').split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+
+    wildcard_match(
+        &pattern,
+        &path,
+        |segment| *segment == "**",
+        |segment, name| {
+            wildcard_match(
+                segment.as_bytes(),
+                name.as_bytes(),
+                |p| *p == b'*',
+                |p, n| *p == b'?' || p == n,
+            )
+        },
+    )
+}
+
+/// Whether `items` match `pattern`, where wildcards match any number of items and everything
+/// else exactly one.
+///
+/// Only the last wildcard is ever backtracked to, a later one can take anything an earlier
+/// one could have, so this never takes more than `pattern.len() * items.len()` steps.
+fn wildcard_match<P, T>(
+    pattern: &[P],
+    items: &[T],
+    is_wildcard: impl Fn(&P) -> bool,
+    matches: impl Fn(&P, &T) -> bool,
+) -> bool {
+    let (mut p, mut i) = (0, 0);
+    // the last wildcard and how many items were matched when it was reached
+    let mut backtrack = None;
+
+    while i < items.len() {
+        if p < pattern.len() && is_wildcard(&pattern[p]) {
+            backtrack = Some((p, i));
+            p += 1;
+        } else if p < pattern.len() && matches(&pattern[p], &items[i]) {
+            p += 1;
+            i += 1;
+        } else if let Some((wildcard, taken)) = backtrack {
+            // the wildcard takes one more item
+            backtrack = Some((wildcard, taken + 1));
+            p = wildcard + 1;
+            i = taken + 1;
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(is_wildcard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matching_paths() {
+        for (pattern, path) in [
+            ("index.html", "index.html"),
+            ("/index.html", "index.html"),
+            ("*.js", "app.js"),
+            ("*", "app.js"),
+            ("assets/*.css", "assets/main.css"),
+            ("img/?.png", "img/a.png"),
+            ("img/??.png", "img/ab.png"),
+            ("assets/**", "assets/main.css"),
+            ("assets/**", "assets/fonts/inter/regular.woff2"),
+            ("**/*.wasm", "app.wasm"),
+            ("**/*.wasm", "pkg/deep/app.wasm"),
+            ("a/**/b", "a/b"),
+            ("a/**/b", "a/x/y/b"),
+            ("/assets/*-*.js", "assets/index-B_x2Yc7Q.js"),
+        ] {
+            assert!(glob_match(pattern, path), "{pattern} {path}");
+        }
+    }
+
+    #[test]
+    fn paths_not_matching() {
+        for (pattern, path) in [
+            ("index.html", "about.html"),
+            ("*.js", "assets/app.js"),
+            ("*.js", "app.css"),
+            ("assets/*.css", "assets/fonts/main.css"),
+            ("img/?.png", "img/ab.png"),
+            ("img/?.png", "img/.png"),
+            ("assets/**", "img/a.png"),
+            ("a/**/b", "a/x/c"),
+            ("**/*.wasm", "pkg/app.js"),
+            ("assets", "assets/main.css"),
+        ] {
+            assert!(!glob_match(pattern, path), "{pattern} {path}");
+        }
+    }
+
+    #[test]
+    fn runs_of_wildcards_are_refused() {
+        for pattern in ["assets/***", "a**b", "**/**/x", "/**/**"] {
+            let content = format!("[[cache]]\npath = \"{pattern}\"\ncache_control = \"no-cache\"");
+            assert!(PageConfig::parse(&content).is_err(), "{pattern}");
+        }
+
+        let content = "[[cache]]\npath = \"**/*.*\"\ncache_control = \"no-cache\"";
+        assert!(PageConfig::parse(content).is_ok());
+    }
+
+    #[test]
+    fn many_wildcards_do_not_backtrack_forever() {
+        let name = "a".repeat(255);
+
+        assert!(!glob_match(&format!("{}b", "*a".repeat(100)), &name));
+        assert!(!glob_match(
+            &format!("{}x", "**/".repeat(100)),
+            &vec![name.as_str(); 100].join("/")
+        ));
+    }
+}
@@ -0,0 +1,130 @@
+//! Keeps uploaded pages away from everything else on the domain.
+//!
+//! Pages share the registrable domain with the auth cookie, so their scripts must not
+//! run with the origin of the sandbox, which also serves the page manager.
+
+use std::collections::HashMap;
+
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::Response,
+};
+use common::{
+    Apps,
+    api::{ApiError, ApiResult},
+    apps::IntoEnumIterator,
+};
+
+/// Sent along with every file of a page unless the page replaces them.
+///
+/// The `sandbox` directive gives the page an origin of its own, which is why its files
+/// are also handed out to other origins.
+pub fn defaults() -> Vec<(HeaderName, String)> {
+    let ancestors = Apps::iter()
+        .filter(|app| *app != Apps::SandBox)
+        .map(|app| app.url())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    vec![
+        (
+            header::CONTENT_SECURITY_POLICY,
+            [
+                "default-src 'self' data: blob:",
+                "script-src 'self' 'unsafe-inline' 'unsafe-eval' 'wasm-unsafe-eval' blob:",
+                "style-src 'self' 'unsafe-inline'",
+                "connect-src 'self' data: blob:",
+                "form-action 'self'",
+                "base-uri 'self'",
+                &format!("frame-ancestors 'self' {ancestors}"),
+                "sandbox allow-scripts allow-forms allow-modals allow-popups allow-pointer-lock allow-downloads",
+            ]
+            .join("; "),
+        ),
+        // needed for SharedArrayBuffer and with it WASM threads
+        (
+            HeaderName::from_static("cross-origin-opener-policy"),
+            "same-origin".into(),
+        ),
+        (
+            HeaderName::from_static("cross-origin-embedder-policy"),
+            "require-corp".into(),
+        ),
+        (
+            HeaderName::from_static("cross-origin-resource-policy"),
+            "cross-origin".into(),
+        ),
+        (header::ACCESS_CONTROL_ALLOW_ORIGIN, "*".into()),
+        (header::X_CONTENT_TYPE_OPTIONS, "nosniff".into()),
+        (header::REFERRER_POLICY, "no-referrer".into()),
+    ]
+}
+
+/// Adds the [`defaults`] with the overrides of a page applied.
+pub fn apply(response: &mut Response, overrides: &HashMap<String, String>) {
+    let headers = response.headers_mut();
+
+    for (name, value) in defaults() {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            headers.insert(name, value);
+        }
+    }
+
+    // checked when they were set
+    for (name, value) in parse(overrides).unwrap_or_default() {
+        match value {
+            Some(value) => headers.insert(name, value),
+            None => headers.remove(name),
+        };
+    }
+}
+
+/// Turns the overrides of a page into headers, refusing what can not be sent or
+/// would bring cookies back.
+pub fn parse(
+    overrides: &HashMap<String, String>,
+) -> ApiResult<Vec<(HeaderName, Option<HeaderValue>)>> {
+    let mut errors = Vec::new();
+    let mut parsed = Vec::new();
+
+    for (name, value) in overrides {
+        let Ok(name) = HeaderName::from_bytes(name.trim().as_bytes()) else {
+            errors.push(format!("{name}: not a valid header name"));
+            continue;
+        };
+
+        if name == header::SET_COOKIE {
+            errors.push(format!("{name}: pages can not set cookies"));
+            continue;
+        }
+
+        let value = value.trim();
+        if value.is_empty() {
+            parsed.push((name, None));
+            continue;
+        }
+
+        match HeaderValue::from_str(value) {
+            Ok(value) => parsed.push((name, Some(value))),
+            Err(_) => errors.push(format!("{name}: not a valid header value")),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(parsed)
+    } else {
+        Err(ApiError::MultipleMessages(StatusCode::BAD_REQUEST, errors))
+    }
+}
+
+/// Neither hands the cookies sent to the sandbox to a page nor lets it set any.
+pub async fn strip_cookies(mut req: Request, next: Next) -> Response {
+    req.headers_mut().remove(header::COOKIE);
+
+    let mut response = next.run(req).await;
+    response.headers_mut().remove(header::SET_COOKIE);
+
+    response
+}
@@ -1,19 +1,34 @@
+#![recursion_limit = "256"]
+
+#[cfg(feature = "back")]
 use axum::{
-    Extension, Router,
+    Router,
     extract::DefaultBodyLimit,
-    http::StatusCode,
     response::{Html, IntoResponse},
-    routing::{get, post},
+    routing::{get, post, put},
 };
-use common::models::User;
 
+pub mod app;
+#[cfg(feature = "back")]
+mod archive;
+#[cfg(feature = "back")]
+mod config;
+#[cfg(feature = "back")]
+mod headers;
+mod manage;
+#[cfg(feature = "back")]
 mod page;
 
+#[cfg(feature = "back")]
+pub use page::ExtractZipJob;
+
+#[cfg(feature = "back")]
 use common::trace::TraceExt;
 
+/// The json api and the pages, the page manager in [`app`] is served next to them.
+#[cfg(feature = "back")]
 pub fn router() -> Router {
     Router::new()
-        .route("/manage", get(manage_html))
         .route(
             "/create",
             post(page::create).layer(DefaultBodyLimit::max(1e+9 as usize)),
@@ -22,18 +37,40 @@ pub fn router() -> Router {
             "/page/{id}",
             get(page::get_by_id).delete(page::delete_by_id),
         )
+        // the slug of the page, which is created if it does not exist
+        .route(
+            "/page/{id}",
+            put(page::deploy).layer(DefaultBodyLimit::max(1e+9 as usize)),
+        )
+        .route("/page/{id}/versions", get(page::versions))
+        .route("/page/{id}/rollback", post(page::rollback))
+        .route("/page/{id}/headers", post(page::set_headers))
+        .route("/page/{id}/meta", post(page::set_meta))
         .route("/pages", get(page::list))
-        .route("/{slug}", get(page::view))
-        .route("/{slug}/{*file_path}", get(page::view_static))
-        .with_tracing()
         .layer(axum::middleware::from_fn(common::auth::auth_guard))
+        .merge(gallery())
+        .merge(pages())
+        .with_tracing()
+}
+
+/// The public pages, open to anyone.
+#[cfg(feature = "back")]
+fn gallery() -> Router {
+    Router::new()
+        .route("/gallery", get(gallery_html))
+        .route("/gallery/pages", get(page::gallery))
 }
 
-#[tracing::instrument(skip(user))]
-async fn manage_html(user: Option<Extension<User>>) -> impl IntoResponse {
-    if !user.is_some_and(|u| u.admin) {
-        return StatusCode::UNAUTHORIZED.into_response();
-    };
+/// The uploaded pages, which never get to see who is looking at them.
+#[cfg(feature = "back")]
+fn pages() -> Router {
+    Router::new()
+        .route("/{slug}", get(page::view))
+        .route("/{slug}/{*file_path}", get(page::view_static))
+        .layer(axum::middleware::from_fn(headers::strip_cookies))
+}
 
-    Html(include_str!("manage.html")).into_response()
+#[cfg(feature = "back")]
+async fn gallery_html() -> impl IntoResponse {
+    Html(include_str!("gallery.html"))
 }
@@ -0,0 +1,445 @@
+//! Uploading and looking after sandbox pages through the json api.
+
+use std::collections::HashMap;
+
+use gloo_net::http::Request;
+use leptos::{prelude::*, task::spawn_local};
+use web_sys::KeyboardEvent;
+
+use common::{
+    apps::IntoEnumIterator,
+    client,
+    models::{SandboxPage, SandboxPageMeta, SandboxVersion, Visibility},
+    ui::{Breadcrumbs, DropZone, Upload, UploadList, track_upload},
+};
+
+/// Runs an action against the api, showing what went wrong or loading the pages again.
+fn run(reload: RwSignal<u32>, action: impl Future<Output = Result<(), String>> + 'static) {
+    spawn_local(async move {
+        match action.await {
+            Ok(()) => reload.update(|n| *n += 1),
+            Err(e) => {
+                let _ = window().alert_with_message(&e);
+            }
+        }
+    });
+}
+
+#[component]
+pub fn ManagePage() -> impl IntoView {
+    // bumped after every change to load the pages again
+    let reload = RwSignal::new(0u32);
+    let selected = RwSignal::new(None::<usize>);
+    let uploads = RwSignal::new(Vec::<Upload>::new());
+    let slug = RwSignal::new(String::new());
+
+    let pages = LocalResource::new(move || {
+        reload.track();
+        async move { client::fetch::<Vec<SandboxPage>>(Request::get("/pages")).await }
+    });
+
+    let list = Signal::derive(move || pages.get().and_then(Result::ok).unwrap_or_default());
+    let selected_page = Signal::derive(move || {
+        selected
+            .get()
+            .and_then(|i| list.with(|l| l.get(i).cloned()))
+    });
+
+    let crumbs = Signal::derive(move || {
+        let mut crumbs = vec![("Sandbox".to_string(), "/manage".to_string())];
+        if let Some(page) = selected_page.get() {
+            crumbs.push((page.slug.clone(), format!("/{}", page.slug)));
+        }
+        crumbs
+    });
+
+    let on_files = Callback::new(move |files: Vec<web_sys::File>| {
+        let slug = slug.get_untracked().trim().to_string();
+        if slug.is_empty() {
+            let _ = window().alert_with_message("Enter a slug first.");
+            return;
+        }
+
+        // a version per zip, the last one dropped goes live last
+        for file in files {
+            track_upload(
+                uploads,
+                "PUT",
+                format!("/page/{}", encode_slug(&slug)),
+                vec![file],
+                move |_| reload.update(|n| *n += 1),
+            );
+        }
+    });
+
+    let on_key = move |ev: KeyboardEvent| {
+        let last = list.with(Vec::len).saturating_sub(1);
+
+        match ev.key().as_str() {
+            "ArrowDown" | "j" => selected.update(|s| *s = Some(s.map_or(0, |i| (i + 1).min(last)))),
+            "ArrowUp" | "k" => selected.update(|s| *s = Some(s.map_or(0, |i| i.saturating_sub(1)))),
+            "Home" => selected.set(Some(0)),
+            "End" => selected.set(Some(last)),
+            "Escape" => selected.set(None),
+            "Enter" => {
+                if let Some(page) = selected_page.get_untracked() {
+                    let _ = window().open_with_url_and_target(&format!("/{}", page.slug), "_blank");
+                }
+            }
+            _ => return,
+        }
+
+        ev.prevent_default();
+    };
+
+    view! {
+        <div class="mx-auto flex max-w-6xl flex-col gap-6 p-6">
+            <h1 class="text-2xl font-bold">"Sandbox Page Manager"</h1>
+
+            <Breadcrumbs crumbs />
+
+            <div class="grid gap-6 md:grid-cols-3">
+                <section class="flex flex-col gap-2 rounded bg-white p-4 shadow">
+                    <h2 class="font-bold">"Upload Page"</h2>
+                    <p class="text-sm">
+                        "Uploading to an existing slug adds a new version, which goes live once it is extracted."
+                    </p>
+                    <p class="text-sm">
+                        "An optional " <code>"sandbox.toml"</code>
+                        " at the root of the zip sets the entry file, a fallback for single page apps, a 404 page and cache rules."
+                    </p>
+                    <input
+                        type="text"
+                        placeholder="Slug"
+                        class="rounded border border-gray-300 px-2 py-1"
+                        bind:value=slug
+                    />
+                    <DropZone on_files accept=".zip" multiple=true>
+                        <span>"Drop a zip here or click to choose one"</span>
+                    </DropZone>
+                    <UploadList uploads />
+                </section>
+
+                <section class="md:col-span-2 rounded bg-white p-4 shadow">
+                    <h2 class="font-bold">"Pages"</h2>
+                    <p class="mb-2 text-sm">
+                        "Public pages are listed in the "
+                        <a class="underline" href="/gallery" target="_blank">
+                            "gallery"
+                        </a>
+                        ", unlisted ones are only served and private ones not at all."
+                    </p>
+                    <p class="mb-2 text-xs text-gray-500">
+                        "Arrow keys select, Enter opens and Escape closes the details."
+                    </p>
+
+                    {move || {
+                        pages
+                            .get()
+                            .and_then(Result::err)
+                            .map(|e| view! { <p class="text-red-700">{e}</p> })
+                    }}
+
+                    <ul
+                        tabindex="0"
+                        class="flex flex-col focus:outline-none focus:ring"
+                        aria-label="Pages"
+                        on:keydown=on_key
+                    >
+                        {move || {
+                            list.get()
+                                .into_iter()
+                                .enumerate()
+                                .map(|(i, page)| {
+                                    view! {
+                                        <li
+                                            class="flex cursor-pointer items-center justify-between gap-2 rounded px-2 py-1"
+                                            class:bg-nf-white=move || selected.get() == Some(i)
+                                            on:click=move |_| selected.set(Some(i))
+                                        >
+                                            <span class="truncate">
+                                                {format!("{} ({})", page.title, page.slug)}
+                                            </span>
+                                            <span class="shrink-0 text-sm text-gray-500">
+                                                {page.visibility.to_string()}
+                                            </span>
+                                        </li>
+                                    }
+                                })
+                                .collect_view()
+                        }}
+                    </ul>
+                </section>
+            </div>
+
+            {move || {
+                selected_page
+                    .get()
+                    .map(|page| {
+                        view! {
+                            <PageDetails
+                                page
+                                reload
+                                on_deleted=Callback::new(move |_| selected.set(None))
+                            />
+                        }
+                    })
+            }}
+        </div>
+    }
+}
+
+/// Only the characters a slug may have are left as they are, see `page::check_slug`.
+fn encode_slug(slug: &str) -> String {
+    slug.bytes()
+        .map(|b| match b {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' => (b as char).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+#[component]
+fn PageDetails(
+    page: SandboxPage,
+    reload: RwSignal<u32>,
+    #[prop(into)] on_deleted: Callback<()>,
+) -> impl IntoView {
+    let id = page.id.to_string();
+
+    let title = RwSignal::new(page.title.clone());
+    let description = RwSignal::new(page.description.clone());
+    let tags = RwSignal::new(page.tags.join(", "));
+    let thumbnail = RwSignal::new(page.thumbnail.clone().unwrap_or_default());
+    let visibility = RwSignal::new(page.visibility.to_string());
+    let headers = RwSignal::new(
+        serde_json::to_string_pretty(&page.headers).unwrap_or_else(|_| "{}".to_string()),
+    );
+
+    let save_meta = {
+        let id = id.clone();
+        move |ev: leptos::ev::SubmitEvent| {
+            ev.prevent_default();
+
+            let meta = SandboxPageMeta {
+                title: title.get_untracked(),
+                description: description.get_untracked(),
+                tags: tags
+                    .get_untracked()
+                    .split(',')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect(),
+                thumbnail: Some(thumbnail.get_untracked()).filter(|t| !t.trim().is_empty()),
+                visibility: Visibility::iter()
+                    .find(|v| v.to_string() == visibility.get_untracked())
+                    .unwrap_or_default(),
+            };
+
+            let id = id.clone();
+            run(reload, async move {
+                client::fetch_with::<_, SandboxPage>(
+                    Request::post(&format!("/page/{id}/meta")),
+                    &meta,
+                )
+                .await
+                .map(|_| ())
+            });
+        }
+    };
+
+    let save_headers = {
+        let id = id.clone();
+        move |ev: leptos::ev::SubmitEvent| {
+            ev.prevent_default();
+
+            let id = id.clone();
+            run(reload, async move {
+                let headers =
+                    serde_json::from_str::<HashMap<String, String>>(&headers.get_untracked())
+                        .map_err(|e| format!("Invalid JSON: {e}"))?;
+
+                client::send_with(Request::post(&format!("/page/{id}/headers")), &headers)
+                    .await
+                    .map(|_| ())
+            });
+        }
+    };
+
+    let delete = {
+        let id = id.clone();
+        let slug = page.slug.clone();
+        move |_| {
+            if !window()
+                .confirm_with_message(&format!("Delete {slug} with all its versions?"))
+                .unwrap_or(false)
+            {
+                return;
+            }
+
+            let id = id.clone();
+            run(reload, async move {
+                client::send(Request::delete(&format!("/page/{id}"))).await?;
+                on_deleted.run(());
+                Ok(())
+            });
+        }
+    };
+
+    let url = format!("/{}", page.slug);
+
+    view! {
+        <div class="grid gap-6 md:grid-cols-2">
+            <section class="flex flex-col gap-4 rounded bg-white p-4 shadow">
+                <div class="flex items-center justify-between gap-2">
+                    <h2 class="font-bold">{page.slug.clone()}</h2>
+                    <a class="underline" href=url.clone() target="_blank">
+                        "Open"
+                    </a>
+                </div>
+
+                <form class="flex flex-col gap-2" on:submit=save_meta>
+                    <label class="flex flex-col">
+                        "Title" <input type="text" class="rounded border px-2 py-1" bind:value=title />
+                    </label>
+                    <label class="flex flex-col">
+                        "Description"
+                        <textarea rows="3" class="rounded border px-2 py-1" bind:value=description></textarea>
+                    </label>
+                    <label class="flex flex-col">
+                        "Tags, separated by commas"
+                        <input type="text" class="rounded border px-2 py-1" bind:value=tags />
+                    </label>
+                    <label class="flex flex-col">
+                        "Thumbnail, an image inside the page"
+                        <input
+                            type="text"
+                            placeholder="thumbnail.png"
+                            class="rounded border px-2 py-1"
+                            bind:value=thumbnail
+                        />
+                    </label>
+                    <label class="flex flex-col">
+                        "Visibility"
+                        <select class="rounded border px-2 py-1" bind:value=visibility>
+                            {Visibility::iter()
+                                .map(|v| view! { <option value=v.to_string()>{v.to_string()}</option> })
+                                .collect_view()}
+                        </select>
+                    </label>
+                    <button type="submit" class="self-start rounded bg-nf-color px-3 py-1 text-white">
+                        "Save"
+                    </button>
+                </form>
+
+                <form class="flex flex-col gap-2" on:submit=save_headers>
+                    <label class="flex flex-col">
+                        "Headers replacing the defaults as JSON, an empty value drops the header"
+                        <textarea
+                            rows="4"
+                            class="rounded border px-2 py-1 font-mono text-sm"
+                            bind:value=headers
+                        ></textarea>
+                    </label>
+                    <button type="submit" class="self-start rounded bg-nf-color px-3 py-1 text-white">
+                        "Save headers"
+                    </button>
+                </form>
+
+                <Versions id=id.clone() reload />
+
+                <button class="self-start rounded bg-red-700 px-3 py-1 text-white" on:click=delete>
+                    "Delete"
+                </button>
+            </section>
+
+            <section class="rounded bg-white p-4 shadow">
+                <h2 class="mb-2 font-bold">"Preview"</h2>
+                // the page is served with its own sandboxing, see `headers`
+                <iframe
+                    class="aspect-video w-full border-0"
+                    src=url
+                    title=page.title.clone()
+                ></iframe>
+            </section>
+        </div>
+    }
+}
+
+#[component]
+fn Versions(id: String, reload: RwSignal<u32>) -> impl IntoView {
+    let versions = LocalResource::new({
+        let id = id.clone();
+        move || {
+            reload.track();
+            let id = id.clone();
+            async move {
+                client::fetch::<Vec<SandboxVersion>>(Request::get(&format!("/page/{id}/versions")))
+                    .await
+            }
+        }
+    });
+
+    view! {
+        <div class="flex flex-col gap-1 text-sm">
+            <h3 class="font-bold">"Versions"</h3>
+            {move || {
+                let id = id.clone();
+                versions
+                    .get()
+                    .and_then(Result::ok)
+                    .map(|versions| {
+                        versions
+                            .into_iter()
+                            .map(|version| {
+                                let state = if version.failed_at.is_some() {
+                                    " | failed"
+                                } else if version.current {
+                                    " | live"
+                                } else if version.extracted_at.is_none() {
+                                    " | extracting"
+                                } else {
+                                    ""
+                                };
+                                let rollback = (version.extracted_at.is_some() && !version.current)
+                                    .then(|| {
+                                        let id = id.clone();
+                                        view! {
+                                            <button
+                                                class="underline"
+                                                on:click=move |_| {
+                                                    let id = id.clone();
+                                                    run(reload, async move {
+                                                        client::send(Request::post(&format!(
+                                                            "/page/{id}/rollback?version={}",
+                                                            version.version,
+                                                        )))
+                                                        .await
+                                                        .map(|_| ())
+                                                    });
+                                                }
+                                            >
+                                                "Roll back"
+                                            </button>
+                                        }
+                                    });
+
+                                view! {
+                                    <div class="flex justify-between gap-2">
+                                        <span>
+                                            {format!(
+                                                "v{} | {}{state}",
+                                                version.version,
+                                                version.created_at.format("%Y-%m-%d %H:%M"),
+                                            )}
+                                        </span>
+                                        {rollback}
+                                    </div>
+                                }
+                            })
+                            .collect_view()
+                    })
+            }}
+        </div>
+    }
+}
@@ -1,26 +1,56 @@
-use std::{collections::HashMap, io::Cursor, path::PathBuf};
+use std::{
+    collections::HashMap,
+    io::{Read, Seek},
+    path::PathBuf,
+};
 
 use axum::{
     Extension, Json,
     extract::{Multipart, Path, Query},
-    http::{StatusCode, header},
-    response::IntoResponse,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::Response,
 };
 use chrono::Utc;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncWriteExt, sync::mpsc};
 use uuid::Uuid;
 use zip::ZipArchive;
 
 use common::{
     api::{ApiError, ApiResult},
-    models::{Directory, File, SandboxPage, User},
+    db::sqlx,
+    jobs::{self, Job},
+    models::{
+        Directory, File, GalleryPage, SandboxPage, SandboxPageMeta, SandboxVersion, User,
+        sandbox_embed,
+    },
 };
 
-use files::{DIRECTORY, directory};
+use files::{DIRECTORY, directory::DeleteDirectoryJob};
+
+use crate::{
+    archive::{self, Limits},
+    config::PageConfig,
+    headers,
+};
 
 use common::Apps;
 
 pub static SANDBOX_DIR: &str = "/~/sandbox";
+/// where uploaded zips wait for extraction, inside of [`DIRECTORY`]
+static UPLOAD_DIR: &str = "uploads";
+
+/// How many versions of a page are kept if not configured otherwise.
+const DEFAULT_KEEP_VERSIONS: i64 = 5;
+
+/// Versions of a page kept to roll back to, older ones are deleted once a new one is live.
+pub fn keep_versions() -> i64 {
+    std::env::var("SANDBOX_KEEP_VERSIONS")
+        .ok()
+        .and_then(|keep| keep.parse().ok())
+        .unwrap_or(DEFAULT_KEEP_VERSIONS)
+        .max(1)
+}
 
 #[derive(Deserialize, Debug)]
 pub struct UploadPageQuery {
@@ -37,10 +67,106 @@ pub async fn create(
         return Err(ApiError::unauthorized());
     };
 
+    check_slug(&q.slug)?;
+    let upload = read_zip(multipart).await?;
+    let archive = upload.archive.clone();
+
+    let created = new_page(&q.slug, upload).await;
+    if created.is_err() {
+        discard(archive);
+    }
+
+    created.map(|(page, _)| Json(page))
+}
+
+/// Uploads a new version of a page, creating the page if there is none yet.
+///
+/// The current version is served until the new one is extracted.
+#[tracing::instrument(skip(user, multipart))]
+pub async fn deploy(
+    Path(slug): Path<String>,
+    user: Option<Extension<User>>,
+    multipart: Multipart,
+) -> ApiResult<Json<SandboxVersion>> {
+    if !user.is_some_and(|u| u.admin) {
+        return Err(ApiError::unauthorized());
+    };
+
+    let upload = read_zip(multipart).await?;
+    let archive = upload.archive.clone();
+
+    let version = async {
+        let page = common::db_query_as!(
+            SandboxPage,
+            fetch_optional,
+            "SELECT * FROM sandbox WHERE slug = $1",
+            &slug
+        )?;
+
+        match page {
+            Some(page) => new_version(&page, upload).await,
+            None => {
+                check_slug(&slug)?;
+                new_page(&slug, upload).await.map(|(_, version)| version)
+            }
+        }
+    }
+    .await;
+
+    if version.is_err() {
+        discard(archive);
+    }
+
+    version.map(Json)
+}
+
+/// Paths of the sandbox host that are not pages, see `router` and the assets of the site.
+const RESERVED_SLUGS: [&str; 11] = [
+    "api",
+    "assets",
+    "create",
+    "favicon.ico",
+    "fonts",
+    "gallery",
+    "manage",
+    "page",
+    "pages",
+    "pkg",
+    "resources",
+];
+
+/// Slugs end up in urls and directory names, so they are kept to what needs no escaping.
+fn check_slug(slug: &str) -> ApiResult<()> {
+    let valid = !slug.is_empty()
+        && !slug.starts_with('.')
+        && slug
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'));
+
+    if !valid {
+        return Err(ApiError::Message(
+            StatusCode::BAD_REQUEST,
+            "Slugs may only contain letters, digits, '-', '_' and '.' and not start with '.'."
+                .to_string(),
+        ));
+    }
+
+    if RESERVED_SLUGS.contains(&slug) {
+        return Err(ApiError::Message(
+            StatusCode::BAD_REQUEST,
+            format!("{slug} is reserved."),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Creates the directory of a page with its first version in it.
+async fn new_page(slug: &str, upload: Upload) -> ApiResult<(SandboxPage, SandboxVersion)> {
     let Some(sbx_dir) = common::db_query_as!(
         Directory,
         fetch_optional,
-        "SELECT * FROM directories WHERE dir_path = $1",
+        "SELECT * FROM directories WHERE dir_path = $1 AND deleted_at IS NULL",
         SANDBOX_DIR
     )
     .unwrap_or(None) else {
@@ -56,49 +182,399 @@ pub async fn create(
             .await
             .iter()
             .fold(String::new(), |acc, dir| format!("{acc}/{}", dir.dir_name)),
-        &q.slug,
+        slug,
     );
 
-    let dir = common::db_query_as!(
-        Directory,
-        fetch_one,
+    // all or nothing, a half created page would be in the way of the next attempt
+    let mut tx = common::db::db().begin().await?;
+
+    let page_dir = sqlx::query_as::<_, Directory>(
         r#"
         INSERT INTO directories (parent_id, dir_name, dir_path)
         VALUES ($1, $2, $3)
         RETURNING *
-    "#,
-        sbx_dir.id,
-        &q.slug,
-        &full_path,
-    )?;
+        "#,
+    )
+    .bind(sbx_dir.id)
+    .bind(slug)
+    .bind(&full_path)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| files::conflict(e, &full_path))?;
 
-    let _files = upload_zip(dir.id, full_path, &q.slug, multipart).await?;
+    let dir = version_dir(&mut tx, &page_dir, 1).await?;
 
-    common::db_query_as!(
-        SandboxPage,
+    let page = sqlx::query_as::<_, SandboxPage>(
+        r#"
+        INSERT INTO sandbox (directory_id, slug, title)
+        VALUES ($1, $2, $2)
+        RETURNING *
+        "#,
+    )
+    .bind(dir.id)
+    .bind(slug)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let version = insert_version(&mut tx, &page, &dir, 1, &upload.config).await?;
+
+    tx.commit().await?;
+
+    queue_extraction(&page, dir, &version, upload).await?;
+
+    Ok((page, version))
+}
+
+/// Adds a version next to the ones a page already has.
+async fn new_version(page: &SandboxPage, upload: Upload) -> ApiResult<SandboxVersion> {
+    let page_dir = common::db_query_as!(
+        Directory,
         fetch_one,
         r#"
-        INSERT INTO sandbox (directory_id, slug)
-        VALUES ($1, $2)
+        SELECT p.* FROM directories p
+        JOIN directories d ON d.parent_id = p.id
+        WHERE d.id = $1
+        "#,
+        page.directory_id
+    )?;
+
+    let mut tx = common::db::db().begin().await?;
+
+    let version = sqlx::query_scalar::<_, i32>(
+        "SELECT COALESCE(MAX(version), 0) + 1 FROM sandbox_versions WHERE page_id = $1",
+    )
+    .bind(page.id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let dir = version_dir(&mut tx, &page_dir, version).await?;
+    let version = insert_version(&mut tx, page, &dir, version, &upload.config).await?;
+
+    tx.commit().await?;
+
+    queue_extraction(page, dir, &version, upload).await?;
+
+    Ok(version)
+}
+
+/// Creates the directory a version is extracted into, named after the version.
+async fn version_dir(
+    tx: &mut sqlx::PgConnection,
+    page_dir: &Directory,
+    version: i32,
+) -> ApiResult<Directory> {
+    let name = format!("@{version}");
+    let path = format!("{}/{name}", page_dir.dir_path);
+
+    sqlx::query_as::<_, Directory>(
+        r#"
+        INSERT INTO directories (parent_id, dir_name, dir_path)
+        VALUES ($1, $2, $3)
         RETURNING *
-    "#,
-        dir.id,
-        &q.slug,
+        "#,
     )
-    .map(Json)
+    .bind(page_dir.id)
+    .bind(&name)
+    .bind(&path)
+    .fetch_one(tx)
+    .await
+    .map_err(|e| files::conflict(e, &path))
+}
+
+/// Records a version, it is served once [`ExtractZipJob`] is done with it.
+async fn insert_version(
+    tx: &mut sqlx::PgConnection,
+    page: &SandboxPage,
+    dir: &Directory,
+    version: i32,
+    config: &PageConfig,
+) -> ApiResult<SandboxVersion> {
+    sqlx::query_as::<_, SandboxVersion>(
+        r#"
+        INSERT INTO sandbox_versions (page_id, version, directory_id, config)
+        VALUES ($1, $2, $3, $4)
+        RETURNING version, directory_id, created_at, extracted_at, failed_at,
+            directory_id = $5 AS current
+        "#,
+    )
+    .bind(page.id)
+    .bind(version)
+    .bind(dir.id)
+    .bind(sqlx::types::Json(config))
+    .bind(page.directory_id)
+    .fetch_one(tx)
+    .await
     .map_err(Into::into)
 }
 
-pub async fn upload_zip(
+/// Leaves extracting the upload into the directory of a version to a job.
+async fn queue_extraction(
+    page: &SandboxPage,
+    dir: Directory,
+    version: &SandboxVersion,
+    upload: Upload,
+) -> ApiResult<()> {
+    jobs::enqueue(&ExtractZipJob {
+        directory_id: dir.id,
+        root_path: dir.dir_path,
+        slug: page.slug.clone(),
+        archive: upload.archive,
+        page_id: Some(page.id),
+        version: Some(version.version),
+    })
+    .await?;
+
+    Ok(())
+}
+
+/// Unpacks an uploaded zip into the directory of a sandbox page.
+///
+/// The archive is kept on disk until it was extracted, files already extracted
+/// by an earlier attempt are skipped. Once done the version goes live, if all attempts
+/// fail the archive is removed and the version marked as failed.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ExtractZipJob {
+    pub directory_id: i32,
+    pub root_path: String,
+    pub slug: String,
+    pub archive: PathBuf,
+    /// missing for jobs queued before pages had versions
+    #[serde(default)]
+    pub page_id: Option<Uuid>,
+    #[serde(default)]
+    pub version: Option<i32>,
+}
+
+impl Job for ExtractZipJob {
+    const KIND: &'static str = "sandbox::extract_zip";
+
+    async fn run(self) -> Result<(), String> {
+        extract_zip(
+            self.directory_id,
+            self.root_path,
+            &self.slug,
+            self.archive.clone(),
+        )
+        .await
+        .map_err(|e| match e {
+            ApiError::MultipleMessages(_, errors) => errors.join(", "),
+            e => e.to_string(),
+        })?;
+
+        if let (Some(page_id), Some(version)) = (self.page_id, self.version) {
+            activate(page_id, version)
+                .await
+                .map_err(|e| format!("DB error: {e}"))?;
+            prune(page_id).await.map_err(|e| e.to_string())?;
+        }
+
+        tokio::fs::remove_file(&self.archive)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn give_up(self) {
+        if let Err(e) = tokio::fs::remove_file(&self.archive).await {
+            tracing::error!("Error while removing {}: {e}", self.archive.display());
+        }
+
+        if let (Some(page_id), Some(version)) = (self.page_id, self.version)
+            && let Err(e) = common::db_query!(
+                execute,
+                "UPDATE sandbox_versions SET failed_at = NOW() WHERE page_id = $1 AND version = $2",
+                page_id,
+                version
+            )
+        {
+            tracing::error!("Error while marking version {version} as failed: {e:?}");
+        }
+    }
+}
+
+/// Marks a version as extracted and serves it, unless a newer one went live in the meantime
+/// or the page was rolled back after it was uploaded.
+async fn activate(page_id: Uuid, version: i32) -> Result<(), sqlx::Error> {
+    let mut tx = common::db::db().begin().await?;
+
+    sqlx::query(
+        "UPDATE sandbox_versions SET extracted_at = NOW() WHERE page_id = $1 AND version = $2",
+    )
+    .bind(page_id)
+    .bind(version)
+    .execute(&mut *tx)
+    .await?;
+
+    // pointing the page at another directory is all it takes to switch versions
+    sqlx::query(
+        r#"
+        UPDATE sandbox s SET
+            directory_id = v.directory_id,
+            updated_at = CASE WHEN s.directory_id = v.directory_id THEN s.updated_at ELSE NOW() END
+        FROM sandbox_versions v, sandbox_versions c
+        WHERE s.id = $1 AND v.page_id = s.id AND v.version = $2
+            AND c.page_id = s.id AND c.directory_id = s.directory_id AND c.version <= v.version
+            AND (s.rolled_back_at IS NULL OR v.created_at > s.rolled_back_at)
+        "#,
+    )
+    .bind(page_id)
+    .bind(version)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await
+}
+
+/// Deletes all but the newest [`keep_versions`], never the one being served.
+async fn prune(page_id: Uuid) -> ApiResult<()> {
+    let directories = common::db_query_scalar!(
+        i32,
+        fetch_all,
+        r#"
+        DELETE FROM sandbox_versions v USING sandbox s
+        WHERE v.page_id = $1 AND s.id = v.page_id AND v.directory_id <> s.directory_id
+            AND v.version NOT IN (
+                SELECT version FROM sandbox_versions WHERE page_id = $1
+                ORDER BY version DESC LIMIT $2
+            )
+        RETURNING v.directory_id
+        "#,
+        page_id,
+        keep_versions(),
+    )?;
+
+    for id in directories {
+        jobs::enqueue(&DeleteDirectoryJob { id }).await?;
+    }
+
+    Ok(())
+}
+
+/// A zip that passed the checks, waiting in [`UPLOAD_DIR`] along with its `sandbox.toml`.
+struct Upload {
+    archive: PathBuf,
+    config: PageConfig,
+}
+
+/// Streams the zip out of the multipart to disk and makes sure it can become a page,
+/// it is removed again if not.
+async fn read_zip(mut multipart: Multipart) -> ApiResult<Upload> {
+    let mut field = multipart.next_field().await?.ok_or(ApiError::Message(
+        StatusCode::BAD_REQUEST,
+        "At least one file should be in multipart.".to_string(),
+    ))?;
+
+    let upload_dir = PathBuf::from(DIRECTORY).join(UPLOAD_DIR);
+    tokio::fs::create_dir_all(&upload_dir).await?;
+    let archive = upload_dir.join(format!("{}.zip", Uuid::new_v4()));
+
+    let config = async {
+        let mut file = tokio::fs::File::create(&archive).await?;
+        while let Some(chunk) = field.chunk().await? {
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+
+        check_zip(archive.clone()).await
+    }
+    .await;
+
+    match config {
+        Ok(config) => Ok(Upload { archive, config }),
+        Err(e) => {
+            discard(archive);
+            Err(e)
+        }
+    }
+}
+
+/// The checks of an upload, reading the headers of the zip off the async runtime.
+async fn check_zip(path: PathBuf) -> ApiResult<PageConfig> {
+    let (unpacked, config) = blocking(move || {
+        let mut zip = open_zip(&path)?;
+
+        // as much as the archive claims to unpack to, reading more is refused later on
+        let unpacked = archive::check(&mut zip, &Limits::from_env())?;
+
+        let config = PageConfig::read(&mut zip).map_err(|e| vec![e])?;
+
+        let missing = config
+            .required_files()
+            .filter(|path| !zip.file_names().any(|name| name == *path))
+            .map(|path| format!("Missing {path} in zip."))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return Err(missing);
+        }
+
+        Ok((unpacked, config))
+    })
+    .await?;
+
+    files::policy::check_quota(unpacked as i64).await?;
+
+    Ok(config)
+}
+
+/// Removes an upload that is not going to be extracted, in the background.
+fn discard(archive: PathBuf) {
+    tokio::spawn(async move {
+        if let Err(e) = tokio::fs::remove_file(&archive).await {
+            tracing::error!("Error while removing {}: {e}", archive.display());
+        }
+    });
+}
+
+/// Runs blocking zip work on a thread of its own, failing with what is wrong with the zip.
+async fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T, Vec<String>> + Send + 'static,
+) -> ApiResult<T> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| ApiError::Message(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|errors| ApiError::MultipleMessages(StatusCode::BAD_REQUEST, errors))
+}
+
+fn open_zip(path: &std::path::Path) -> Result<ZipArchive<std::fs::File>, Vec<String>> {
+    let file = std::fs::File::open(path).map_err(|e| vec![format!("Could not read zip: {e}")])?;
+
+    ZipArchive::new(file).map_err(|e| vec![format!("Not a valid zip: {e}")])
+}
+
+/// A file of a zip by its name, or what kept it from being unpacked.
+type Unpacked = Result<(String, Vec<u8>), String>;
+
+/// Unpacks the files of a zip one after the other, until `entries` is closed.
+fn unpack<R: Read + Seek>(mut zip: ZipArchive<R>, entries: mpsc::Sender<Unpacked>) {
+    for i in 0..zip.len() {
+        let unpacked = match zip.by_index(i) {
+            Ok(entry) if entry.is_dir() => continue,
+            Ok(entry) if !entry.is_file() => Err(format!("{}: not a regular file", entry.name())),
+            Ok(mut entry) => {
+                let name = entry.name().to_string();
+                let mut buf = Vec::new();
+                archive::read_entry(&mut entry, &mut buf)
+                    .map(|_| (name.clone(), buf))
+                    .map_err(|e| format!("{name}: {e}"))
+            }
+            Err(e) => Err(format!("Entry {i}: {e}")),
+        };
+
+        if entries.blocking_send(unpacked).is_err() {
+            return;
+        }
+    }
+}
+
+pub async fn extract_zip(
     parent_id: i32,
     root_path: String,
     slug: &str,
-    mut multipart: Multipart,
+    archive: PathBuf,
 ) -> ApiResult<Vec<File>> {
     let exists = common::db_query_scalar!(
         i64,
         fetch_one,
-        "SELECT COUNT(*) FROM directories WHERE id = $1",
+        "SELECT COUNT(*) FROM directories WHERE id = $1 AND deleted_at IS NULL",
         parent_id
     )
     .unwrap_or(0);
@@ -112,44 +588,56 @@ pub async fn upload_zip(
     let mut uploaded_files = Vec::new();
     let mut errors = Vec::new();
 
-    let field = multipart.next_field().await?.ok_or(ApiError::Message(
-        StatusCode::BAD_REQUEST,
-        "At least one file should be in multipart.".to_string(),
-    ))?;
+    let (zip, config) = blocking(move || {
+        let mut zip = open_zip(&archive)?;
+
+        // the limits may have changed since the upload
+        archive::check(&mut zip, &Limits::from_env())?;
+
+        let config = PageConfig::read(&mut zip).map_err(|e| vec![e])?;
 
-    let data = field.bytes().await?;
+        Ok((zip, config))
+    })
+    .await?;
 
-    let mut archive = ZipArchive::new(Cursor::new(data)).map_err(ApiError::any)?;
+    // entries are unpacked off the async runtime and stored here one at a time
+    let (entries, mut unpacked) = mpsc::channel(1);
+    let unpacking = tokio::task::spawn_blocking(move || unpack(zip, entries));
 
     let mut dir_cache: HashMap<String, i32> = HashMap::new();
     dir_cache.insert("".into(), parent_id); // root
 
-    // Before the loop
-    let mut found_index_html = false;
+    let mut found_entry = false;
 
-    // Inside the for loop
-    for i in 0..archive.len() {
-        let mut entry = archive.by_index(i).unwrap();
-        if entry.is_dir() {
-            continue;
-        }
+    while let Some(entry) = unpacked.recv().await {
+        let (name, mut buf) = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+
+        let mut path = match archive::entry_path(&name) {
+            Ok(path) => path,
+            Err(e) => {
+                errors.push(format!("{name}: {e}"));
+                continue;
+            }
+        };
 
-        let path = PathBuf::from(entry.name());
-        let is_root_index = path.to_str().is_some_and(|p| p == "index.html");
-        if is_root_index {
-            found_index_html = true;
+        let is_entry = name == config.entry;
+        if is_entry {
+            found_entry = true;
         }
 
-        let file_name = path
-            .file_name()
-            .map(|s| s.to_string_lossy().to_string())
-            .unwrap_or_else(|| format!("file_{}", Uuid::new_v4()));
+        let file_name = path.pop().unwrap_or_default().to_string();
 
         let mut current_path = root_path.clone();
         let mut current_parent = parent_id;
 
-        for component in path.parent().unwrap_or(PathBuf::new().as_path()) {
-            let comp_str = component.to_string_lossy().to_string();
+        for component in path {
+            let comp_str = component.to_string();
             current_path = format!("{current_path}/{comp_str}");
             if dir_cache.contains_key(&current_path) {
                 current_parent = dir_cache[&current_path];
@@ -159,7 +647,7 @@ pub async fn upload_zip(
             let existing = common::db_query_as!(
                 Directory,
                 fetch_optional,
-                "SELECT * FROM directories WHERE dir_path = $1",
+                "SELECT * FROM directories WHERE dir_path = $1 AND deleted_at IS NULL",
                 &current_path
             )
             .unwrap_or(None);
@@ -200,25 +688,50 @@ pub async fn upload_zip(
             file_name
         );
 
+        // left over from an earlier attempt
         if common::db_query_scalar!(
             i64,
             fetch_one,
-            "SELECT COUNT(*) FROM files WHERE file_path = $1",
+            "SELECT COUNT(*) FROM files WHERE file_path = $1 AND deleted_at IS NULL",
             &full_path
         )
         .unwrap_or(0)
             > 0
         {
-            errors.push(format!("Duplicate file: {file_name}"));
             continue;
         }
 
+        // the entry is also served at the root of the page and for routes of a single page app,
+        // which would break its relative links
+        if is_entry && let Ok(mut html) = String::from_utf8(buf.clone()) {
+            let base = match config.entry_dir() {
+                "" => format!("{}/{slug}/", Apps::SandBox.url()),
+                dir => format!("{}/{slug}/{dir}/", Apps::SandBox.url()),
+            };
+            let base_tag = format!(r#"<base href="{base}"/>"#);
+            if let Some(idx) = html.find("<head>") {
+                html.insert_str(idx + 6, &base_tag);
+                buf = html.into_bytes();
+            }
+        }
+
+        // assets shared between pages end up in the same blob
+        let (size, sha256) = match files::blob::put(&buf).await {
+            Ok(stored) => stored,
+            Err(e) => {
+                errors.push(format!("{name}: {e}"));
+                continue;
+            }
+        };
+
         let file = common::db_query_as!(
             File,
             fetch_one,
             r#"
-            INSERT INTO files (id, directory_id, file_name, file_path, mime_type, uploaded_at)
-            VALUES ($1, $2, $3, $4, $5, $6)
+            INSERT INTO files (
+                id, directory_id, file_name, file_path, mime_type, uploaded_at, size, sha256
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
             RETURNING *
             "#,
             id,
@@ -227,47 +740,34 @@ pub async fn upload_zip(
             &full_path,
             &mime_type,
             Utc::now(),
+            size,
+            sha256,
         );
 
         match file {
             Ok(res) => {
-                let disk_path = PathBuf::from(DIRECTORY).join(id.to_string());
-                let mut buf = Vec::new();
-                if let Err(e) = std::io::copy(&mut entry, &mut buf).map(|_| ()) {
-                    errors.push(e.to_string());
-                    continue;
-                }
-
-                // If it's index.html in root, modify the HTML
-                if is_root_index && let Ok(mut html) = String::from_utf8(buf.clone()) {
-                    let base_tag = format!(r#"<base href="{}/{slug}/"/>"#, Apps::SandBox.url());
-                    if let Some(idx) = html.find("<head>") {
-                        html.insert_str(idx + 6, &base_tag);
-                        buf = html.into_bytes();
-                    }
-                }
-
-                if let Err(e) = tokio::fs::write(disk_path, buf).await {
-                    errors.push(e.to_string());
-                    continue;
-                }
+                files::encoding::queue(&res).await;
                 uploaded_files.push(res);
             }
-            Err(e) => errors.push(e.to_string()),
+            Err(e) => errors.push(format!("{name}: {e}")),
         }
     }
 
-    if !found_index_html {
+    unpacking
+        .await
+        .map_err(|e| ApiError::Message(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    if !found_entry {
         return Err(ApiError::Message(
             StatusCode::BAD_REQUEST,
-            "Missing index.html in root of zip.".to_string(),
+            format!("Missing {} in zip.", config.entry),
         ));
     }
 
-    if uploaded_files.is_empty() {
-        Err(ApiError::MultipleMessages(StatusCode::BAD_REQUEST, errors))
-    } else {
+    if errors.is_empty() {
         Ok(uploaded_files)
+    } else {
+        Err(ApiError::MultipleMessages(StatusCode::BAD_REQUEST, errors))
     }
 }
 
@@ -307,60 +807,367 @@ pub async fn delete_by_id(Path(id): Path<Uuid>, user: Option<Extension<User>>) -
         return Err(ApiError::unauthorized());
     };
 
-    let dir_id = common::db_query_scalar!(
-        i32,
+    // the page is gone right away, its files follow in the background
+    let page_dir = common::db_query_scalar!(
+        Option<i32>,
         fetch_one,
-        "SELECT d.id FROM directories d JOIN sandbox s ON s.directory_id = d.id WHERE s.id = $1",
+        r#"
+        DELETE FROM sandbox s USING directories d
+        WHERE s.id = $1 AND d.id = s.directory_id
+        RETURNING d.parent_id
+        "#,
         id
     )?;
 
-    println!("{dir_id}");
+    // the directory of the page holds all of its versions
+    if let Some(id) = page_dir {
+        jobs::enqueue(&DeleteDirectoryJob { id }).await?;
+    }
 
-    directory::delete_by_id(Path(dir_id), user).await
+    Ok(())
 }
 
-pub async fn view(Path(slug): Path<String>) -> ApiResult<impl IntoResponse> {
-    let dir = common::db_query_as!(
-        Directory,
+#[tracing::instrument(skip(user))]
+pub async fn versions(
+    Path(id): Path<Uuid>,
+    user: Option<Extension<User>>,
+) -> ApiResult<Json<Vec<SandboxVersion>>> {
+    if !user.is_some_and(|u| u.admin) {
+        return Err(ApiError::unauthorized());
+    };
+
+    common::db_query_as!(
+        SandboxVersion,
+        fetch_all,
+        r#"
+        SELECT v.version, v.directory_id, v.created_at, v.extracted_at, v.failed_at,
+            v.directory_id = s.directory_id AS current
+        FROM sandbox_versions v
+        JOIN sandbox s ON s.id = v.page_id
+        WHERE v.page_id = $1
+        ORDER BY v.version DESC
+        "#,
+        id
+    )
+    .map(Json)
+    .map_err(Into::into)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RollbackQuery {
+    version: i32,
+}
+
+/// Serves an earlier version again, it stays the newest one kept until the next upload.
+///
+/// Uploads still being extracted do not go live over it, they can be rolled forward to.
+#[tracing::instrument(skip(user))]
+pub async fn rollback(
+    Path(id): Path<Uuid>,
+    Query(q): Query<RollbackQuery>,
+    user: Option<Extension<User>>,
+) -> ApiResult<Json<SandboxPage>> {
+    if !user.is_some_and(|u| u.admin) {
+        return Err(ApiError::unauthorized());
+    };
+
+    common::db_query_as!(
+        SandboxPage,
+        fetch_optional,
+        r#"
+        UPDATE sandbox s SET directory_id = v.directory_id, updated_at = NOW(), rolled_back_at = NOW()
+        FROM sandbox_versions v
+        WHERE s.id = $1 AND v.page_id = s.id AND v.version = $2 AND v.extracted_at IS NOT NULL
+        RETURNING s.*
+        "#,
+        id,
+        q.version,
+    )?
+    .map(Json)
+    .ok_or(ApiError::Message(
+        StatusCode::NOT_FOUND,
+        format!("There is no extracted version {} of this page.", q.version),
+    ))
+}
+
+/// Replaces the header overrides of a page, see [`headers::defaults`] for what they override.
+#[tracing::instrument(skip(user))]
+pub async fn set_headers(
+    Path(id): Path<Uuid>,
+    user: Option<Extension<User>>,
+    Json(overrides): Json<HashMap<String, String>>,
+) -> ApiResult<Json<SandboxPage>> {
+    if !user.is_some_and(|u| u.admin) {
+        return Err(ApiError::unauthorized());
+    };
+
+    headers::parse(&overrides)?;
+
+    common::db_query_as!(
+        SandboxPage,
+        fetch_optional,
+        "UPDATE sandbox SET headers = $2 WHERE id = $1 RETURNING *",
+        id,
+        sqlx::types::Json(&overrides),
+    )?
+    .map(Json)
+    .ok_or(ApiError::not_found())
+}
+
+/// Replaces what the gallery shows about a page and whether it is listed at all.
+#[tracing::instrument(skip(user))]
+pub async fn set_meta(
+    Path(id): Path<Uuid>,
+    user: Option<Extension<User>>,
+    Json(meta): Json<SandboxPageMeta>,
+) -> ApiResult<Json<SandboxPage>> {
+    if !user.is_some_and(|u| u.admin) {
+        return Err(ApiError::unauthorized());
+    };
+
+    let thumbnail = meta
+        .thumbnail
+        .as_deref()
+        .map(|path| path.trim().trim_start_matches('/'))
+        .filter(|path| !path.is_empty());
+    if let Some(Err(e)) = thumbnail.map(archive::entry_path) {
+        return Err(ApiError::Message(
+            StatusCode::BAD_REQUEST,
+            format!("thumbnail: {e}"),
+        ));
+    }
+
+    let tags = meta
+        .tags
+        .iter()
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect::<Vec<_>>();
+
+    common::db_query_as!(
+        SandboxPage,
+        fetch_optional,
+        r#"
+        UPDATE sandbox SET
+            title = $2, description = $3, tags = $4, thumbnail = $5, visibility = $6,
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+        id,
+        meta.title.trim(),
+        meta.description.trim(),
+        &tags,
+        thumbnail,
+        meta.visibility,
+    )?
+    .map(Json)
+    .ok_or(ApiError::not_found())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GalleryQuery {
+    tag: Option<String>,
+}
+
+/// The public pages, most recently changed first.
+#[tracing::instrument]
+pub async fn gallery(Query(q): Query<GalleryQuery>) -> ApiResult<Json<Vec<GalleryPage>>> {
+    let pages = common::db_query_as!(
+        SandboxPage,
+        fetch_all,
+        r#"
+        SELECT s.* FROM sandbox s
+        JOIN directories d ON d.id = s.directory_id
+        WHERE s.visibility = 'public' AND d.deleted_at IS NULL
+            AND ($1::TEXT IS NULL OR $1 = ANY(s.tags))
+        ORDER BY COALESCE(s.updated_at, s.created_at) DESC
+        "#,
+        q.tag.map(|tag| tag.trim().to_lowercase()),
+    )?;
+
+    let sandbox_url = Apps::SandBox.url();
+
+    Ok(Json(
+        pages
+            .into_iter()
+            .map(|page| GalleryPage {
+                url: format!("{sandbox_url}/{}", page.slug),
+                thumbnail_url: page
+                    .thumbnail
+                    .as_ref()
+                    .map(|path| format!("{sandbox_url}/{}/{path}", page.slug)),
+                embed: sandbox_embed(&page.slug, &page.title),
+                markdown: format!("```sandbox\n{}\n```", page.slug),
+                slug: page.slug,
+                title: page.title,
+                description: page.description,
+                tags: page.tags,
+                created_at: page.created_at,
+                updated_at: page.updated_at,
+            })
+            .collect(),
+    ))
+}
+
+/// The page under a slug as long as it is not private and the version it serves
+/// is not in the trash.
+async fn find_page(slug: &str) -> ApiResult<SandboxPage> {
+    common::db_query_as!(
+        SandboxPage,
         fetch_one,
-        "SELECT d.* FROM directories d JOIN sandbox s ON d.id = s.directory_id WHERE s.slug = $1",
+        r#"
+        SELECT s.* FROM sandbox s
+        JOIN directories d ON d.id = s.directory_id
+        WHERE s.slug = $1 AND s.visibility <> 'private' AND d.deleted_at IS NULL
+        "#,
         slug
     )
-    .map_err(|_| ApiError::not_found())?;
+    .map_err(|_| ApiError::not_found())
+}
 
-    let contents = files::get_directory_contents(Some(dir.id)).await?;
+/// The config of the version being served, as long as there is something to serve.
+async fn find_config(directory_id: i32) -> ApiResult<PageConfig> {
+    let version = common::db_query_as!(
+        (sqlx::types::Json<PageConfig>, bool, bool),
+        fetch_optional,
+        r#"
+        SELECT config, extracted_at IS NOT NULL, failed_at IS NOT NULL
+        FROM sandbox_versions WHERE directory_id = $1
+        "#,
+        directory_id
+    )?;
 
-    let Some(index) = contents.files.iter().find(|f| f.file_name == "index.html") else {
-        return Err(ApiError::Message(
+    match version {
+        // only the first version of a page is served before it was extracted
+        Some((_, _, true)) => Err(ApiError::Message(
             StatusCode::INTERNAL_SERVER_ERROR,
-            "index.html does not exist.".to_string(),
-        ));
+            "Deploying this page failed.".into(),
+        )),
+        Some((_, false, _)) => Err(ApiError::Message(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "This page is still being deployed.".into(),
+        )),
+        Some((config, true, _)) => Ok(config.0),
+        None => Ok(PageConfig::default()),
+    }
+}
+
+/// A file of the version being served, by its path inside the page.
+async fn find_file(directory_id: i32, path: &str) -> ApiResult<Option<File>> {
+    common::db_query_as!(
+        File,
+        fetch_optional,
+        r#"
+        SELECT f.* FROM files f
+        JOIN directories d ON d.id = $1
+        WHERE f.file_path = d.dir_path || '/' || $2 AND f.deleted_at IS NULL
+        "#,
+        directory_id,
+        path
+    )
+    .map_err(Into::into)
+}
+
+async fn serve(
+    page: &SandboxPage,
+    config: &PageConfig,
+    path: &str,
+    file: &File,
+    status: StatusCode,
+    req_headers: &HeaderMap,
+) -> ApiResult<Response> {
+    let mut response = if status == StatusCode::OK {
+        files::download::serve(file, req_headers).await?
+    } else {
+        // neither ranges nor revalidation make sense for an error page
+        let mut negotiate = HeaderMap::new();
+        if let Some(accept) = req_headers.get(header::ACCEPT_ENCODING) {
+            negotiate.insert(header::ACCEPT_ENCODING, accept.clone());
+        }
+
+        let mut response = files::download::serve(file, &negotiate).await?;
+        *response.status_mut() = status;
+        response
     };
 
-    let file_path: PathBuf = [DIRECTORY, &index.id.to_string()].iter().collect();
-    if let Ok(bytes) = tokio::fs::read(file_path).await {
-        return Ok(([(header::CONTENT_TYPE, &index.mime_type)], bytes).into_response());
+    let cache_control = config.cache_control(path).or_else(|| {
+        files::download::hashed(&file.file_name).then_some(files::download::IMMUTABLE_PUBLIC)
+    });
+    match cache_control.and_then(|value| HeaderValue::from_str(value).ok()) {
+        Some(value) => {
+            response.headers_mut().insert(header::CACHE_CONTROL, value);
+        }
+        None => {
+            response.headers_mut().remove(header::CACHE_CONTROL);
+        }
     }
 
-    Err(ApiError::not_found())
+    headers::apply(&mut response, &page.headers);
+
+    Ok(response)
 }
 
-#[tracing::instrument]
+pub async fn view(Path(slug): Path<String>, req_headers: HeaderMap) -> ApiResult<Response> {
+    let page = find_page(&slug).await?;
+    let config = find_config(page.directory_id).await?;
+
+    let Some(entry) = find_file(page.directory_id, &config.entry).await? else {
+        return Err(ApiError::Message(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("{} does not exist.", config.entry),
+        ));
+    };
+
+    serve(
+        &page,
+        &config,
+        &config.entry,
+        &entry,
+        StatusCode::OK,
+        &req_headers,
+    )
+    .await
+}
+
+#[tracing::instrument(skip(req_headers))]
 pub async fn view_static(
     Path((slug, file_path)): Path<(String, String)>,
-) -> ApiResult<impl IntoResponse> {
-    let file = common::db_query_as!(
-        File,
-        fetch_one,
-        "SELECT * FROM files WHERE file_path = $1",
-        format!("{SANDBOX_DIR}/{slug}/{file_path}")
-    )
-    .map_err(|_| ApiError::not_found())?;
+    req_headers: HeaderMap,
+) -> ApiResult<Response> {
+    let page = find_page(&slug).await?;
+    let config = find_config(page.directory_id).await?;
 
-    let file_path: PathBuf = [DIRECTORY, &file.id.to_string()].iter().collect();
+    let file = find_file(page.directory_id, &file_path).await?;
+    if let Some(file) = file {
+        return serve(
+            &page,
+            &config,
+            &file_path,
+            &file,
+            StatusCode::OK,
+            &req_headers,
+        )
+        .await;
+    }
 
-    tokio::fs::read(file_path)
-        .await
-        .map(|bytes| ([(header::CONTENT_TYPE, file.mime_type)], bytes))
-        .map_err(|_| ApiError::not_found())
+    // a missing script or image should not turn into the page
+    let is_route = !file_path
+        .rsplit('/')
+        .next()
+        .is_some_and(|name| name.contains('.'));
+
+    let (path, status) = if config.spa_fallback && is_route {
+        (config.entry.as_str(), StatusCode::OK)
+    } else if let Some(not_found) = &config.not_found {
+        (not_found.as_str(), StatusCode::NOT_FOUND)
+    } else {
+        return Err(ApiError::not_found());
+    };
+
+    let file = find_file(page.directory_id, path)
+        .await?
+        .ok_or(ApiError::not_found())?;
+
+    serve(&page, &config, path, &file, status, &req_headers).await
 }
@@ -44,6 +44,7 @@ pub fn App() -> impl IntoView {
                 <Route path=path!("/") view=HomePage />
                 <Route path=path!("/resume") view=DownloadCVPage />
                 <Route path=path!("/privacy-policy") view=PrivacyPolicy />
+                <Route path=path!("/projects/:slug") view=ProjectPage />
             </FlatRoutes>
         </Router>
 
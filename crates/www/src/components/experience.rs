@@ -1,27 +1,9 @@
+use common::portfolio::get_experience;
 use leptos::prelude::*;
 
 #[component]
 pub fn Experience() -> impl IntoView {
-    let jobs = [
-        (
-            "https://www.proxmox.com",
-            "Rust Software Developer",
-            "Proxmox Software Solutions",
-            "2025 - present",
-        ),
-        (
-            "https://at.linkedin.com/in/ronald-macek-1b8a398b",
-            "Software Developer",
-            "ICOTEC GmbH",
-            "2024 - 2025",
-        ),
-        (
-            "https://www.cookis.at",
-            "Intern Software Developer",
-            "Cookis GmbH",
-            "2023 - 2024",
-        ),
-    ];
+    let jobs = Resource::new(|| (), |_| get_experience());
 
     view! {
         <div class="grid lg:grid-cols-9 lg:grid-flow-col gap-x-12 lg:gap-y-0 fade-in w-full">
@@ -32,30 +14,35 @@ pub fn Experience() -> impl IntoView {
 
             </div>
             <div class="lg:col-span-5 min-w-full text-xl lg:text-3xl leading-largep text-nf-white font-[400]">
-                {move || {
-                    jobs.iter()
-                        .map(|j| {
-                            view! {
-                                <a href=j.0 target="_blank">
-                                    <div class="experience experience-cta">
-                                        <span class="experience-cta-border"></span>
-                                        <span class="experience-cta-ripple">
-                                            <span></span>
-                                        </span>
-                                        <span class="experience-cta-title">
-                                            <span data-text=j.1 class="justify-between flex-row w-full">
-                                                {j.2}
-                                                <small class="font-montserrat text-md text-nf-color font-[400]">
-                                                    {j.3}
-                                                </small>
+                <Transition>
+                    {move || {
+                        jobs.get()
+                            .and_then(Result::ok)
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|job| {
+                                view! {
+                                    <a href=job.url target="_blank">
+                                        <div class="experience experience-cta">
+                                            <span class="experience-cta-border"></span>
+                                            <span class="experience-cta-ripple">
+                                                <span></span>
                                             </span>
-                                        </span>
-                                    </div>
-                                </a>
-                            }
-                        })
-                        .collect_view()
-                }}
+                                            <span class="experience-cta-title">
+                                                <span data-text=job.title class="justify-between flex-row w-full">
+                                                    {job.company}
+                                                    <small class="font-montserrat text-md text-nf-color font-[400]">
+                                                        {job.period}
+                                                    </small>
+                                                </span>
+                                            </span>
+                                        </div>
+                                    </a>
+                                }
+                            })
+                            .collect_view()
+                    }}
+                </Transition>
             </div>
         </div>
     }
@@ -1,39 +1,22 @@
+use common::portfolio::get_skills;
 use leptos::prelude::*;
 use leptos_use::use_interval_fn;
 use rand::random;
 
 #[component]
 pub fn Skills() -> impl IntoView {
-    let technologies = [
-        "Rust",
-        "Axum",
-        "Leptos",
-        "Bevy",
-        "PostgreSQL",
-        "Java",
-        "Spring",
-        "Vue",
-        "Angular",
-        "PHP",
-        "Javascript",
-        "C",
-        "C++",
-        "OpenGL",
-        "Vulkan",
-        "Linux",
-        "MySQL",
-        "C#",
-        ".NET",
-        "Git",
-        "CI/CD",
-        "Docker",
-    ];
+    let technologies = Resource::new(|| (), |_| get_skills());
 
-    let (highlighted, write_highlighted) = signal(vec![false; technologies.len()]);
+    let (highlighted, write_highlighted) = signal(Vec::<bool>::new());
 
     let _ = use_interval_fn(
         move || {
-            let new_highlighted = (0..highlighted.get_untracked().len())
+            let count = technologies
+                .get_untracked()
+                .and_then(Result::ok)
+                .map_or(0, |technologies| technologies.len());
+
+            let new_highlighted = (0..count)
                 .map(|index| {
                     if index % 2 == 0 {
                         random::<bool>()
@@ -57,21 +40,27 @@ pub fn Skills() -> impl IntoView {
 
             </div>
             <div class="lg:col-span-5 py-5 lg:py-10 border-solid border-t border-b border-nf-white flex flex-wrap gap-x-4 gap-y-2 lg:gap-x-10 lg:gap-y-4 min-w-full text-xl lg:text-5xl leading-p lg:leading-largep font-[400]">
-                {move || {
-                    technologies
-                        .iter()
-                        .enumerate()
-                        .map(|(index, tech)| {
-                            let is_highlighted = highlighted.get()[index];
-                            let class = if is_highlighted {
-                                "text-nf-color drop-shadow-[0_0_5px_#047857] drop-shadow-[0_0_15px_#047857] drop-shadow-[0_0_30px_#047857]"
-                            } else {
-                                "text-nf-white"
-                            };
-                            view! { <span class=move || class>{tech.to_string()}</span> }
-                        })
-                        .collect::<Vec<_>>()
-                }}
+                <Transition>
+                    {move || {
+                        technologies
+                            .get()
+                            .and_then(Result::ok)
+                            .unwrap_or_default()
+                            .into_iter()
+                            .enumerate()
+                            .map(|(index, tech)| {
+                                let class = move || {
+                                    if highlighted.get().get(index).copied().unwrap_or(false) {
+                                        "text-nf-color drop-shadow-[0_0_5px_#047857] drop-shadow-[0_0_15px_#047857] drop-shadow-[0_0_30px_#047857]"
+                                    } else {
+                                        "text-nf-white"
+                                    }
+                                };
+                                view! { <span class=class>{tech.name}</span> }
+                            })
+                            .collect_view()
+                    }}
+                </Transition>
 
             </div>
         </div>
@@ -0,0 +1,279 @@
+//! The resume, compiled from the typst sources in `cv/` once for every language.
+//!
+//! Every build is kept in the files store under [`CV_DIR`], one directory per language,
+//! and www serves the newest file of it. Older builds can be looked at or restored
+//! in the file browser. Until the first build is done, or if typst is missing on the host,
+//! the PDFs bundled in `public/assets` are served instead.
+//!
+//! Building needs the typst binary ([`typst`]) and network access for the packages the
+//! sources import. A build is queued on startup whenever the sources ([`sources`]) changed.
+
+use std::{io, path::PathBuf};
+
+use axum::{
+    extract::Path,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use common::{
+    api::{ApiError, ApiResult},
+    jobs::{self, Job},
+    models::{Directory, FILE_ROOT, File},
+};
+
+pub static CV_DIR: &str = "cv";
+
+/// The languages there are `cv/modules_{language}` for.
+pub const LANGUAGES: [&str; 2] = ["en", "de"];
+
+/// Where `cv.typ` and its modules are, `cv` in the working directory by default.
+fn sources() -> PathBuf {
+    std::env::var("CV_SOURCES")
+        .unwrap_or("cv".to_string())
+        .into()
+}
+
+/// The typst binary, looked up in the `PATH` by default.
+fn typst() -> String {
+    std::env::var("TYPST").unwrap_or("typst".to_string())
+}
+
+/// Compiles the resume for all [`LANGUAGES`] and stores them as the newest builds.
+///
+/// A repeated attempt builds all of them again, which only leaves one more version around.
+/// Also queued by kind from the blog admin page, so it has to stay without fields.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BuildCvJob {}
+
+impl Job for BuildCvJob {
+    const KIND: &'static str = jobs::kinds::BUILD_CV;
+
+    async fn run(self) -> Result<(), String> {
+        let built_at = Utc::now();
+        let hash = sources_hash().await?;
+
+        for language in LANGUAGES {
+            let pdf = compile(language).await?;
+            store(language, &pdf, built_at)
+                .await
+                .map_err(|e| format!("Could not store the {language} resume: {e}"))?;
+        }
+
+        common::db_query!(
+            execute,
+            r#"
+            INSERT INTO cv_builds (sources_hash) VALUES ($1)
+            ON CONFLICT (sources_hash) DO UPDATE SET built_at = NOW()
+            "#,
+            hash
+        )
+        .map_err(|e| format!("Could not record the resume build: {e}"))?;
+
+        Ok(())
+    }
+}
+
+/// Queues a [`BuildCvJob`] if the resume was never built from the current sources, like on
+/// a fresh install or after they were edited.
+pub async fn build_if_changed() {
+    let hash = match sources_hash().await {
+        Ok(hash) => hash,
+        Err(e) => {
+            tracing::error!("Error while hashing the resume sources: {e}");
+            return;
+        }
+    };
+
+    let built = common::db_query_scalar!(
+        bool,
+        fetch_one,
+        "SELECT EXISTS(SELECT 1 FROM cv_builds WHERE sources_hash = $1)",
+        hash
+    );
+
+    match built {
+        Ok(false) => {
+            if let Err(e) = jobs::enqueue_once(&BuildCvJob {}).await {
+                tracing::error!("Error while queueing the resume build: {e:?}");
+            }
+        }
+        Ok(true) => {}
+        Err(e) => tracing::error!("Error while looking for the resume build: {e:?}"),
+    }
+}
+
+/// A hash over the paths and contents of all files in [`sources`].
+async fn sources_hash() -> Result<String, String> {
+    fn visit(dir: &std::path::Path, root: &std::path::Path, hasher: &mut Sha256) -> io::Result<()> {
+        let mut entries = std::fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let path = entry.path();
+
+            if entry.file_type()?.is_dir() {
+                visit(&path, root, hasher)?;
+                continue;
+            }
+
+            let content = std::fs::read(&path)?;
+            let name = path.strip_prefix(root).unwrap_or(&path).to_string_lossy();
+
+            // lengths first, so moving bytes between a name and a content changes the hash
+            hasher.update((name.len() as u64).to_le_bytes());
+            hasher.update(name.as_bytes());
+            hasher.update((content.len() as u64).to_le_bytes());
+            hasher.update(&content);
+        }
+
+        Ok(())
+    }
+
+    let root = sources();
+
+    tokio::task::spawn_blocking(move || {
+        let mut hasher = Sha256::new();
+        visit(&root, &root, &mut hasher)
+            .map(|_| hex::encode(hasher.finalize()))
+            .map_err(|e| format!("Could not read {}: {e}", root.display()))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+async fn compile(language: &str) -> Result<Vec<u8>, String> {
+    let output = std::env::temp_dir().join(format!(
+        "cv-{language}-{}.pdf",
+        Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    ));
+
+    let result = tokio::process::Command::new(typst())
+        .arg("compile")
+        .args(["--input", &format!("language={language}")])
+        .arg(sources().join("cv.typ"))
+        .arg(&output)
+        .output()
+        .await
+        .map_err(|e| format!("Could not run typst: {e}"))?;
+
+    if !result.status.success() {
+        return Err(format!(
+            "typst failed for {language}: {}",
+            String::from_utf8_lossy(&result.stderr).trim()
+        ));
+    }
+
+    let pdf = tokio::fs::read(&output)
+        .await
+        .map_err(|e| format!("Could not read {}: {e}", output.display()));
+    let _ = tokio::fs::remove_file(&output).await;
+
+    pdf
+}
+
+async fn store(language: &str, pdf: &[u8], built_at: DateTime<Utc>) -> ApiResult<File> {
+    let directory = directory(&[CV_DIR, language]).await?;
+
+    let (size, sha256) = files::blob::put(pdf)
+        .await
+        .map_err(|e| ApiError::Message(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    let file_name = format!(
+        "CV_{}_{}.pdf",
+        language.to_uppercase(),
+        built_at.format("%Y%m%d-%H%M%S")
+    );
+
+    let file = common::db_query_as!(
+        File,
+        fetch_one,
+        r#"
+        INSERT INTO files (directory_id, file_name, file_path, mime_type, uploaded_at, size, sha256)
+        VALUES ($1, $2, $3, 'application/pdf', $4, $5, $6)
+        RETURNING *
+        "#,
+        directory.id,
+        &file_name,
+        format!("{}/{file_name}", directory.dir_path),
+        built_at,
+        size,
+        sha256,
+    )?;
+
+    files::encoding::queue(&file).await;
+
+    Ok(file)
+}
+
+/// Finds the directory below the root of the files store, creating what is missing.
+async fn directory(names: &[&str]) -> ApiResult<Directory> {
+    let mut parent: Option<Directory> = None;
+
+    for name in names {
+        let path = match &parent {
+            Some(parent) => format!("{}/{name}", parent.dir_path),
+            None => format!("/{FILE_ROOT}/{name}"),
+        };
+
+        let existing = common::db_query_as!(
+            Directory,
+            fetch_optional,
+            "SELECT * FROM directories WHERE dir_path = $1 AND deleted_at IS NULL",
+            &path
+        )?;
+
+        let directory = match existing {
+            Some(directory) => directory,
+            None => common::db_query_as!(
+                Directory,
+                fetch_one,
+                r#"
+                INSERT INTO directories (parent_id, dir_name, dir_path)
+                VALUES ($1, $2, $3)
+                RETURNING *
+                "#,
+                parent.as_ref().map(|parent| parent.id),
+                name,
+                &path,
+            )
+            .map_err(|e| files::conflict(e, &path))?,
+        };
+
+        parent = Some(directory);
+    }
+
+    parent.ok_or_else(ApiError::not_found)
+}
+
+/// The newest build of the resume in a language, or the bundled one if there is none.
+#[tracing::instrument(skip(headers))]
+pub async fn latest(Path(language): Path<String>, headers: HeaderMap) -> ApiResult<Response> {
+    if !LANGUAGES.contains(&language.as_str()) {
+        return Err(ApiError::not_found());
+    }
+
+    let file = common::db_query_as!(
+        File,
+        fetch_optional,
+        r#"
+        SELECT f.* FROM files f
+        JOIN directories d ON d.id = f.directory_id
+        WHERE d.dir_path = $1 AND d.deleted_at IS NULL AND f.deleted_at IS NULL
+        ORDER BY f.uploaded_at DESC
+        LIMIT 1
+        "#,
+        format!("/{FILE_ROOT}/{CV_DIR}/{language}")
+    )?;
+
+    match file {
+        Some(file) => files::download::serve(&file, &headers).await,
+        None => Ok(
+            Redirect::temporary(&format!("/assets/CV_{}.pdf", language.to_uppercase()))
+                .into_response(),
+        ),
+    }
+}
@@ -1,8 +1,23 @@
+#[cfg(feature = "back")]
+use axum::{Router, routing::get};
+
 pub mod app;
 pub mod components;
+#[cfg(feature = "back")]
+pub mod cv;
 pub mod pages;
 pub mod sections;
 
+/// The newest resume built by [`cv::BuildCvJob`], served next to the pages.
+#[cfg(feature = "back")]
+pub fn router() -> Router {
+    use common::trace::TraceExt;
+
+    Router::new()
+        .route("/cv/{language}", get(cv::latest))
+        .with_tracing()
+}
+
 pub(crate) mod utils {
     pub fn map_y_to_value(y: f64, y_visible_coord: f64) -> f64 {
         let start_y = y_visible_coord;
@@ -63,7 +63,7 @@ pub fn DownloadCVPage() -> impl IntoView {
                         <iframe
                             class="iframe"
                             allowfullscreen
-                            src=move || format!("/assets/CV_{}.pdf", language.get())
+                            src=move || format!("/cv/{}", language.get().to_lowercase())
                         ></iframe>
                     </div>
 
@@ -92,7 +92,7 @@ pub fn DownloadCVPage() -> impl IntoView {
                         <button class="button button-cta">
                             <a
                                 target="_blank"
-                                href=move || format!("/assets/CV_{}.pdf", language.get())
+                                href=move || format!("/cv/{}", language.get().to_lowercase())
                                 download=move || format!("CV_{}.pdf", language.get())
                             >
                                 <span class="button-cta-border"></span>
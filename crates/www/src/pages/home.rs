@@ -14,6 +14,7 @@ pub fn HomePage() -> impl IntoView {
         <main class="flex flex-col gap-y-20 md:gap-y-28 lg:gap-y-64 mt-12 md:mt-20 xl:mt-28">
             <Hero />
             <About />
+            <Projects />
             <Contact />
             <Info />
         </main>
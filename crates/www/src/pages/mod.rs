@@ -2,5 +2,6 @@ pub mod download_cv;
 pub mod home;
 pub mod not_found;
 pub mod privacy_policy;
+pub mod project;
 
-pub use {download_cv::*, home::*, not_found::*, privacy_policy::*};
+pub use {download_cv::*, home::*, not_found::*, privacy_policy::*, project::*};
@@ -0,0 +1,131 @@
+use leptos::prelude::*;
+use leptos::svg::Svg;
+use leptos_meta::*;
+use leptos_router::hooks::use_params_map;
+use leptos_use::use_element_hover;
+
+use crate::{components::*, pages::NotFound};
+use common::{Apps, models::ProjectDetails, portfolio::get_project};
+
+#[component]
+pub fn ProjectPage() -> impl IntoView {
+    let params = use_params_map();
+    let slug = move || params.with(|params| params.get("slug").unwrap_or_default());
+
+    let project = Resource::new(slug, get_project);
+
+    view! {
+        <Transition>
+            {move || {
+                project
+                    .get()
+                    .map(|project| match project {
+                        Ok(details) => view! { <ProjectDetailsView details /> }.into_any(),
+                        Err(_) => view! { <NotFound /> }.into_any(),
+                    })
+            }}
+        </Transition>
+    }
+}
+
+#[component]
+fn ProjectDetailsView(details: ProjectDetails) -> impl IntoView {
+    let close_icon = NodeRef::<Svg>::new();
+    let is_hovered = use_element_hover(close_icon);
+
+    let ProjectDetails {
+        project,
+        demo_url,
+        posts,
+    } = details;
+
+    let name = project.name;
+    let links = [
+        project.url.map(|url| ("website", url)),
+        project.repository.map(|url| ("repository", url)),
+    ];
+
+    view! {
+        <Title text=format!("Nicolas Frey - {name}") />
+        <Meta name="description" content=project.description.clone() />
+
+        <Close el=close_icon />
+
+        <main class={
+            let base_class = "grid gap-20 md:gap-28 lg:gap-64 mt-10 xl:mt-28 delay-75 duration-1000 ease-out";
+            move || {
+                if is_hovered.get() {
+                    format!("{} {}", base_class, "usecase-in")
+                } else {
+                    format!("{} {}", base_class, "usecase-out")
+                }
+            }
+        }>
+            <Layout id="project" aria_label="Project" class_name="flex-col gap-10 mb-10 xl:mb-28">
+                <h1 class="text-5xl xs:text-6xl sm:text-7xl lg:text-8xl text-nf-white font-bold uppercase leading-smallheading sm:leading-mediumheading tracking-smallheading sm:tracking-heading">
+                    {name.clone()}
+                </h1>
+                <div class="flex flex-wrap gap-x-4 gap-y-2 text-md lg:text-lg text-nf-color uppercase">
+                    {project
+                        .tags
+                        .into_iter()
+                        .map(|tag| view! { <span>{tag}</span> })
+                        .collect_view()}
+                </div>
+                <p class="font-montserrat text-xl lg:text-3xl leading-p lg:leading-largep text-nf-white whitespace-pre-line">
+                    {project.description}
+                </p>
+                <div class="flex flex-wrap gap-x-10 gap-y-4 text-xl lg:text-2xl text-nf-white font-montserrat">
+                    {links
+                        .into_iter()
+                        .flatten()
+                        .map(|(label, url)| {
+                            view! {
+                                <a href=url target="_blank" class="underline">
+                                    {label}
+                                </a>
+                            }
+                        })
+                        .collect_view()}
+                </div>
+                {demo_url
+                    .map(|url| {
+                        view! {
+                            <iframe
+                                src=url
+                                title=name
+                                allow="fullscreen"
+                                class="w-full aspect-video border-0 rounded-lg bg-nf-white"
+                            ></iframe>
+                        }
+                    })}
+                {(!posts.is_empty())
+                    .then(|| {
+                        view! {
+                            <div class="flex flex-col gap-4">
+                                <div class="text-md lg:text-lg leading-about text-nf-white uppercase">
+                                    <span class="uppercase">"Related posts —"</span>
+                                </div>
+                                {posts
+                                    .into_iter()
+                                    .map(|post| {
+                                        view! {
+                                            <a
+                                                href=format!("{}/posts/{}", Apps::Blog.url(), post.slug)
+                                                class="flex flex-col text-nf-white"
+                                            >
+                                                <span class="text-xl lg:text-3xl font-bold">{post.title}</span>
+                                                <small class="font-montserrat text-md text-nf-color">
+                                                    {post.description}
+                                                </small>
+                                            </a>
+                                        }
+                                    })
+                                    .collect_view()}
+                            </div>
+                        }
+                    })}
+            </Layout>
+        </main>
+    }
+}
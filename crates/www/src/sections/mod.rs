@@ -2,5 +2,6 @@ pub mod about;
 pub mod contact;
 pub mod hero;
 pub mod info;
+pub mod projects;
 
-pub use {about::*, contact::*, hero::*, info::*};
+pub use {about::*, contact::*, hero::*, info::*, projects::*};
@@ -0,0 +1,50 @@
+use crate::components::*;
+use common::portfolio::get_projects;
+
+use leptos::prelude::*;
+
+#[component]
+pub fn Projects() -> impl IntoView {
+    let projects = Resource::new(|| (), |_| get_projects());
+
+    let projects = move || projects.get().and_then(Result::ok).unwrap_or_default();
+
+    view! {
+        <Transition>
+            <Show when=move || !projects().is_empty()>
+                <Layout id="projects" aria_label="Projects" class_name="flex-col">
+                    <div class="grid lg:grid-cols-9 lg:grid-flow-col gap-x-12 lg:gap-y-0 fade-in w-full">
+                        <div class="lg:col-span-2">
+                            <div class="text-md lg:text-lg leading-about text-nf-white uppercase">
+                                <span class="uppercase">"Projects —"</span>
+                            </div>
+                        </div>
+                        <div class="lg:col-span-5 min-w-full text-xl lg:text-3xl leading-largep text-nf-white font-[400]">
+                            <For each=projects key=|project| project.id let:project>
+                                <a href=format!("/projects/{}", project.slug)>
+                                    <div class="experience experience-cta">
+                                        <span class="experience-cta-border"></span>
+                                        <span class="experience-cta-ripple">
+                                            <span></span>
+                                        </span>
+                                        <span class="experience-cta-title">
+                                            <span
+                                                data-text=project.description.clone()
+                                                class="justify-between flex-row w-full"
+                                            >
+                                                {project.name}
+                                                <small class="font-montserrat text-md text-nf-color font-[400]">
+                                                    {project.tags.join(" · ")}
+                                                </small>
+                                            </span>
+                                        </span>
+                                    </div>
+                                </a>
+                            </For>
+                        </div>
+                    </div>
+                </Layout>
+            </Show>
+        </Transition>
+    }
+}
@@ -1,6 +1,6 @@
 // Imports
 #import "@preview/brilliant-cv:2.0.6": cv
-#let metadata = toml("./metadata.toml")
+#import "./metadata.typ": metadata
 #let importModules(modules, lang: metadata.language) = {
   for module in modules {
     include {
@@ -0,0 +1,7 @@
+// metadata.toml with the language given by `typst compile --input language=de`,
+// falling back to the one in the file
+#let metadata = {
+  let metadata = toml("./metadata.toml")
+  metadata.insert("language", sys.inputs.at("language", default: metadata.language))
+  metadata
+}
@@ -1,6 +1,6 @@
 // Imports
 #import "@preview/brilliant-cv:2.0.6": cvSection, cvEntry, hBar
-#let metadata = toml("../metadata.toml")
+#import "../metadata.typ": metadata
 #let cvSection = cvSection.with(metadata: metadata)
 #let cvEntry = cvEntry.with(metadata: metadata)
 
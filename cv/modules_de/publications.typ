@@ -1,6 +1,6 @@
 // Imports
 #import "@preview/brilliant-cv:2.0.6": cvSection, cvPublication
-#let metadata = toml("../metadata.toml")
+#import "../metadata.typ": metadata
 #let cvSection = cvSection.with(metadata: metadata)
 
 
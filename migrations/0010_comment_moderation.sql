@@ -0,0 +1,14 @@
+CREATE TYPE comment_status AS ENUM ('pending', 'approved', 'rejected', 'spam');
+
+ALTER TABLE comments ADD COLUMN status comment_status NOT NULL DEFAULT 'pending';
+ALTER TABLE comments ADD COLUMN spam_score REAL NOT NULL DEFAULT 0;
+
+-- everything posted before moderation existed stays visible
+UPDATE comments SET status = 'approved';
+
+-- token counts for the bayesian spam filter
+CREATE TABLE IF NOT EXISTS spam_tokens (
+    token TEXT PRIMARY KEY,
+    spam_count INTEGER NOT NULL DEFAULT 0,
+    ham_count INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,23 @@
+CREATE TYPE notification_kind AS ENUM ('post_comment', 'reply');
+
+-- users without a row here get the defaults
+CREATE TABLE IF NOT EXISTS notification_preferences (
+    user_id INTEGER PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    email_enabled BOOLEAN NOT NULL DEFAULT true,
+    notify_post_comments BOOLEAN NOT NULL DEFAULT true,
+    notify_replies BOOLEAN NOT NULL DEFAULT true,
+    digest BOOLEAN NOT NULL DEFAULT false,
+    webhook_url TEXT,
+    webhook_secret TEXT,
+    unsubscribe_token UUID NOT NULL UNIQUE DEFAULT gen_random_uuid()
+);
+
+CREATE TABLE IF NOT EXISTS notifications (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER REFERENCES users(id) ON DELETE CASCADE NOT NULL,
+    comment_id INTEGER REFERENCES comments(id) ON DELETE CASCADE NOT NULL,
+    kind notification_kind NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    sent_at TIMESTAMP,
+    UNIQUE (user_id, comment_id)
+);
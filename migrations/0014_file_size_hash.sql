@@ -0,0 +1,3 @@
+-- filled in while uploading, unknown for files stored before
+ALTER TABLE files ADD COLUMN size BIGINT;
+ALTER TABLE files ADD COLUMN sha256 TEXT;
@@ -0,0 +1,32 @@
+-- file contents stored once per SHA-256, shared by all files with that content
+CREATE TABLE IF NOT EXISTS blobs (
+    sha256 TEXT PRIMARY KEY,
+    size BIGINT NOT NULL,
+    ref_count INTEGER NOT NULL DEFAULT 0,
+    last_used_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- nothing is in the blob store yet, the hashes are recomputed when moving files over
+UPDATE files SET sha256 = NULL;
+
+ALTER TABLE files ADD FOREIGN KEY (sha256) REFERENCES blobs(sha256);
+CREATE INDEX IF NOT EXISTS files_sha256_idx ON files (sha256);
+
+-- keeps ref_count in line with the files pointing at a blob, including cascading deletes
+CREATE OR REPLACE FUNCTION count_blob_refs() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP IN ('UPDATE', 'DELETE') AND OLD.sha256 IS NOT NULL THEN
+        UPDATE blobs SET ref_count = ref_count - 1 WHERE sha256 = OLD.sha256;
+    END IF;
+
+    IF TG_OP IN ('INSERT', 'UPDATE') AND NEW.sha256 IS NOT NULL THEN
+        UPDATE blobs SET ref_count = ref_count + 1 WHERE sha256 = NEW.sha256;
+    END IF;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER files_blob_refs
+AFTER INSERT OR DELETE OR UPDATE OF sha256 ON files
+FOR EACH ROW EXECUTE FUNCTION count_blob_refs();
@@ -0,0 +1,7 @@
+-- blobs whose content could not be removed from the storage stay around to be retried
+ALTER TABLE blobs ADD COLUMN delete_attempts INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE blobs ADD COLUMN delete_error TEXT;
+ALTER TABLE blobs ADD COLUMN delete_attempted_at TIMESTAMP;
+
+CREATE INDEX IF NOT EXISTS directories_parent_id_idx ON directories (parent_id);
+CREATE INDEX IF NOT EXISTS files_directory_id_idx ON files (directory_id);
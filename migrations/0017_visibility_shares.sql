@@ -0,0 +1,20 @@
+-- public: reachable by path, unlisted: only by id or share link, private: admins and share links
+CREATE TYPE visibility AS ENUM ('public', 'unlisted', 'private');
+
+-- NULL inherits from the parent directory, the root is public
+ALTER TABLE directories ADD COLUMN visibility visibility;
+ALTER TABLE files ADD COLUMN visibility visibility;
+
+UPDATE directories SET visibility = 'private' WHERE dir_name = '.private';
+
+CREATE TABLE IF NOT EXISTS shares (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    file_id UUID REFERENCES files(id) ON DELETE CASCADE,
+    directory_id INTEGER REFERENCES directories(id) ON DELETE CASCADE,
+    expires_at TIMESTAMP,
+    max_downloads INTEGER,
+    downloads INTEGER NOT NULL DEFAULT 0,
+    password_hash TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CHECK ((file_id IS NULL) <> (directory_id IS NULL))
+);
@@ -0,0 +1,18 @@
+-- resized or converted images, stored as blobs derived from the blob of the original
+CREATE TABLE IF NOT EXISTS variants (
+    source_sha256 TEXT NOT NULL REFERENCES blobs(sha256) ON DELETE CASCADE,
+    -- normalized query, e.g. w=640&h=0&fit=contain&fmt=webp
+    params TEXT NOT NULL,
+    sha256 TEXT NOT NULL REFERENCES blobs(sha256),
+    mime_type TEXT NOT NULL,
+    size BIGINT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (source_sha256, params)
+);
+
+CREATE INDEX IF NOT EXISTS variants_sha256_idx ON variants (sha256);
+
+-- variants keep their blob alive like files do, and go away with their source
+CREATE TRIGGER variants_blob_refs
+AFTER INSERT OR DELETE OR UPDATE OF sha256 ON variants
+FOR EACH ROW EXECUTE FUNCTION count_blob_refs();
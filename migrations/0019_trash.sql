@@ -0,0 +1,12 @@
+-- deleting moves to the trash, everything below a deleted directory shares its deleted_at
+ALTER TABLE directories ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE files ADD COLUMN deleted_at TIMESTAMP;
+
+-- paths only have to be unique outside of the trash
+ALTER TABLE directories DROP CONSTRAINT directories_dir_path_key;
+ALTER TABLE files DROP CONSTRAINT files_file_path_key;
+CREATE UNIQUE INDEX directories_dir_path_key ON directories(dir_path) WHERE deleted_at IS NULL;
+CREATE UNIQUE INDEX files_file_path_key ON files(file_path) WHERE deleted_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS directories_deleted_at_idx ON directories(deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX IF NOT EXISTS files_deleted_at_idx ON files(deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,8 @@
+-- what may be uploaded into a directory and below, NULL inherits from the parent directory
+-- mime types can end in /* to match a whole group
+CREATE TABLE IF NOT EXISTS directory_policies (
+    directory_id INTEGER PRIMARY KEY REFERENCES directories(id) ON DELETE CASCADE,
+    allowed_mime_types TEXT[],
+    denied_mime_types TEXT[],
+    max_file_size BIGINT CHECK (max_file_size > 0)
+);
@@ -0,0 +1,44 @@
+-- every upload of a page is extracted into a directory of its own, named @<version>,
+-- below the directory of the page, the sandbox row points at the one being served
+CREATE TABLE IF NOT EXISTS sandbox_versions (
+    page_id UUID REFERENCES sandbox(id) ON DELETE CASCADE NOT NULL,
+    version INTEGER NOT NULL,
+    directory_id INTEGER REFERENCES directories(id) ON DELETE CASCADE NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    -- NULL while the upload is still being extracted
+    extracted_at TIMESTAMP,
+    PRIMARY KEY (page_id, version)
+);
+
+-- existing pages become their own first version
+INSERT INTO directories (parent_id, dir_name, dir_path)
+SELECT d.id, '@1', d.dir_path || '/@1'
+FROM sandbox s
+JOIN directories d ON d.id = s.directory_id;
+
+CREATE TEMPORARY TABLE moved_pages AS
+SELECT s.id AS page_id, d.id AS page_dir, d.dir_path AS page_path, v.id AS version_dir
+FROM sandbox s
+JOIN directories d ON d.id = s.directory_id
+JOIN directories v ON v.parent_id = d.id AND v.dir_name = '@1';
+
+UPDATE files f SET
+    file_path = m.page_path || '/@1' || substr(f.file_path, length(m.page_path) + 1),
+    directory_id = CASE WHEN f.directory_id = m.page_dir THEN m.version_dir ELSE f.directory_id END
+FROM moved_pages m
+WHERE starts_with(f.file_path, m.page_path || '/');
+
+UPDATE directories d SET
+    dir_path = m.page_path || '/@1' || substr(d.dir_path, length(m.page_path) + 1),
+    parent_id = CASE WHEN d.parent_id = m.page_dir THEN m.version_dir ELSE d.parent_id END
+FROM moved_pages m
+WHERE starts_with(d.dir_path, m.page_path || '/') AND d.id <> m.version_dir;
+
+INSERT INTO sandbox_versions (page_id, version, directory_id, extracted_at)
+SELECT page_id, 1, version_dir, NOW() FROM moved_pages;
+
+UPDATE sandbox s SET directory_id = m.version_dir
+FROM moved_pages m
+WHERE s.id = m.page_id;
+
+DROP TABLE moved_pages;
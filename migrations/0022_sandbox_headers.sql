@@ -0,0 +1,2 @@
+-- response headers replacing the defaults for a page, an empty value drops the header
+ALTER TABLE sandbox ADD COLUMN IF NOT EXISTS headers JSONB NOT NULL DEFAULT '{}'::jsonb;
@@ -0,0 +1,2 @@
+-- the sandbox.toml of a version, missing keys take their defaults
+ALTER TABLE sandbox_versions ADD COLUMN IF NOT EXISTS config JSONB NOT NULL DEFAULT '{}'::jsonb;
@@ -0,0 +1,17 @@
+-- gzip and brotli compressed content, stored as blobs derived from the blob of the original
+CREATE TABLE IF NOT EXISTS encodings (
+    source_sha256 TEXT NOT NULL REFERENCES blobs(sha256) ON DELETE CASCADE,
+    -- as sent in Content-Encoding, e.g. br
+    encoding TEXT NOT NULL,
+    sha256 TEXT NOT NULL REFERENCES blobs(sha256),
+    size BIGINT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (source_sha256, encoding)
+);
+
+CREATE INDEX IF NOT EXISTS encodings_sha256_idx ON encodings (sha256);
+
+-- like variants, they keep their blob alive and go away with their source
+CREATE TRIGGER encodings_blob_refs
+AFTER INSERT OR DELETE OR UPDATE OF sha256 ON encodings
+FOR EACH ROW EXECUTE FUNCTION count_blob_refs();
@@ -0,0 +1,20 @@
+-- what the public gallery shows about a page
+ALTER TABLE sandbox
+    ADD COLUMN IF NOT EXISTS title TEXT NOT NULL DEFAULT '',
+    ADD COLUMN IF NOT EXISTS description TEXT NOT NULL DEFAULT '',
+    ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}',
+    -- an image inside the page, relative to it
+    ADD COLUMN IF NOT EXISTS thumbnail TEXT,
+    -- public pages are listed, unlisted ones only served and private ones neither
+    ADD COLUMN IF NOT EXISTS visibility visibility NOT NULL DEFAULT 'unlisted',
+    ADD COLUMN IF NOT EXISTS created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    ADD COLUMN IF NOT EXISTS updated_at TIMESTAMP;
+
+UPDATE sandbox s SET
+    title = s.slug,
+    created_at = COALESCE(
+        (SELECT MIN(v.created_at) FROM sandbox_versions v WHERE v.page_id = s.id),
+        s.created_at
+    );
+
+CREATE INDEX IF NOT EXISTS sandbox_visibility_idx ON sandbox (visibility);
@@ -0,0 +1,60 @@
+-- what the www page shows, each ordered by position
+CREATE TABLE IF NOT EXISTS experience (
+    id SERIAL PRIMARY KEY,
+    title TEXT NOT NULL,
+    company TEXT NOT NULL,
+    url TEXT NOT NULL DEFAULT '',
+    -- free text, like 2023 - 2024
+    period TEXT NOT NULL DEFAULT '',
+    position INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS skills (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS projects (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    url TEXT,
+    position INTEGER NOT NULL DEFAULT 0
+);
+
+-- the name is also the icon, /assets/{name}.svg
+CREATE TABLE IF NOT EXISTS social_links (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    url TEXT NOT NULL,
+    position INTEGER NOT NULL DEFAULT 0
+);
+
+-- what was hard-coded before
+INSERT INTO experience (url, title, company, period, position)
+SELECT * FROM (VALUES
+    ('https://www.proxmox.com', 'Rust Software Developer', 'Proxmox Software Solutions', '2025 - present', 0),
+    ('https://at.linkedin.com/in/ronald-macek-1b8a398b', 'Software Developer', 'ICOTEC GmbH', '2024 - 2025', 1),
+    ('https://www.cookis.at', 'Intern Software Developer', 'Cookis GmbH', '2023 - 2024', 2)
+) AS seed
+WHERE NOT EXISTS (SELECT 1 FROM experience);
+
+INSERT INTO skills (name, position)
+SELECT name, position - 1 FROM unnest(ARRAY[
+    'Rust', 'Axum', 'Leptos', 'Bevy', 'PostgreSQL', 'Java', 'Spring', 'Vue', 'Angular', 'PHP',
+    'Javascript', 'C', 'C++', 'OpenGL', 'Vulkan', 'Linux', 'MySQL', 'C#', '.NET', 'Git', 'CI/CD',
+    'Docker'
+]) WITH ORDINALITY AS seed (name, position)
+WHERE NOT EXISTS (SELECT 1 FROM skills);
+
+INSERT INTO social_links (name, url, position)
+SELECT * FROM (VALUES
+    ('instagram', 'https://www.instagram.com/nic_ol_ass', 0),
+    ('bluesky', 'https://bsky.app/profile/nicolas-frey.com', 1),
+    ('youtube', 'https://www.youtube.com/@microwonk', 2),
+    ('github', 'https://www.github.com/Microwonk', 3),
+    ('itch-io', 'https://microwonk.itch.io', 4),
+    ('discord', 'https://discordapp.com/users/444924590913749002', 5)
+) AS seed
+WHERE NOT EXISTS (SELECT 1 FROM social_links);
@@ -0,0 +1,21 @@
+-- what a project page on www shows besides the name and description
+ALTER TABLE projects
+    ADD COLUMN IF NOT EXISTS slug TEXT,
+    ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN IF NOT EXISTS repository TEXT,
+    -- the live demo, renaming or deleting the page keeps or drops the link
+    ADD COLUMN IF NOT EXISTS sandbox_slug TEXT REFERENCES sandbox(slug) ON UPDATE CASCADE ON DELETE SET NULL;
+
+-- the same as slugify in common::portfolio, with the id keeping them apart
+UPDATE projects
+SET slug = trim(both '-' from regexp_replace(lower(name), '[^a-z0-9]+', '-', 'g')) || '-' || id
+WHERE slug IS NULL;
+
+ALTER TABLE projects ALTER COLUMN slug SET NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS projects_slug_idx ON projects (slug);
+
+CREATE TABLE IF NOT EXISTS project_posts (
+    project_id INTEGER REFERENCES projects(id) ON DELETE CASCADE,
+    post_id INTEGER REFERENCES posts(id) ON DELETE CASCADE,
+    PRIMARY KEY(project_id, post_id)
+);
@@ -0,0 +1,5 @@
+-- what the bayes filter learned a comment as, only admin decisions are learned
+ALTER TABLE comments ADD COLUMN IF NOT EXISTS trained_as comment_status;
+
+-- existing comments were never learned, the filter arrives together with this column,
+-- so they stay NULL and are learned once an admin decides on them
@@ -0,0 +1,5 @@
+-- queued by enqueue_once and not picked up by a worker yet, claiming a job clears it
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS once BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- at most one of those per kind, so concurrent enqueue_once calls cannot both insert
+CREATE UNIQUE INDEX IF NOT EXISTS jobs_once_idx ON jobs (kind) WHERE status = 'queued' AND once;
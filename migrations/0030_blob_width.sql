@@ -0,0 +1,2 @@
+-- pixel width of images, measured the first time a post shows one
+ALTER TABLE blobs ADD COLUMN IF NOT EXISTS width INTEGER;
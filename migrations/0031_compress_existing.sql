@@ -0,0 +1,2 @@
+-- files uploaded before they were compressed on ingest, see CompressExistingJob
+INSERT INTO jobs (kind, payload) VALUES ('files::compress_existing', '{}');
@@ -0,0 +1,5 @@
+-- the hashes of the cv sources the resume was built from, a new one means it is outdated
+CREATE TABLE IF NOT EXISTS cv_builds (
+    sources_hash TEXT PRIMARY KEY,
+    built_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,2 @@
+-- notifications are handed to delivery jobs, which retry until they are sent
+ALTER TABLE notifications RENAME COLUMN sent_at TO queued_at;
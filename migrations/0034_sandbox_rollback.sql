@@ -0,0 +1,2 @@
+-- versions uploaded before a rollback stay where they are once extracted instead of undoing it
+ALTER TABLE sandbox ADD COLUMN IF NOT EXISTS rolled_back_at TIMESTAMP;
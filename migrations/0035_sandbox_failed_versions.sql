@@ -0,0 +1,2 @@
+-- set once extracting an upload was given up, such a version is never served
+ALTER TABLE sandbox_versions ADD COLUMN IF NOT EXISTS failed_at TIMESTAMP;
@@ -8,10 +8,26 @@ pub use hydrate::*;
 pub(crate) mod ssr {
     use common::apps::*;
 
+    /// Served by the fallback of leptos, listed for apps whose own routes would catch them.
+    const SITE_PATHS: [&str; 5] = [
+        "/pkg/{*path}",
+        "/assets/{*path}",
+        "/fonts/{*path}",
+        "/resources/{*path}",
+        "/favicon.ico",
+    ];
+
     macro_rules! define_leptos_router {
         (
         $static_name:ident,
         $with_auth:expr
+    ) => {
+            define_leptos_router!($static_name, $with_auth, axum::Router::new())
+        };
+        (
+        $static_name:ident,
+        $with_auth:expr,
+        $api:expr
     ) => {{
             use axum::Router;
             use common::trace::TraceExt;
@@ -26,11 +42,19 @@ pub(crate) mod ssr {
                     let leptos_options = conf.leptos_options.clone();
                     let routes = generate_route_list(App);
 
-                    let mut router = Router::new()
-                        .leptos_routes(&leptos_options, routes, {
-                            let leptos_options = leptos_options.clone();
-                            move || shell(leptos_options.clone())
-                        })
+                    let mut router = Router::new().leptos_routes(&leptos_options, routes, {
+                        let leptos_options = leptos_options.clone();
+                        move || shell(leptos_options.clone())
+                    });
+
+                    for path in SITE_PATHS {
+                        router = router.route(
+                            path,
+                            axum::routing::get(leptos_axum::file_and_error_handler(shell)),
+                        );
+                    }
+
+                    let mut router = router
                         .with_tracing()
                         .fallback(leptos_axum::file_and_error_handler(shell));
 
@@ -38,24 +62,13 @@ pub(crate) mod ssr {
                         router = router.layer(axum::middleware::from_fn(common::auth::auth_guard));
                     }
 
-                    router.with_state(leptos_options)
+                    $api.merge(router.with_state(leptos_options))
                 })
                 .await
                 .clone()
         }};
     }
 
-    macro_rules! define_router {
-        (
-    $static_name:ident,
-    $router:expr
-    ) => {{
-            static $static_name: OnceCell<axum::Router> = OnceCell::const_new();
-
-            $static_name.get_or_init(|| async { $router }).await.clone()
-        }};
-    }
-
     pub trait SsrApps {
         fn router(&self) -> impl std::future::Future<Output = axum::Router> + Send;
         fn routers() -> impl std::future::Future<Output = Vec<(Self, axum::Router)>> + Send
@@ -79,14 +92,20 @@ pub(crate) mod ssr {
                 }
                 Apps::Www => {
                     use www::app::*;
-                    define_leptos_router!(WWW_ROUTER, false)
+                    define_leptos_router!(WWW_ROUTER, false, www::router())
                 }
                 Apps::Auth => {
                     use auth::app::*;
                     define_leptos_router!(AUTH_ROUTER, true)
                 }
-                Apps::Files => define_router!(FILES_ROUTER, files::router()),
-                Apps::SandBox => define_router!(SANDBOX_ROUTER, sandbox::router()),
+                Apps::Files => {
+                    use files::app::*;
+                    define_leptos_router!(FILES_ROUTER, true, files::router())
+                }
+                Apps::SandBox => {
+                    use sandbox::app::*;
+                    define_leptos_router!(SANDBOX_ROUTER, true, sandbox::router())
+                }
             }
         }
 
@@ -134,13 +153,12 @@ pub(crate) mod hydrate {
         fn hydrate() {
             Apps::iter()
                 .filter(|a| {
-                    a.is_leptos()
-                        && a.starts_with(
-                            leptos::prelude::window()
-                                .location()
-                                .hostname()
-                                .unwrap_or(Apps::Www.prefix().into()),
-                        )
+                    a.starts_with(
+                        leptos::prelude::window()
+                            .location()
+                            .hostname()
+                            .unwrap_or(Apps::Www.prefix().into()),
+                    )
                 })
                 .for_each(|app| leptos::mount::hydrate_body(app.app()));
         }
@@ -150,7 +168,8 @@ pub(crate) mod hydrate {
                 Apps::Blog => Box::new(|| blog::app::App().into_any()),
                 Apps::Www => Box::new(|| www::app::App().into_any()),
                 Apps::Auth => Box::new(|| auth::app::App().into_any()),
-                Apps::Files | Apps::SandBox => unreachable!(),
+                Apps::Files => Box::new(|| files::app::App().into_any()),
+                Apps::SandBox => Box::new(|| sandbox::app::App().into_any()),
             }
         }
     }
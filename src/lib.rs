@@ -1,5 +1,4 @@
 #![recursion_limit = "256"]
-#![feature(impl_trait_in_assoc_type)]
 
 pub mod apps;
 
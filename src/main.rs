@@ -1,5 +1,9 @@
 #![recursion_limit = "256"]
 
+/// how many jobs of the queue in `common::jobs` can run at the same time
+#[cfg(feature = "ssr")]
+const JOB_WORKERS: usize = 4;
+
 #[cfg(feature = "ssr")]
 #[tokio::main]
 async fn main() {
@@ -19,16 +23,59 @@ async fn main() {
         .with_max_level(tracing::Level::INFO)
         .init();
 
+    // `microweb migrate-storage <from> <to>` copies all blobs between storages, e.g. `local s3`
+    if let [_, command, from, to] = std::env::args().collect::<Vec<_>>().as_slice()
+        && command == "migrate-storage"
+    {
+        let from = files::storage::from_config(from).expect("problem with the source storage");
+        let to = files::storage::from_config(to).expect("problem with the target storage");
+
+        match files::storage::migrate(from.as_ref(), to.as_ref()).await {
+            Ok(copied) => tracing::info!("Copied {copied} blobs"),
+            Err(e) => {
+                tracing::error!("Error while migrating storage: {e:?}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    files::storage::init_storage().expect("problem during initialization of the storage");
+
     common::db::init_db()
         .await
         .expect("problem during initialization of the database");
 
+    tokio::spawn(files::trash::run_purge());
+
+    common::jobs::Workers::default()
+        .register::<files::directory::DeleteDirectoryJob>()
+        .register::<files::blob::CollectGarbageJob>()
+        .register::<files::blob::MigrateLegacyFilesJob>()
+        .register::<files::trash::PurgeTrashJob>()
+        .register::<files::encoding::CompressJob>()
+        .register::<files::encoding::CompressExistingJob>()
+        .register::<sandbox::ExtractZipJob>()
+        .register::<blog::pages::rss::RegenerateFeedJob>()
+        .register::<blog::notifications::DeliverNotificationsJob>()
+        .register::<blog::notifications::SendDigestJob>()
+        .register::<www::cv::BuildCvJob>()
+        .start(JOB_WORKERS);
+
+    www::cv::build_if_changed().await;
+    blog::notifications::schedule_digest().await;
+
+    files::blob::migrate_legacy_files().await;
+    files::blob::collect_garbage().await;
+
     let app = Router::new()
         .layer(
             CorsLayer::new()
                 .allow_credentials(true)
                 .allow_origin(
+                    // the sandbox runs uploaded code, which must not act as the user
                     Apps::iter()
+                        .filter(|app| *app != Apps::SandBox)
                         .filter_map(|app| HeaderValue::from_str(&app.url()).ok())
                         .collect::<Vec<_>>(),
                 )